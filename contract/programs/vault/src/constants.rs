@@ -1,13 +1,25 @@
-// BTC Price Feed ID from Pyth Network
-pub const BTC_FEED_ID: &str = "0xe62df6c8b4c85fe16de9a89c73c0c1e8e7b41d0a16b3b0b0e8e6e8b0e8b0e8b0";
+// Values below seed the ProtocolConfig account on `init_config`; the live values
+// are read from that account and changed with `update_config`.
 
 // Maximum age for price feeds (in seconds)
 pub const MAXIMUM_AGE: u64 = 60; // 1 minute
 
+// Widest accepted Pyth confidence interval, as conf / price in basis points
+pub const MAX_CONFIDENCE_BPS: u16 = 200; // 2%
+
 // Trading fees in basis points (1 basis point = 0.01%)
 pub const TRADING_FEE_BPS: u16 = 10; // 0.1% trading fee
 pub const CLOSING_FEE_BPS: u16 = 5;  // 0.05% closing fee
 
+// Flat fee on every withdrawal, charged on top of any early-withdrawal penalty
+pub const WITHDRAWAL_FEE_BPS: u16 = 0;
+
+// Default fee split (basis points of every fee, must sum to 10000)
+pub const FEE_SPLIT_LP_BPS: u16 = 5000;        // 50% to LP share value
+pub const FEE_SPLIT_TREASURY_BPS: u16 = 3000;  // 30% to the protocol treasury
+pub const FEE_SPLIT_INSURANCE_BPS: u16 = 1500; // 15% to the insurance fund
+pub const FEE_SPLIT_REFERRER_BPS: u16 = 500;   // 5% to the referrer, treasury if none
+
 // Minimum position sizes
 pub const MIN_POSITION_SIZE: u64 = 1000; // Minimum position size in lamports
 
@@ -20,5 +32,27 @@ pub const HEALTHY_THRESHOLD: u16 = 150;      // 1.5x collateral ratio
 pub const WARNING_THRESHOLD: u16 = 120;      // 1.2x collateral ratio
 pub const LIQUIDATION_THRESHOLD: u16 = 110;  // 1.1x collateral ratio
 
+// Share of seized collateral paid to the liquidator (in basis points)
+pub const LIQUIDATION_BOUNTY_BPS: u16 = 500; // 5% of collateral
+
+// Partial liquidation
+pub const LIQUIDATION_STEP_BPS: u16 = 2500;   // 25% of the size closed per step
+pub const LIQUIDATION_PENALTY_BPS: u16 = 250; // 2.5% of the closed size
+pub const MAX_LIQUIDATION_STEPS: u8 = 4;
+
 // Reward rates
-pub const BASE_REWARD_RATE_BPS: u16 = 10; // 0.1% per hour base reward rate
\ No newline at end of file
+pub const BASE_REWARD_RATE_BPS: u16 = 10; // 0.1% per hour base reward rate
+
+// Range (STAY_IN / BREAKOUT) positions
+pub const RANGE_POSITION_DURATION: i64 = 24 * 60 * 60; // 24 hours
+
+// Bounds enforced on ProtocolConfig updates
+pub const MAX_CONFIG_PRICE_AGE: u64 = 300;                   // 5 minutes
+pub const MAX_CONFIG_CONFIDENCE_BPS: u16 = 1000;             // 10%
+pub const MAX_CONFIG_TWAP_WINDOW: u32 = 60 * 60;             // 1 hour, also bounds Market params
+pub const MAX_CONFIG_BREAKER_WINDOW: u32 = 60 * 60;          // 1 hour, Market circuit breaker window
+pub const MAX_CONFIG_FEE_BPS: u16 = 1000;                    // 10%
+pub const MAX_CONFIG_LIQUIDATION_BOUNTY_BPS: u16 = 2000;     // 20%
+pub const MAX_CONFIG_WITHDRAWAL_PENALTY_BPS: u16 = 2000;     // 20%
+pub const MIN_CONFIG_RANGE_DURATION: i64 = 60 * 60;          // 1 hour
+pub const MAX_CONFIG_RANGE_DURATION: i64 = 30 * 24 * 60 * 60; // 30 days
@@ -17,6 +17,27 @@ pub enum ErrorCode {
     #[msg("Withdrawal not authorized")]
     UnauthorizedWithdrawal,
 
+    #[msg("Deposit amount is above maximum allowed")]
+    AmountTooLarge,
+
+    #[msg("Insufficient balance")]
+    InsufficientBalance,
+
+    #[msg("Insufficient funds in user vault")]
+    InsufficientVaultFunds,
+
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+
+    #[msg("Lock period must be between 1 and 365 days")]
+    InvalidLockPeriod,
+
+    #[msg("Signer does not own this position")]
+    InvalidPositionOwnership,
+
+    #[msg("Position is not active")]
+    PositionNotActive,
+
 //    <-----------------Position------------->
 
     #[msg("Position has already been settled")]
@@ -24,6 +45,12 @@ pub enum ErrorCode {
     
     #[msg("Position has not been settled yet")]
     PositionNotSettled,
+
+    #[msg("Position has already been claimed")]
+    PositionAlreadyClaimed,
+
+    #[msg("Position was liquidated")]
+    PositionLiquidated,
     
     #[msg("Invalid price range")]
     InvalidRange,
@@ -40,6 +67,21 @@ pub enum ErrorCode {
     #[msg("Please Verify price update!")]
     UnverifiedPriceUpdate,
 
+    #[msg("Oracle price must be positive")]
+    NonPositivePrice,
+
+    #[msg("Oracle confidence interval is too wide")]
+    PriceConfidenceTooWide,
+
+    #[msg("Secondary price message is missing or invalid")]
+    InvalidSecondaryPrice,
+
+    #[msg("Primary and secondary prices deviate too much")]
+    PriceDeviationTooLarge,
+
+    #[msg("Not enough price history for the TWAP window")]
+    InsufficientPriceHistory,
+
     //    <-----------------Pool------------->
 
     #[msg("Insufficient balance in trading pool")]
@@ -62,6 +104,140 @@ pub enum ErrorCode {
     
     #[msg("Invalid leverage")]
     InvalidLeverage,
+
+    #[msg("Invalid position type for this instruction")]
+    InvalidPositionType,
+
+    #[msg("Invalid payout curve parameters")]
+    InvalidPayoutCurve,
+
+    #[msg("Payout curve account is required to settle range positions")]
+    PayoutCurveNotConfigured,
+
+    #[msg("Position is healthy and cannot be liquidated")]
+    PositionNotLiquidatable,
+
+    #[msg("Expiration time must be in the future")]
+    InvalidExpirationTime,
+
+    //    <-----------------Fees------------->
+
+    #[msg("Referrer is not the one registered for this wallet")]
+    ReferrerNotRegistered,
+
+    //    <-----------------Rewards------------->
+
+    #[msg("Insufficient reward reserves")]
+    InsufficientRewardReserves,
+
+    #[msg("Insufficient staked shares")]
+    InsufficientStake,
+
+    #[msg("No rewards to claim")]
+    NoRewardsToClaim,
+
+    #[msg("Invalid reward emission schedule")]
+    InvalidEmissionSchedule,
+
+    //    <-----------------Insurance------------->
+
+    #[msg("Payout exceeds trading pool and insurance fund balances")]
+    InsufficientInsuranceFunds,
+
+    #[msg("No deferred payout for this position")]
+    NoDeferredPayout,
+
+    //    <-----------------Collateral------------->
+
+    #[msg("Collateral mint is not enabled")]
+    CollateralMintDisabled,
+
+    #[msg("Collateral does not match the position's mint")]
+    CollateralMintMismatch,
+
+    #[msg("Token collateral accounts are missing")]
+    MissingCollateralAccounts,
+
+    #[msg("Token account does not belong to this collateral mint")]
+    InvalidCollateralAccount,
+
+    #[msg("Haircut must be below 10000 basis points")]
+    InvalidHaircut,
+
+    #[msg("Mint has a Token-2022 extension that is not supported as collateral")]
+    UnsupportedMintExtension,
+
+    #[msg("Registry and price accounts are missing for the position's margin")]
+    MissingMarginAccounts,
+
+    #[msg("Position already holds the maximum number of margin assets")]
+    MarginSlotsFull,
+
+    #[msg("Margin deposit slot is empty")]
+    EmptyMarginSlot,
+
+    #[msg("Collateral amount must be greater than zero")]
+    InvalidCollateralAmount,
+
+    #[msg("Margin can only be released once the position is closed")]
+    PositionStillOpen,
+
+    //    <-----------------Market------------->
+
+    #[msg("Market is not active")]
+    MarketInactive,
+
+    #[msg("Market circuit breaker is tripped, new positions are paused")]
+    CircuitBreakerTripped,
+
+    #[msg("Position belongs to a different market")]
+    MarketMismatch,
+
+    #[msg("Price update is not for this market's feed")]
+    PriceFeedMismatch,
+
+    #[msg("Position size is below the minimum")]
+    PositionTooSmall,
+
+    #[msg("Position size exceeds the market limit")]
+    PositionTooLarge,
+
+    //    <-----------------Config------------->
+
+    #[msg("Protocol config parameter out of bounds")]
+    InvalidConfig,
+
+    #[msg("Program is not paused")]
+    ProgramNotPaused,
+
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+
+    #[msg("Protocol is in emergency withdraw-only mode")]
+    EmergencyModeActive,
+
+    #[msg("Protocol is not in emergency mode")]
+    EmergencyModeNotActive,
+
+    #[msg("Account is frozen")]
+    AccountFrozen,
+
+    #[msg("Account is not frozen")]
+    AccountNotFrozen,
+
+    //    <-----------------Compliance------------->
+
+    #[msg("Address is on the sanctions list")]
+    OFACViolation,
+
+    #[msg("Address is already on the sanctions list")]
+    AddressAlreadySanctioned,
+
+    #[msg("Address is not on the sanctions list")]
+    AddressNotSanctioned,
+
+    #[msg("Sanctions page is full")]
+    SanctionsPageFull,
     
 
     
@@ -0,0 +1,144 @@
+use anchor_lang::prelude::*;
+
+use crate::state::ProtocolConfig;
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct AdminControl<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+impl<'info> AdminControl<'info> {
+    pub fn pause_protocol(&mut self) -> Result<()> {
+        require!(!self.protocol_config.is_paused, ErrorCode::ProgramPaused);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        self.protocol_config.is_paused = true;
+        self.protocol_config.paused_at = current_time;
+
+        emit!(ProtocolPausedEvent {
+            admin: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn resume_protocol(&mut self) -> Result<()> {
+        require!(self.protocol_config.is_paused, ErrorCode::ProgramNotPaused);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        self.protocol_config.is_paused = false;
+        self.protocol_config.paused_at = 0;
+
+        emit!(ProtocolResumedEvent {
+            admin: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Emergency mode blocks new positions and opens the penalty-free exit paths
+    pub fn set_emergency_mode(&mut self, enabled: bool) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        self.protocol_config.is_emergency = enabled;
+        self.protocol_config.emergency_started_at = if enabled { current_time } else { 0 };
+
+        emit!(EmergencyModeUpdatedEvent {
+            admin: self.admin.key(),
+            is_emergency: enabled,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Step one of the admin transfer: the new admin must call `accept_admin`.
+    // Proposing Pubkey::default() cancels a pending transfer.
+    pub fn update_admin(&mut self, new_admin: Pubkey) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        self.protocol_config.pending_admin = new_admin;
+
+        emit!(AdminTransferProposedEvent {
+            current_admin: self.admin.key(),
+            pending_admin: new_admin,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub new_admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.pending_admin != Pubkey::default() @ ErrorCode::NotPendingAdmin,
+        constraint = protocol_config.pending_admin == new_admin.key() @ ErrorCode::NotPendingAdmin
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+impl<'info> AcceptAdmin<'info> {
+    pub fn accept_admin(&mut self) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let previous_admin = self.protocol_config.admin;
+
+        self.protocol_config.admin = self.new_admin.key();
+        self.protocol_config.pending_admin = Pubkey::default();
+
+        emit!(AdminTransferredEvent {
+            previous_admin,
+            new_admin: self.new_admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct ProtocolPausedEvent {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolResumedEvent {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyModeUpdatedEvent {
+    pub admin: Pubkey,
+    pub is_emergency: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferProposedEvent {
+    pub current_admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferredEvent {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub timestamp: i64,
+}
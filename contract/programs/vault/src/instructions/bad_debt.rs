@@ -0,0 +1,328 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::state::{
+    CollateralMint, FreezeRecord, InsuranceFund, LossLedger, PoolLiquidity, PositionState, ProtocolConfig, TradingPool, VaultState,
+};
+use crate::instructions::{draw_payout, ensure_collateral_matches, PayoutSources, TokenRoute};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct InitLossLedger<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + LossLedger::INIT_SPACE,
+        seeds = [b"loss_ledger"],
+        bump
+    )]
+    pub loss_ledger: Account<'info, LossLedger>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitLossLedger<'info> {
+    pub fn init_loss_ledger(&mut self, bumps: &InitLossLedgerBumps) -> Result<()> {
+        self.loss_ledger.set_inner(LossLedger {
+            total_socialized: 0,
+            socialization_events: 0,
+            cumulative_haircut_per_share: 0,
+            outstanding_deferred_payouts: 0,
+            total_deferred_paid: 0,
+            last_socialized_at: 0,
+            bump: bumps.loss_ledger,
+        });
+
+        Ok(())
+    }
+}
+
+// Writes `shortfall` down against LP liquidity so every share absorbs it pro rata,
+// and books it as owed to the trader. Withdrawals value shares against the written-down
+// liquidity, so the haircut stays in the trading pool vault to fund the deferred payout.
+pub fn socialize_bad_debt(
+    loss_ledger: &mut LossLedger,
+    pool_liquidity: &mut PoolLiquidity,
+    shortfall: u64,
+    position: Pubkey,
+    user: Pubkey,
+) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // Only liquidity that exists can absorb the loss; the trader is still owed all of it
+    let total_liquidity_before = pool_liquidity.total_liquidity;
+    let written_down = pool_liquidity.write_down(shortfall, current_time);
+
+    let haircut_per_share = if pool_liquidity.total_shares > 0 {
+        (written_down as u128)
+            .checked_mul(LossLedger::HAIRCUT_PRECISION)
+            .ok_or(ErrorCode::MathOverflow)?
+            / pool_liquidity.total_shares as u128
+    } else {
+        0
+    };
+
+    loss_ledger.total_socialized = loss_ledger.total_socialized
+        .checked_add(written_down)
+        .ok_or(ErrorCode::MathOverflow)?;
+    loss_ledger.socialization_events = loss_ledger.socialization_events
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    loss_ledger.cumulative_haircut_per_share = loss_ledger.cumulative_haircut_per_share
+        .checked_add(haircut_per_share)
+        .ok_or(ErrorCode::MathOverflow)?;
+    loss_ledger.outstanding_deferred_payouts = loss_ledger.outstanding_deferred_payouts
+        .checked_add(shortfall)
+        .ok_or(ErrorCode::MathOverflow)?;
+    loss_ledger.last_socialized_at = current_time;
+
+    emit!(BadDebtSocializedEvent {
+        position,
+        user,
+        shortfall,
+        total_liquidity_before,
+        total_liquidity_after: pool_liquidity.total_liquidity,
+        total_shares: pool_liquidity.total_shares,
+        haircut_per_share,
+        cumulative_haircut_per_share: loss_ledger.cumulative_haircut_per_share,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ClaimDeferredPayout<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            user.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump = position.bump,
+        constraint = position.user == user.key() && position.order_id == order_id,
+        constraint = position.deferred_payout > 0 @ ErrorCode::NoDeferredPayout,
+    )]
+    pub position: Account<'info, PositionState>,
+
+    #[account(
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = user_vault_state.state_bump
+    )]
+    pub user_vault_state: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user_vault_state.key().as_ref()],
+        bump = user_vault_state.vault_bump
+    )]
+    pub user_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault"],
+        bump = insurance_fund.vault_bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"loss_ledger"],
+        bump = loss_ledger.bump
+    )]
+    pub loss_ledger: Account<'info, LossLedger>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    // SPL collateral; required for token positions, unset for SOL positions
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClaimDeferredPayout<'info> {
+    // Pays as much of the deferred settlement as the pool and insurance fund can cover
+    // right now; the rest stays claimable.
+    pub fn claim_deferred_payout(&mut self) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        FreezeRecord::ensure_not_frozen(&self.freeze_record.to_account_info())?;
+
+        let owed = self.position.deferred_payout;
+
+        let token_route = TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )?;
+        ensure_collateral_matches(self.position.collateral_mint, &token_route)?;
+
+        if let Some(route) = token_route {
+            return self.claim_deferred_tokens(&route, owed, current_time);
+        }
+
+        let trading_pool_key = self.trading_pool.key();
+        let pool_seeds = &[
+            b"trading_pool_vault",
+            trading_pool_key.as_ref(),
+            &[self.trading_pool.vault_bump],
+        ];
+        let insurance_seeds = &[b"insurance_vault".as_ref(), &[self.insurance_fund.vault_bump]];
+
+        let sources = PayoutSources {
+            trading_pool_vault: self.trading_pool_vault.to_account_info(),
+            pool_signer_seeds: &[&pool_seeds[..]],
+            insurance_vault: self.insurance_vault.to_account_info(),
+            insurance_signer_seeds: &[&insurance_seeds[..]],
+            recipient: self.user_vault.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+        };
+
+        let breakdown = draw_payout(&sources, owed)?;
+        let paid = breakdown.from_pool + breakdown.from_insurance;
+        require!(paid > 0, ErrorCode::InsufficientInsuranceFunds);
+
+        self.position.deferred_payout = breakdown.shortfall;
+
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .saturating_sub(breakdown.from_pool);
+        self.insurance_fund.total_covered = self.insurance_fund.total_covered
+            .checked_add(breakdown.from_insurance)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        self.loss_ledger.outstanding_deferred_payouts = self.loss_ledger.outstanding_deferred_payouts
+            .saturating_sub(paid);
+        self.loss_ledger.total_deferred_paid = self.loss_ledger.total_deferred_paid
+            .checked_add(paid)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(DeferredPayoutClaimedEvent {
+            position: self.position.key(),
+            user: self.user.key(),
+            paid_from_pool: breakdown.from_pool,
+            paid_from_insurance: breakdown.from_insurance,
+            remaining: breakdown.shortfall,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Token settlements are owed by the mint's pool token vault only
+    fn claim_deferred_tokens(&mut self, route: &TokenRoute<'info>, owed: u64, current_time: i64) -> Result<()> {
+        let vault_token_account = route.check_vault_token_account(
+            self.vault_token_account.as_deref(),
+            &self.user_vault_state.key(),
+        )?;
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        let pool_token_vault = route.check_program_vault(
+            self.pool_token_vault.as_deref(),
+            &collateral_mint.pool_token_vault,
+        )?;
+
+        let pool_balance = self.pool_token_vault.as_ref().map(|account| account.amount).unwrap_or(0);
+        let paid = owed.min(collateral_mint.payable_balance(pool_balance));
+        require!(paid > 0, ErrorCode::InsufficientPoolBalance);
+
+        route.transfer_from_program(pool_token_vault, vault_token_account, paid)?;
+
+        self.position.deferred_payout = owed - paid;
+
+        collateral_mint.total_pool_amount = collateral_mint.total_pool_amount.saturating_sub(paid);
+        collateral_mint.deferred_payouts = collateral_mint.deferred_payouts.saturating_sub(paid);
+
+        emit!(DeferredPayoutClaimedEvent {
+            position: self.position.key(),
+            user: self.user.key(),
+            paid_from_pool: paid,
+            paid_from_insurance: 0,
+            remaining: owed - paid,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct BadDebtSocializedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub shortfall: u64,
+    pub total_liquidity_before: u64,
+    pub total_liquidity_after: u64,
+    pub total_shares: u64,
+    pub haircut_per_share: u128, // Lamports per share, scaled by LossLedger::HAIRCUT_PRECISION
+    pub cumulative_haircut_per_share: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DeferredPayoutClaimedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub paid_from_pool: u64,
+    pub paid_from_insurance: u64,
+    pub remaining: u64,
+    pub timestamp: i64,
+}
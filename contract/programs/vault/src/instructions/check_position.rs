@@ -1,8 +1,9 @@
 use anchor_lang::prelude::*;
-use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, PriceUpdateV2, VerificationLevel};
-use crate::state::{PositionState, PositionStatus};
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use crate::oracle;
+use crate::state::{CurveKind, PayoutCurve, PositionState, PositionStatus, PositionType, ProtocolConfig, Market, PriceHistory};
+use crate::instructions::{record_market_price, revalue_position_margin};
 use crate::error::ErrorCode;
-use crate::constants::{BTC_FEED_ID, MAXIMUM_AGE};
 
 #[derive(Accounts)]
 #[instruction(order_id: u64)]
@@ -22,15 +23,51 @@ pub struct CheckPosition<'info> {
     )]
     pub position: Account<'info, PositionState>,
 
+    #[account(
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump,
+        constraint = position.market == market.key() @ ErrorCode::MarketMismatch
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
     #[account(
         owner = pyth_solana_receiver_sdk::ID,
         constraint = price_update.verification_level == VerificationLevel::Full,
     )]
-    pub price_update: Account<'info, PriceUpdateV2>
+    pub price_update: Account<'info, PriceUpdateV2>,
+
+    /// CHECK: Instructions sysvar, only needed to read the market's secondary signed price
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // Required for range positions, ignored for leveraged ones
+    #[account(
+        seeds = [b"payout_curve".as_ref(), &[position.position_type as u8]],
+        bump = payout_curve.bump,
+    )]
+    pub payout_curve: Option<Account<'info, PayoutCurve>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
 }
 
 impl<'info> CheckPosition<'info> {
-    pub fn check_position(&mut self, _bumps: &CheckPositionBumps) -> Result<()> {
+    pub fn check_position(&mut self, _bumps: &CheckPositionBumps, remaining_accounts: &[AccountInfo]) -> Result<()> {
+        // Range positions are settled against their bounds instead of health-scored
+        if self.position.is_range() {
+            return self.check_range_position();
+        }
+
         let position = &mut self.position;
 
         // Only check active positions
@@ -40,31 +77,43 @@ impl<'info> CheckPosition<'info> {
 
         let clock = Clock::get()?;
 
-        // Verify price update is valid
-        require!(
-            self.price_update.verification_level == VerificationLevel::Full,
-            ErrorCode::UnverifiedPriceUpdate
-        );
-
         // Fetch current market price
-        let price_data = self.price_update.get_price_no_older_than(
+        let oracle_price = oracle::get_market_price_with_fallback(
+            &self.price_update,
+            &self.market,
+            self.instructions_sysvar.as_deref(),
             &clock,
-            MAXIMUM_AGE,
-            &get_feed_id_from_hex(BTC_FEED_ID)?,
-        ).map_err(|_| error!(ErrorCode::StalePriceFeed))?;
+            self.protocol_config.params.maximum_age,
+        )?;
+        record_market_price(&self.market, &mut self.price_history, clock.slot, &oracle_price);
 
-        let current_price = price_data.price as u64;
+        // Health uses the market's mark price (spot or EMA), at the side the position
+        // could be closed at: bid for longs, ask for shorts
+        let mark_price = oracle_price.mark(&self.market, &self.price_history, clock.unix_timestamp)?;
+        mark_price.check_confidence(self.protocol_config.params.max_confidence_bps)?;
+        let current_price = mark_price.conservative(position.is_long, false);
         let current_time = clock.unix_timestamp;
 
+        position.record_price_observation(oracle_price.price, oracle_price.publish_time);
+
+        // Revalue the haircut margin from the collateral registry whenever the position
+        // holds extra margin. Without it health uses the raw collateral and remaining
+        // accounts are ignored, so the caller cannot choose whether the haircut applies
+        position.expire_margin_valuation(current_time, self.protocol_config.params.maximum_age);
+        if position.has_margin_deposits() {
+            revalue_position_margin(position, remaining_accounts, self.protocol_config.params.maximum_age, &clock)?;
+        }
+
         // Calculate health score (Collateral / Required Margin)
-        let health_score = self.calculate_health_score(position, current_price)?;
+        let health_score = position.calculate_health_score(current_price)?;
 
         // Perform health score analysis and update status
-        let new_status = if health_score > 150 { // > 1.5
+        let config = self.protocol_config.params;
+        let new_status = if health_score > config.healthy_threshold {
             PositionStatus::Healthy
-        } else if health_score >= 120 { // 1.2-1.5
+        } else if health_score >= config.warning_threshold {
             PositionStatus::Warning
-        } else { // < 1.2
+        } else {
             PositionStatus::LiquidationRisk
         };
 
@@ -84,12 +133,12 @@ impl<'info> CheckPosition<'info> {
         }
 
         // Calculate unrealized P&L for all statuses
-        let unrealized_pnl = self.calculate_unrealized_pnl(position, current_price)?;
+        let unrealized_pnl = Self::calculate_unrealized_pnl(position, current_price)?;
         position.unrealized_pnl = unrealized_pnl;
 
         // Handle liquidation risk
         if new_status == PositionStatus::LiquidationRisk {
-            self.handle_liquidation_risk(position, current_time, current_price)?;
+            Self::handle_liquidation_risk(position, current_time, current_price)?;
         }
 
         // Return position metrics
@@ -106,44 +155,100 @@ impl<'info> CheckPosition<'info> {
         Ok(())
     }
 
-    fn calculate_health_score(&self, position: &PositionState, current_price: u64) -> Result<u16> {
-        // Get current collateral value
-        let collateral_value = position.collateral_amount;
-        
-        // Calculate required margin based on position size and current market conditions
-        let position_value = position.size.checked_mul(current_price)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        // Required margin is typically a percentage of position value (e.g., 10% for 10x leverage)
-        let required_margin = position_value.checked_div(position.leverage as u64)
-            .ok_or(ErrorCode::MathOverflow)?;
-
-        // Health score = (Collateral / Required Margin) * 100
-        let health_score = collateral_value.checked_mul(100)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(required_margin)
-            .ok_or(ErrorCode::DivisionByZero)? as u16;
-
-        Ok(health_score)
-    }
+    fn check_range_position(&mut self) -> Result<()> {
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        require!(
+            self.position.status == PositionStatus::Active,
+            ErrorCode::PositionAlreadySettled
+        );
+
+        // Verified, normalized price from the market's feed
+        let oracle_price = oracle::get_market_price_with_fallback(
+            &self.price_update,
+            &self.market,
+            self.instructions_sysvar.as_deref(),
+            &clock,
+            self.protocol_config.params.maximum_age,
+        )?;
+        record_market_price(&self.market, &mut self.price_history, clock.slot, &oracle_price);
+        oracle_price.check_confidence(self.protocol_config.params.max_confidence_bps)?;
+
+        let current_price = oracle_price.price;
+        let position = &mut self.position;
 
-    fn calculate_unrealized_pnl(&self, position: &PositionState, current_price: u64) -> Result<i64> {
-        let entry_price = position.entry_price;
-        let position_size = position.size;
+        if position.record_price_observation(current_price, oracle_price.publish_time) {
+            emit!(BarrierTouchedEvent {
+                position: position.key(),
+                user: position.user,
+                price: current_price,
+                lower_bound: position.lower_bound,
+                upper_bound: position.upper_bound,
+                touched_at: position.barrier_touched_at,
+            });
+        }
+
+        // Outcome is decided from the observed path, not the latest price alone
+        let breached = position.barrier_touched;
+        let expired = position.is_expired(current_time);
+
+        let outcome = match position.position_type {
+            PositionType::StayIn if breached => Some(false),
+            PositionType::StayIn if expired => Some(true),
+            PositionType::Breakout if breached => Some(true),
+            PositionType::Breakout if expired => Some(false),
+            _ => None,
+        };
+
+        position.last_health_check = current_time;
 
-        let price_diff = if position.is_long {
-            current_price as i64 - entry_price as i64
+        let Some(is_win) = outcome else {
+            msg!("Range position still open at price {}", current_price);
+            return Ok(());
+        };
+
+        let payout_curve = self.payout_curve
+            .as_ref()
+            .ok_or(ErrorCode::PayoutCurveNotConfigured)?;
+
+        // Time held (StayIn) or time to breakout (Breakout); a clean expiry uses the full window
+        let duration = position.expires_at - position.created_at;
+        let elapsed = if position.barrier_touched {
+            position.barrier_touched_at - position.created_at
         } else {
-            entry_price as i64 - current_price as i64
+            duration
         };
 
-        let unrealized_pnl = price_diff.checked_mul(position_size as i64)
-            .ok_or(ErrorCode::MathOverflow)?;
+        let payout_percentage = payout_curve.payout_percentage(is_win, elapsed, duration)?;
+        position.settle(current_time, current_price, payout_percentage)?;
 
-        Ok(unrealized_pnl)
+        emit!(RangePositionSettledEvent {
+            position: position.key(),
+            user: position.user,
+            position_type: position.position_type,
+            lower_bound: position.lower_bound,
+            upper_bound: position.upper_bound,
+            settlement_price: current_price,
+            min_observed_price: position.min_observed_price,
+            max_observed_price: position.max_observed_price,
+            is_win,
+            curve_kind: payout_curve.kind,
+            elapsed,
+            payout_percentage,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    fn calculate_unrealized_pnl(position: &PositionState, current_price: u64) -> Result<i64> {
+        let unrealized_pnl = position.price_pnl(position.size, current_price)?;
+
+        i64::try_from(unrealized_pnl).map_err(|_| error!(ErrorCode::MathOverflow))
     }
 
-    fn handle_liquidation_risk(&self, position: &mut PositionState, current_time: i64, current_price: u64) -> Result<()> {
+    fn handle_liquidation_risk(position: &mut Account<'info, PositionState>, current_time: i64, current_price: u64) -> Result<()> {
         // Mark position for potential liquidation
         position.liquidation_price = Some(current_price);
         position.last_health_check = current_time;
@@ -153,7 +258,7 @@ impl<'info> CheckPosition<'info> {
             user: position.user,
             current_price,
             collateral_amount: position.collateral_amount,
-            required_margin: position.size * current_price / position.leverage as u64,
+            required_margin: position.required_margin(current_price)?,
             timestamp: current_time,
         });
 
@@ -191,4 +296,31 @@ pub struct LiquidationRiskEvent {
     pub collateral_amount: u64,
     pub required_margin: u64,
     pub timestamp: i64,
+}
+
+#[event]
+pub struct RangePositionSettledEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub position_type: PositionType,
+    pub lower_bound: u64,
+    pub upper_bound: u64,
+    pub settlement_price: u64,
+    pub min_observed_price: u64,
+    pub max_observed_price: u64,
+    pub is_win: bool,
+    pub curve_kind: CurveKind,
+    pub elapsed: i64,
+    pub payout_percentage: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BarrierTouchedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub price: u64,
+    pub lower_bound: u64,
+    pub upper_bound: u64,
+    pub touched_at: i64,
 }
\ No newline at end of file
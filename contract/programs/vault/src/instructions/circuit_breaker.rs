@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use crate::oracle::{self, OraclePrice};
+use crate::state::{BreakerChange, Market, PriceHistory, ProtocolConfig};
+
+// Records a market price in its history and emits the circuit breaker transition it caused
+pub fn record_market_price(
+    market: &Account<Market>,
+    price_history: &mut PriceHistory,
+    slot: u64,
+    price: &OraclePrice,
+) {
+    match price_history.observe(market, slot, price) {
+        Some(BreakerChange::Tripped { reference_price, deviation_bps }) => {
+            emit!(CircuitBreakerTrippedEvent {
+                market: market.key(),
+                price: price.price,
+                reference_price,
+                deviation_bps,
+                threshold_bps: market.params.breaker_threshold_bps,
+                timestamp: price.publish_time,
+            });
+        }
+        Some(BreakerChange::Cleared) => {
+            emit!(CircuitBreakerClearedEvent {
+                market: market.key(),
+                price: price.price,
+                timestamp: price.publish_time,
+            });
+        }
+        None => {}
+    }
+}
+
+// Permissionless crank: records the latest market price so the circuit breaker trips and
+// clears even while no position of the market is touched
+#[derive(Accounts)]
+pub struct UpdateCircuitBreaker<'info> {
+    #[account(
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    #[account(
+        owner = pyth_solana_receiver_sdk::ID,
+        constraint = price_update.verification_level == VerificationLevel::Full,
+    )]
+    pub price_update: Account<'info, PriceUpdateV2>,
+
+    /// CHECK: Instructions sysvar, only needed to read the market's secondary signed price
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+impl<'info> UpdateCircuitBreaker<'info> {
+    pub fn update_circuit_breaker(&mut self) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let oracle_price = oracle::get_market_price_with_fallback(
+            &self.price_update,
+            &self.market,
+            self.instructions_sysvar.as_deref(),
+            &clock,
+            self.protocol_config.params.maximum_age,
+        )?;
+        record_market_price(&self.market, &mut self.price_history, clock.slot, &oracle_price);
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct CircuitBreakerTrippedEvent {
+    pub market: Pubkey,
+    pub price: u64,
+    pub reference_price: u64, // Stored price the move was measured against
+    pub deviation_bps: u64,
+    pub threshold_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CircuitBreakerClearedEvent {
+    pub market: Pubkey,
+    pub price: u64,
+    pub timestamp: i64,
+}
@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{Transfer, transfer};
-use crate::state::{PositionState, PositionStatus, VaultState, TradingPool, RewardPool};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::state::{PositionState, PositionStatus, PositionType, VaultState, TradingPool, RewardPool, ProtocolConfig, FreezeRecord, InsuranceFund, LossLedger, PoolLiquidity, CollateralMint};
+use crate::instructions::{ensure_collateral_matches, pay_token_settlement, pay_with_insurance_backstop, socialize_bad_debt, PayoutSources, TokenRoute};
 use crate::error::ErrorCode;
 
 #[derive(Accounts)]
@@ -63,32 +65,113 @@ pub struct ClaimPosition<'info> {
     )]
     pub reward_pool_vault: SystemAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault"],
+        bump = insurance_fund.vault_bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"loss_ledger"],
+        bump = loss_ledger.bump
+    )]
+    pub loss_ledger: Account<'info, LossLedger>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_liquidity"],
+        bump = pool_liquidity.bump
+    )]
+    pub pool_liquidity: Account<'info, PoolLiquidity>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    // SPL collateral; required for token positions, unset for SOL positions
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub reward_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> ClaimPosition<'info> {
     pub fn claim(&mut self, _bumps: &ClaimPositionBumps) -> Result<()> {
-        let position = &mut self.position;
+        // Work on a copy so the helpers below can borrow the other accounts
+        let mut position = (*self.position).clone();
         let clock = Clock::get()?;
-        
+
         msg!("=== REWARD CLAIM PROCESS ===");
         msg!("Position order_id: {}", position.order_id);
         msg!("Position status: {:?}", position.status);
         msg!("Current time: {}", clock.unix_timestamp);
 
+        // Reject while the protocol is paused
+        require!(!self.protocol_config.is_paused, ErrorCode::ProgramPaused);
+
+        // Reject frozen wallets
+        FreezeRecord::ensure_not_frozen(&self.freeze_record.to_account_info())?;
+
         // Validate position ownership (already done by constraints, but explicit check)
         require!(
             position.user == self.user.key(),
             ErrorCode::UnauthorizedAccess
         );
 
+        // Token positions are paid in their own mint, rewards included
+        let token_route = TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )?;
+        ensure_collateral_matches(position.collateral_mint, &token_route)?;
+
         // Handle different claim scenarios based on position status
         match position.status {
             PositionStatus::Settled => {
-                self.claim_settled_position(position, clock.unix_timestamp)?;
+                self.claim_settled_position(&mut position, clock.unix_timestamp, token_route.as_ref())?;
             },
             PositionStatus::Healthy | PositionStatus::Warning | PositionStatus::LiquidationRisk => {
-                self.claim_rewards(position, clock.unix_timestamp)?;
+                self.claim_rewards(&mut position, clock.unix_timestamp, token_route.as_ref())?;
             },
             PositionStatus::Liquidated => {
                 return Err(ErrorCode::PositionLiquidated.into());
@@ -98,10 +181,12 @@ impl<'info> ClaimPosition<'info> {
             }
         }
 
+        self.position.set_inner(position);
+
         Ok(())
     }
 
-    fn claim_settled_position(&mut self, position: &mut PositionState, current_time: i64) -> Result<()> {
+    fn claim_settled_position(&mut self, position: &mut PositionState, current_time: i64, token_route: Option<&TokenRoute<'info>>) -> Result<()> {
         // Check if position is already claimed
         require!(
             !position.is_claimed,
@@ -113,14 +198,22 @@ impl<'info> ClaimPosition<'info> {
 
         msg!("Settlement payout percentage: {}", settlement_data.payout_percentage);
 
-        // Calculate base payout from settlement
-        let base_payout = (position.collateral_amount as u128 * settlement_data.payout_percentage as u128 / 100) as u64;
+        // Only range positions are paid out here; close_position already settled a
+        // leveraged position, so claiming it only collects rewards
+        let base_payout = if position.position_type == PositionType::Leveraged {
+            0
+        } else {
+            (position.collateral_amount as u128 * settlement_data.payout_percentage as u128 / 100) as u64
+        };
         
         // Calculate time-based rewards
         let time_rewards = self.calculate_time_based_rewards(position, current_time)?;
         
         // Calculate performance rewards
         let performance_rewards = self.calculate_performance_rewards(position)?;
+
+        let (time_rewards, performance_rewards) =
+            self.cap_rewards(time_rewards, performance_rewards, current_time, token_route)?;
         
         let total_payout = base_payout + time_rewards + performance_rewards;
 
@@ -132,19 +225,26 @@ impl<'info> ClaimPosition<'info> {
         // Check if rewards are available
         let total_rewards = time_rewards + performance_rewards;
         if total_rewards > 0 {
-            self.validate_and_transfer_rewards(position, total_rewards, current_time)?;
+            self.validate_and_transfer_rewards(time_rewards, performance_rewards, current_time, token_route)?;
         }
 
-        // Transfer base payout from trading pool
+        // Transfer base payout from trading pool; any unpaid part stays owed to the user
+        // on top of what an earlier settlement already deferred
         if base_payout > 0 {
-            self.transfer_base_payout(position, base_payout)?;
+            let shortfall = match token_route {
+                Some(route) => self.transfer_token_base_payout(route, position, base_payout)?,
+                None => self.transfer_base_payout(position, base_payout)?,
+            };
+            position.deferred_payout = position.deferred_payout
+                .checked_add(shortfall)
+                .ok_or(ErrorCode::MathOverflow)?;
         }
 
         // Mark position as claimed
         position.claim(current_time)?;
 
         emit!(PositionClaimedEvent {
-            position: position.key(),
+            position: self.position.key(),
             user: position.user,
             base_payout,
             time_rewards,
@@ -155,12 +255,15 @@ impl<'info> ClaimPosition<'info> {
         Ok(())
     }
 
-    fn claim_rewards(&mut self, position: &mut PositionState, current_time: i64) -> Result<()> {
+    fn claim_rewards(&mut self, position: &mut PositionState, current_time: i64, token_route: Option<&TokenRoute<'info>>) -> Result<()> {
         // Calculate time-based rewards for active positions
         let time_rewards = self.calculate_time_based_rewards(position, current_time)?;
         
         // Calculate performance rewards based on current position performance
         let performance_rewards = self.calculate_performance_rewards(position)?;
+
+        let (time_rewards, performance_rewards) =
+            self.cap_rewards(time_rewards, performance_rewards, current_time, token_route)?;
         
         let total_rewards = time_rewards + performance_rewards;
 
@@ -175,7 +278,7 @@ impl<'info> ClaimPosition<'info> {
         }
 
         // Validate and transfer rewards
-        self.validate_and_transfer_rewards(position, total_rewards, current_time)?;
+        self.validate_and_transfer_rewards(time_rewards, performance_rewards, current_time, token_route)?;
 
         // Update last claim timestamp
         position.last_reward_claim = current_time;
@@ -184,7 +287,7 @@ impl<'info> ClaimPosition<'info> {
         position.claimable_rewards = 0;
 
         emit!(RewardsClaimedEvent {
-            position: position.key(),
+            position: self.position.key(),
             user: position.user,
             time_rewards,
             performance_rewards,
@@ -197,7 +300,9 @@ impl<'info> ClaimPosition<'info> {
 
     fn calculate_time_based_rewards(&self, position: &PositionState, current_time: i64) -> Result<u64> {
         // Time-based rewards: Position Size × Rate × Time
-        let time_elapsed = current_time.saturating_sub(position.last_reward_claim);
+        // Only time inside the reward pool's emission schedule earns rewards
+        let time_elapsed = self.reward_pool
+            .emission_seconds_between(position.last_reward_claim, current_time);
         
         // Convert to hours for calculation (assuming rate is per hour)
         let hours_elapsed = time_elapsed / 3600;
@@ -221,9 +326,17 @@ impl<'info> ClaimPosition<'info> {
     }
 
     fn calculate_performance_rewards(&self, position: &PositionState) -> Result<u64> {
-        // Performance rewards: Pool Share × Performance
-        let pool_share = if self.trading_pool.total_active_amount > 0 {
-            (position.size as u128 * 10000) / self.trading_pool.total_active_amount as u128
+        // Performance rewards: Pool Share × Performance, measured in the position's collateral
+        let (total_active_amount, performance_pool_amount) = match &self.collateral_mint {
+            Some(collateral_mint) => (
+                collateral_mint.total_active_amount,
+                collateral_mint.total_rewards_funded.saturating_sub(collateral_mint.total_rewards_distributed),
+            ),
+            None => (self.trading_pool.total_active_amount, self.reward_pool.performance_pool_amount),
+        };
+
+        let pool_share = if total_active_amount > 0 {
+            (position.size as u128 * 10000) / total_active_amount as u128
         } else {
             0
         };
@@ -248,7 +361,7 @@ impl<'info> ClaimPosition<'info> {
             _ => 100
         };
 
-        let base_performance_reward = self.reward_pool.performance_pool_amount
+        let base_performance_reward = performance_pool_amount
             .checked_mul(pool_share as u64)
             .ok_or(ErrorCode::MathOverflow)?
             .checked_div(10000)
@@ -263,9 +376,60 @@ impl<'info> ClaimPosition<'info> {
         Ok(performance_rewards)
     }
 
-    fn validate_and_transfer_rewards(&mut self, position: &PositionState, reward_amount: u64, current_time: i64) -> Result<()> {
-        // Validate pool reward reserves
-        let reward_vault_balance = self.reward_pool_vault.lamports();
+    // Caps rewards at what their buckets hold. SOL time rewards draw on the unallocated
+    // emission budget and performance rewards on `performance_pool_amount`, and together
+    // they never touch lamports stakers have accrued but not claimed. Token rewards share
+    // the mint's funded reward budget.
+    fn cap_rewards(
+        &mut self,
+        time_rewards: u64,
+        performance_rewards: u64,
+        current_time: i64,
+        token_route: Option<&TokenRoute<'info>>,
+    ) -> Result<(u64, u64)> {
+        let (time_budget, performance_budget, available) = match token_route {
+            Some(_) => {
+                let collateral_mint = self.collateral_mint.as_ref().ok_or(ErrorCode::MissingCollateralAccounts)?;
+                let budget = collateral_mint.total_rewards_funded
+                    .saturating_sub(collateral_mint.total_rewards_distributed);
+                let reward_vault_balance = self.reward_token_vault.as_ref().map(|account| account.amount).unwrap_or(0);
+                (budget, budget, budget.min(reward_vault_balance))
+            },
+            None => {
+                // Bring staker accruals up to date so the reserve is current
+                self.reward_pool.accrue(current_time)?;
+                (
+                    self.reward_pool.unallocated_emissions(),
+                    self.reward_pool.performance_pool_amount,
+                    self.reward_pool_vault.lamports().saturating_sub(self.reward_pool.staker_reserve()),
+                )
+            }
+        };
+
+        let time_rewards = time_rewards.min(time_budget).min(available);
+        let performance_rewards = performance_rewards
+            .min(performance_budget)
+            .min(available - time_rewards);
+
+        Ok((time_rewards, performance_rewards))
+    }
+
+    fn validate_and_transfer_rewards(
+        &mut self,
+        time_rewards: u64,
+        performance_rewards: u64,
+        current_time: i64,
+        token_route: Option<&TokenRoute<'info>>,
+    ) -> Result<()> {
+        let reward_amount = time_rewards + performance_rewards;
+
+        if let Some(route) = token_route {
+            return self.transfer_token_rewards(route, reward_amount);
+        }
+
+        // Validate pool reward reserves, leaving stakers' accrued rewards untouched
+        let reward_vault_balance = self.reward_pool_vault.lamports()
+            .saturating_sub(self.reward_pool.staker_reserve());
         require!(
             reward_vault_balance >= reward_amount,
             ErrorCode::InsufficientRewardReserves
@@ -290,10 +454,11 @@ impl<'info> ClaimPosition<'info> {
 
         transfer(cpi_ctx, reward_amount)?;
 
-        // Update reward pool distribution
-        self.reward_pool.total_distributed = self.reward_pool.total_distributed
-            .checked_add(reward_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+        // Debit each bucket
+        self.reward_pool.record_time_rewards(time_rewards)?;
+        self.reward_pool.performance_pool_amount = self.reward_pool.performance_pool_amount
+            .checked_sub(performance_rewards)
+            .ok_or(ErrorCode::InsufficientRewardReserves)?;
 
         self.reward_pool.last_distribution_time = current_time;
 
@@ -302,45 +467,110 @@ impl<'info> ClaimPosition<'info> {
         Ok(())
     }
 
-    fn transfer_base_payout(&mut self, position: &PositionState, payout_amount: u64) -> Result<()> {
-        // Validate trading pool balance
-        let pool_vault_balance = self.trading_pool_vault.lamports();
+    // Token rewards come from the mint's reward token vault, funded via fund_token_rewards
+    fn transfer_token_rewards(&mut self, route: &TokenRoute<'info>, reward_amount: u64) -> Result<()> {
+        let vault_token_account = route.check_vault_token_account(
+            self.vault_token_account.as_deref(),
+            &self.user_vault_state.key(),
+        )?;
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        let reward_token_vault = route.check_program_vault(
+            self.reward_token_vault.as_deref(),
+            &collateral_mint.reward_token_vault,
+        )?;
+
+        let reward_vault_balance = self.reward_token_vault.as_ref().map(|account| account.amount).unwrap_or(0);
         require!(
-            pool_vault_balance >= payout_amount,
-            ErrorCode::InsufficientPoolBalance
+            reward_vault_balance >= reward_amount,
+            ErrorCode::InsufficientRewardReserves
         );
 
-        // Update trading pool accounting
-        self.trading_pool.total_active_amount = self.trading_pool.total_active_amount
-            .checked_sub(position.size)
+        route.transfer_from_program(reward_token_vault, vault_token_account, reward_amount)?;
+
+        collateral_mint.total_rewards_distributed = collateral_mint.total_rewards_distributed
+            .checked_add(reward_amount)
             .ok_or(ErrorCode::MathOverflow)?;
-        
-        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
-            .checked_sub(payout_amount)
+
+        Ok(())
+    }
+
+    // Token payouts have no insurance backstop; returns the part that could not be paid now
+    fn transfer_token_base_payout(&mut self, route: &TokenRoute<'info>, position: &PositionState, payout_amount: u64) -> Result<u64> {
+        let vault_token_account = route.check_vault_token_account(
+            self.vault_token_account.as_deref(),
+            &self.user_vault_state.key(),
+        )?;
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        route.check_program_vault(self.pool_token_vault.as_deref(), &collateral_mint.pool_token_vault)?;
+        let pool_token_vault = self.pool_token_vault.as_ref().ok_or(ErrorCode::MissingCollateralAccounts)?;
+
+        let shortfall = pay_token_settlement(
+            route,
+            collateral_mint,
+            pool_token_vault,
+            vault_token_account,
+            payout_amount,
+            self.position.key(),
+            position.user,
+        )?;
+
+        collateral_mint.total_active_amount = collateral_mint.total_active_amount
+            .checked_sub(position.size)
             .ok_or(ErrorCode::MathOverflow)?;
 
-        // Transfer from trading pool vault to user vault
+        Ok(shortfall)
+    }
+
+    // Returns the part of the payout that could not be paid now
+    fn transfer_base_payout(&mut self, position: &PositionState, payout_amount: u64) -> Result<u64> {
+        // Transfer from trading pool vault to user vault; the insurance fund covers any shortfall
+        let trading_pool_key = self.trading_pool.key();
         let pool_vault_seeds = &[
             b"trading_pool_vault",
-            self.trading_pool.to_account_info().key.as_ref(),
+            trading_pool_key.as_ref(),
             &[self.trading_pool.vault_bump],
         ];
-        let signer_seeds = &[&pool_vault_seeds[..]];
-        
-        let cpi_ctx = CpiContext::new_with_signer(
-            self.system_program.to_account_info(),
-            Transfer {
-                from: self.trading_pool_vault.to_account_info(),
-                to: self.user_vault.to_account_info(),
-            },
-            signer_seeds,
-        );
+        let insurance_seeds = &[b"insurance_vault".as_ref(), &[self.insurance_fund.vault_bump]];
+
+        let sources = PayoutSources {
+            trading_pool_vault: self.trading_pool_vault.to_account_info(),
+            pool_signer_seeds: &[&pool_vault_seeds[..]],
+            insurance_vault: self.insurance_vault.to_account_info(),
+            insurance_signer_seeds: &[&insurance_seeds[..]],
+            recipient: self.user_vault.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+        };
 
-        transfer(cpi_ctx, payout_amount)?;
+        let breakdown = pay_with_insurance_backstop(
+            &sources,
+            payout_amount,
+            &mut self.insurance_fund,
+            self.position.key(),
+            position.user,
+        )?;
+
+        // Pool and insurance exhausted: LPs absorb the rest instead of reverting
+        if breakdown.shortfall > 0 {
+            socialize_bad_debt(
+                &mut self.loss_ledger,
+                &mut self.pool_liquidity,
+                breakdown.shortfall,
+                self.position.key(),
+                position.user,
+            )?;
+        }
 
-        msg!("Base payout transferred: {} lamports", payout_amount);
+        // Update trading pool accounting
+        self.trading_pool.total_active_amount = self.trading_pool.total_active_amount
+            .checked_sub(position.size)
+            .ok_or(ErrorCode::MathOverflow)?;
         
-        Ok(())
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .saturating_sub(breakdown.from_pool);
+
+        msg!("Base payout transferred: {} lamports", payout_amount - breakdown.shortfall);
+        
+        Ok(breakdown.shortfall)
     }
 }
 
@@ -1,9 +1,11 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{Transfer, transfer};
-use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, PriceUpdateV2, VerificationLevel};
-use crate::state::{PositionState, PositionStatus, VaultState, TradingPool, SettlementData};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use crate::oracle;
+use crate::state::{notional, PositionState, PositionStatus, PositionType, ProtocolConfig, FreezeRecord, VaultState, TradingPool, FeeLedger, FeeCategory, PoolLiquidity, InsuranceFund, LossLedger, CollateralMint, Market, PriceHistory, ReferralRecord};
+use crate::instructions::{distribute_fee, ensure_collateral_matches, pay_token_settlement, pay_with_insurance_backstop, record_market_price, retain_token_fee, socialize_bad_debt, FeeRoute, PayoutSources, TokenRoute};
 use crate::error::ErrorCode;
-use crate::constants::{BTC_FEED_ID, MAXIMUM_AGE, TRADING_FEE_BPS, CLOSING_FEE_BPS};
 
 #[derive(Accounts)]
 #[instruction(order_id: u64)]
@@ -23,6 +25,7 @@ pub struct ClosePosition<'info> {
         constraint = position.order_id == order_id,
         constraint = position.status != PositionStatus::Settled @ ErrorCode::PositionAlreadySettled,
         constraint = position.status != PositionStatus::Liquidated @ ErrorCode::PositionLiquidated,
+        constraint = position.position_type == PositionType::Leveraged @ ErrorCode::InvalidPositionType,
     )]
     pub position: Account<'info, PositionState>,
     
@@ -54,20 +57,120 @@ pub struct ClosePosition<'info> {
     )]
     pub trading_pool_vault: SystemAccount<'info>,
 
+    #[account(
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump,
+        constraint = position.market == market.key() @ ErrorCode::MarketMismatch
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
     #[account(
         owner = pyth_solana_receiver_sdk::ID,
         constraint = price_update.verification_level == VerificationLevel::Full,
     )]
     pub price_update: Account<'info, PriceUpdateV2>,
-    
+
+    /// CHECK: Instructions sysvar, only needed to read the market's secondary signed price
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_liquidity"],
+        bump = pool_liquidity.bump
+    )]
+    pub pool_liquidity: Account<'info, PoolLiquidity>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault"],
+        bump = insurance_fund.vault_bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"loss_ledger"],
+        bump = loss_ledger.bump
+    )]
+    pub loss_ledger: Account<'info, LossLedger>,
+
+    // Optional referrer, receives the referrer share of fees. Must be the referrer
+    // registered for `user` at their first deposit
+    #[account(mut)]
+    pub referrer: Option<SystemAccount<'info>>,
+
+    /// CHECK: Referral record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"referral", user.key().as_ref()],
+        bump
+    )]
+    pub referral: UncheckedAccount<'info>,
+
+    // SPL collateral; required for token positions, unset for SOL positions
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
     pub system_program: Program<'info, System>
 }
 
 impl<'info> ClosePosition<'info> {
     pub fn close_position(&mut self, _bumps: &ClosePositionBumps) -> Result<()> {
-        let position = &mut self.position;
+        // Work on a copy so the helpers below can borrow the other accounts
+        let mut position = (*self.position).clone();
         let clock = Clock::get()?;
-        
+
         msg!("=== POSITION SETTLEMENT PROCESS ===");
         msg!("Position order_id: {}", position.order_id);
         msg!("Position size: {}", position.size);
@@ -75,65 +178,92 @@ impl<'info> ClosePosition<'info> {
         msg!("Collateral amount: {}", position.collateral_amount);
         msg!("Is long: {}", position.is_long);
 
-        // Verify price update
-        require!(
-            self.price_update.verification_level == VerificationLevel::Full,
-            ErrorCode::UnverifiedPriceUpdate
-        );
+        // Reject frozen wallets
+        FreezeRecord::ensure_not_frozen(&self.freeze_record.to_account_info())?;
+
+        // Token positions settle in their own mint
+        let token_route = self.token_route()?;
+        ensure_collateral_matches(position.collateral_mint, &token_route)?;
 
         // Get current market price
-        let price_data = self.price_update.get_price_no_older_than(
+        let oracle_price = oracle::get_market_price_with_fallback(
+            &self.price_update,
+            &self.market,
+            self.instructions_sysvar.as_deref(),
             &clock,
-            MAXIMUM_AGE,
-            &get_feed_id_from_hex(BTC_FEED_ID)?,
-        ).map_err(|_| error!(ErrorCode::StalePriceFeed))?;
+            self.protocol_config.params.maximum_age,
+        )?;
+        record_market_price(&self.market, &mut self.price_history, clock.slot, &oracle_price);
+        oracle_price.check_confidence(self.protocol_config.params.max_confidence_bps)?;
 
-        let current_price = price_data.price as u64;
         let current_time = clock.unix_timestamp;
+
+        // Markets can settle at the TWAP so a single update cannot set the exit price
+        let exit_price = if self.market.params.settle_at_twap {
+            oracle_price.at_twap(&self.market, &self.price_history, current_time)?
+        } else {
+            oracle_price
+        };
+
+        // Longs close at the bottom of the confidence interval, shorts at the top
+        let current_price = exit_price.conservative(position.is_long, false);
         
         msg!("Current market price: {}", current_price);
 
         // Calculate current position value
-        let position_value = self.calculate_current_position_value(position, current_price)?;
+        let position_value = self.calculate_current_position_value(&position, current_price)?;
         msg!("Current position value: {}", position_value);
 
         // Account for all fees & costs
-        let total_fees = self.calculate_total_fees(position, position_value)?;
+        let trading_fee = self.calculate_trading_fee(position_value)?;
+        let closing_fee = self.calculate_closing_fee(position_value)?;
+        let total_fees = trading_fee
+            .checked_add(closing_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
         msg!("Total fees: {}", total_fees);
 
         // Calculate final P&L
-        let final_pnl = self.calculate_final_pnl(position, current_price, total_fees)?;
+        let final_pnl = self.calculate_final_pnl(&position, current_price, total_fees)?;
         msg!("Final P&L: {}", final_pnl);
 
+        // Fees are taken from the position's value in the trading pool vault and split
+        self.distribute_fees(&position, final_pnl, trading_fee, closing_fee, token_route.as_ref())?;
+
         // Determine settlement amount
-        let settlement_result = self.determine_settlement_amount(position, final_pnl)?;
+        let settlement_result = self.determine_settlement_amount(&position, final_pnl)?;
         msg!("Settlement amount: {}", settlement_result.settlement_amount);
         msg!("Settlement type: {:?}", settlement_result.settlement_type);
 
         // Execute settlement based on P&L
-        match settlement_result.settlement_type {
+        let deferred_payout = match settlement_result.settlement_type {
             SettlementType::Positive => {
-                self.handle_positive_settlement(position, settlement_result.settlement_amount, current_time, current_price)?;
+                self.handle_positive_settlement(&position, settlement_result.settlement_amount, token_route.as_ref())?
             },
             SettlementType::Negative => {
-                self.handle_negative_settlement(position, settlement_result.settlement_amount, current_time, current_price)?;
+                self.handle_negative_settlement(&position, settlement_result.settlement_amount, token_route.as_ref())?;
+                0
             }
-        }
+        };
+
+        // Anything the pool and insurance could not pay stays owed, see claim_deferred_payout
+        position.deferred_payout = deferred_payout;
 
         // Close position account (mark as settled)
-        self.close_position_account(position, current_time, current_price, settlement_result.payout_percentage)?;
+        self.close_position_account(&mut position, current_time, current_price, settlement_result.payout_percentage)?;
 
         // Return rent to user (if position account is being closed)
         self.return_rent_to_user()?;
 
         // Update pool active positions
-        self.update_pool_active_positions(position)?;
+        self.update_pool_active_positions(&position)?;
 
         // Log final performance
-        self.log_final_performance(position, current_price, final_pnl, settlement_result.settlement_amount)?;
+        self.log_final_performance(&position, current_price, final_pnl, settlement_result.settlement_amount)?;
+
+        self.position.set_inner(position.clone());
 
         emit!(PositionClosedEvent {
-            position: position.key(),
+            position: self.position.key(),
             user: position.user,
             order_id: position.order_id,
             entry_price: position.entry_price,
@@ -149,58 +279,131 @@ impl<'info> ClosePosition<'info> {
         Ok(())
     }
 
+    fn token_route(&self) -> Result<Option<TokenRoute<'info>>> {
+        TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )
+    }
+
     fn calculate_current_position_value(&self, position: &PositionState, current_price: u64) -> Result<u64> {
-        // Position value = size * current_price
-        let position_value = position.size
-            .checked_mul(current_price)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        Ok(position_value)
+        // Position value = notional at the current price
+        let position_value = notional(position.size, current_price)?;
+
+        u64::try_from(position_value).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    fn calculate_trading_fee(&self, position_value: u64) -> Result<u64> {
+        // Trading fee (applied to position value), at the market's rate if it overrides one
+        let trading_fee = (position_value as u128)
+            * self.market.trading_fee_bps(&self.protocol_config.params) as u128
+            / 10000;
+
+        Ok(trading_fee as u64)
     }
 
-    fn calculate_total_fees(&self, position: &PositionState, position_value: u64) -> Result<u64> {
-        // Trading fee (applied to position value)
-        let trading_fee = position_value
-            .checked_mul(TRADING_FEE_BPS as u64)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::DivisionByZero)?;
-
-        // Closing fee (applied to position size)
-        let closing_fee = position.size
-            .checked_mul(CLOSING_FEE_BPS as u64)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::DivisionByZero)?;
+    fn calculate_closing_fee(&self, position_value: u64) -> Result<u64> {
+        // Closing fee (applied to position value)
+        let closing_fee = (position_value as u128)
+            * self.market.closing_fee_bps(&self.protocol_config.params) as u128
+            / 10000;
 
+        Ok(closing_fee as u64)
+    }
+
+    // Splits the part of the fees the position could actually pay: on a total loss the
+    // collateral runs out before the fees are covered. Trading fee is taken first.
+    fn distribute_fees(&mut self, position: &PositionState, final_pnl: i64, trading_fee: u64, closing_fee: u64, token_route: Option<&TokenRoute<'info>>) -> Result<()> {
         let total_fees = trading_fee
             .checked_add(closing_fee)
             .ok_or(ErrorCode::MathOverflow)?;
+        let value_before_fees = (position.collateral_amount as i128)
+            + final_pnl as i128
+            + total_fees as i128;
+        let retained = value_before_fees.clamp(0, total_fees as i128) as u64;
+
+        let trading_retained = trading_fee.min(retained);
+        let closing_retained = retained - trading_retained;
+
+        // Token fees already sit in the pool token vault
+        if token_route.is_some() {
+            let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+            let fee_split = self.protocol_config.params.fee_split;
+            for (category, amount) in [
+                (FeeCategory::Trading, trading_retained),
+                (FeeCategory::Closing, closing_retained),
+            ] {
+                retain_token_fee(collateral_mint, category, self.user.key(), amount, &fee_split)?;
+            }
+            return Ok(());
+        }
 
-        Ok(total_fees)
-    }
+        let trading_pool_key = self.trading_pool.key();
+        let pool_seeds = &[
+            b"trading_pool_vault",
+            trading_pool_key.as_ref(),
+            &[self.trading_pool.vault_bump],
+        ];
+        let signer_seeds = &[&pool_seeds[..]];
+
+        let referrer = ReferralRecord::registered_referrer(
+            &self.referral.to_account_info(),
+            self.referrer.as_ref(),
+        )?;
+
+        // LP and treasury shares stay in the pool vault; the treasury share is swept later
+        let route = FeeRoute {
+            source: self.trading_pool_vault.to_account_info(),
+            source_signer_seeds: signer_seeds,
+            lp_destination: self.trading_pool_vault.to_account_info(),
+            treasury_destination: self.trading_pool_vault.to_account_info(),
+            insurance_vault: self.insurance_vault.to_account_info(),
+            referrer,
+            system_program: self.system_program.to_account_info(),
+        };
 
-    fn calculate_final_pnl(&self, position: &PositionState, current_price: u64, total_fees: u64) -> Result<i64> {
-        let entry_price = position.entry_price;
-        let position_size = position.size;
+        let fee_split = self.protocol_config.params.fee_split;
+        let mut lp_share = 0u64;
+        let mut paid_out = 0u64;
+
+        for (category, amount) in [
+            (FeeCategory::Trading, trading_retained),
+            (FeeCategory::Closing, closing_retained),
+        ] {
+            let split = distribute_fee(&route, category, self.user.key(), amount, &fee_split, &mut self.fee_ledger)?;
+            lp_share = lp_share
+                .checked_add(split.lp)
+                .ok_or(ErrorCode::MathOverflow)?;
+            paid_out = paid_out
+                .checked_add(split.insurance)
+                .and_then(|total| total.checked_add(split.referrer))
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
 
-        // Calculate raw P&L based on price movement
-        let price_diff = if position.is_long {
-            current_price as i64 - entry_price as i64
-        } else {
-            entry_price as i64 - current_price as i64
-        };
+        // LP share raises the value of every pool share
+        self.pool_liquidity.add_income(lp_share, Clock::get()?.unix_timestamp)?;
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .saturating_sub(paid_out);
 
-        let raw_pnl = price_diff
-            .checked_mul(position_size as i64)
+        self.trading_pool.total_fees_collected = self.trading_pool.total_fees_collected
+            .checked_add(retained)
             .ok_or(ErrorCode::MathOverflow)?;
 
+        Ok(())
+    }
+
+    fn calculate_final_pnl(&self, position: &PositionState, current_price: u64, total_fees: u64) -> Result<i64> {
+        // Raw P&L of the price move, in collateral units
+        let raw_pnl = position.price_pnl(position.size, current_price)?;
+
         // Subtract fees from P&L
         let final_pnl = raw_pnl
-            .checked_sub(total_fees as i64)
+            .checked_sub(total_fees as i128)
             .ok_or(ErrorCode::MathOverflow)?;
 
-        Ok(final_pnl)
+        i64::try_from(final_pnl).map_err(|_| error!(ErrorCode::MathOverflow))
     }
 
     fn determine_settlement_amount(&self, position: &PositionState, final_pnl: i64) -> Result<SettlementResult> {
@@ -225,7 +428,7 @@ impl<'info> ClosePosition<'info> {
             })
         } else {
             // Negative P&L: Partial or no recovery
-            let loss_amount = final_pnl.abs() as u64;
+            let loss_amount = final_pnl.unsigned_abs();
             
             if loss_amount >= position.collateral_amount {
                 // Total loss
@@ -251,46 +454,72 @@ impl<'info> ClosePosition<'info> {
         }
     }
 
-    fn handle_positive_settlement(&mut self, position: &PositionState, settlement_amount: u64, current_time: i64, current_price: u64) -> Result<()> {
+    // Returns the part of the settlement that could not be paid now
+    fn handle_positive_settlement(&mut self, position: &PositionState, settlement_amount: u64, token_route: Option<&TokenRoute<'info>>) -> Result<u64> {
         msg!("Handling positive settlement");
+
+        if let Some(route) = token_route {
+            return self.pay_token_settlement(route, position, settlement_amount);
+        }
         
-        // Transfer profits + collateral from trading pool to user vault
+        // Transfer profits + collateral from trading pool to user vault,
+        // with the insurance fund covering any pool shortfall
         if settlement_amount > 0 {
-            let pool_vault_balance = self.trading_pool_vault.lamports();
-            require!(
-                pool_vault_balance >= settlement_amount,
-                ErrorCode::InsufficientPoolBalance
-            );
-
+            let trading_pool_key = self.trading_pool.key();
             let pool_seeds = &[
                 b"trading_pool_vault",
-                self.trading_pool.to_account_info().key.as_ref(),
+                trading_pool_key.as_ref(),
                 &[self.trading_pool.vault_bump],
             ];
-            let signer_seeds = &[&pool_seeds[..]];
+            let insurance_seeds = &[b"insurance_vault".as_ref(), &[self.insurance_fund.vault_bump]];
+
+            let sources = PayoutSources {
+                trading_pool_vault: self.trading_pool_vault.to_account_info(),
+                pool_signer_seeds: &[&pool_seeds[..]],
+                insurance_vault: self.insurance_vault.to_account_info(),
+                insurance_signer_seeds: &[&insurance_seeds[..]],
+                recipient: self.vault.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+            };
 
-            let cpi_ctx = CpiContext::new_with_signer(
-                self.system_program.to_account_info(),
-                Transfer {
-                    from: self.trading_pool_vault.to_account_info(),
-                    to: self.vault.to_account_info(),
-                },
-                signer_seeds,
-            );
+            let breakdown = pay_with_insurance_backstop(
+                &sources,
+                settlement_amount,
+                &mut self.insurance_fund,
+                self.position.key(),
+                position.user,
+            )?;
 
-            transfer(cpi_ctx, settlement_amount)?;
-            
             // Update trading pool amounts
             self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
-                .checked_sub(settlement_amount)
-                .ok_or(ErrorCode::MathOverflow)?;
+                .saturating_sub(breakdown.from_pool);
+
+            // Pool and insurance exhausted: LPs absorb the rest instead of reverting
+            if breakdown.shortfall > 0 {
+                socialize_bad_debt(
+                    &mut self.loss_ledger,
+                    &mut self.pool_liquidity,
+                    breakdown.shortfall,
+                    self.position.key(),
+                    position.user,
+                )?;
+            }
+
+            return Ok(breakdown.shortfall);
         }
 
-        Ok(())
+        Ok(0)
     }
 
-    fn handle_negative_settlement(&mut self, position: &PositionState, settlement_amount: u64, current_time: i64, current_price: u64) -> Result<()> {
+    fn handle_negative_settlement(&mut self, position: &PositionState, settlement_amount: u64, token_route: Option<&TokenRoute<'info>>) -> Result<()> {
         msg!("Handling negative settlement - partial/no recovery");
+
+        // The remaining collateral is always covered by the pool, so nothing is deferred
+        if let Some(route) = token_route {
+            let shortfall = self.pay_token_settlement(route, position, settlement_amount)?;
+            require!(shortfall == 0, ErrorCode::InsufficientPoolBalance);
+            return Ok(());
+        }
         
         // Only transfer remaining collateral if any
         if settlement_amount > 0 {
@@ -319,11 +548,6 @@ impl<'info> ClosePosition<'info> {
             transfer(cpi_ctx, settlement_amount)?;
         }
 
-        // Update pool accounting - losses remain in pool
-        let collateral_loss = position.collateral_amount
-            .checked_sub(settlement_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
-
         // Pool keeps the losses, only reduce by what was actually paid out
         self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
             .checked_sub(settlement_amount)
@@ -332,6 +556,27 @@ impl<'info> ClosePosition<'info> {
         Ok(())
     }
 
+    // Pays from the pool token vault into the user's token vault; returns the unpaid part
+    fn pay_token_settlement(&mut self, route: &TokenRoute<'info>, position: &PositionState, settlement_amount: u64) -> Result<u64> {
+        let vault_token_account = route.check_vault_token_account(
+            self.vault_token_account.as_deref(),
+            &self.vault_state.key(),
+        )?;
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        route.check_program_vault(self.pool_token_vault.as_deref(), &collateral_mint.pool_token_vault)?;
+        let pool_token_vault = self.pool_token_vault.as_ref().ok_or(ErrorCode::MissingCollateralAccounts)?;
+
+        pay_token_settlement(
+            route,
+            collateral_mint,
+            pool_token_vault,
+            vault_token_account,
+            settlement_amount,
+            self.position.key(),
+            position.user,
+        )
+    }
+
     fn close_position_account(&mut self, position: &mut PositionState, current_time: i64, current_price: u64, payout_percentage: u8) -> Result<()> {
         msg!("Closing position account");
         
@@ -352,9 +597,18 @@ impl<'info> ClosePosition<'info> {
         msg!("Updating pool active positions");
         
         // Reduce active amount by position size
-        self.trading_pool.total_active_amount = self.trading_pool.total_active_amount
-            .checked_sub(position.size)
-            .ok_or(ErrorCode::MathOverflow)?;
+        match self.collateral_mint.as_mut() {
+            Some(collateral_mint) => {
+                collateral_mint.total_active_amount = collateral_mint.total_active_amount
+                    .checked_sub(position.size)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            },
+            None => {
+                self.trading_pool.total_active_amount = self.trading_pool.total_active_amount
+                    .checked_sub(position.size)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+        }
 
         // Update vault state
         self.vault_state.active_positions = self.vault_state.active_positions
@@ -0,0 +1,317 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use crate::state::{PositionState, ProtocolConfig, SanctionsPage, TradingPool, VaultState, PositionVault, CollateralMint, Market, PriceHistory};
+use crate::instructions::{
+    ensure_can_open_position, check_position_size, read_entry_price,
+    transfer_stake_to_pool, add_open_position, StakeSource,
+};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(
+    is_long: bool,
+    size: u64,
+    leverage: u8,
+    collateral_amount: u64,
+    order_id: u64,
+)]
+pub struct CreateLeveragedPosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PositionState::LEN,
+        seeds = [
+            b"position".as_ref(),
+            user.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub position: Account<'info, PositionState>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PositionVault::INIT_SPACE,
+        seeds = [
+            b"position_vault".as_ref(),
+            position.key().as_ref()
+        ],
+        bump
+    )]
+    pub position_vault: Account<'info, PositionVault>,
+    
+    // User's personal vault
+    #[account(
+        mut,
+        seeds = [b"vault", user_vault_state.key().as_ref()],
+        bump = user_vault_state.vault_bump,
+    )]
+    pub user_vault: SystemAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = user_vault_state.state_bump
+    )]
+    pub user_vault_state: Account<'info, VaultState>,
+    
+    // Trading pool 
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+    
+    // Trading pool vault
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+    
+    #[account(
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump,
+        constraint = market.params.is_active @ ErrorCode::MarketInactive
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    // Pyth price update
+    #[account(
+        owner = pyth_solana_receiver_sdk::ID,
+        constraint = price_update.verification_level == VerificationLevel::Full,
+    )]
+    pub price_update: Account<'info, PriceUpdateV2>,
+
+    /// CHECK: Instructions sysvar, only needed to read the market's secondary signed price
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    /// CHECK: Sanctions page for `user`'s bucket, may not exist; checked in the handler
+    #[account(
+        seeds = [b"sanctions_page", &[SanctionsPage::bucket_for(&user.key())]],
+        bump
+    )]
+    pub sanctions_page: UncheckedAccount<'info>,
+
+    // SPL collateral; leave all unset to post native SOL
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump,
+        constraint = collateral_mint.is_enabled @ ErrorCode::CollateralMintDisabled
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateLeveragedPosition<'info> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_leveraged_position(
+        &mut self,
+        is_long: bool,
+        size: u64,
+        leverage: u8,
+        collateral_amount: u64,
+        order_id: u64,
+        expires_at: i64,
+        bumps: &CreateLeveragedPositionBumps
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let config = self.protocol_config.params;
+
+        msg!("=== LEVERAGED POSITION CREATION ===");
+        msg!("User: {}", self.user.key());
+        msg!("Order ID: {}", order_id);
+        msg!("Is Long: {}", is_long);
+        msg!("Size: {}", size);
+        msg!("Leverage: {}x", leverage);
+        msg!("Collateral: {}", collateral_amount);
+
+        ensure_can_open_position(
+            &self.protocol_config,
+            &self.freeze_record,
+            &self.sanctions_page,
+            &self.user.key(),
+        )?;
+
+        // Validate leverage ratio against the configured limits
+        self.validate_leverage_ratio(leverage)?;
+
+        require!(collateral_amount > 0, ErrorCode::InvalidCollateralAmount);
+        require!(expires_at > clock.unix_timestamp, ErrorCode::InvalidExpirationTime);
+
+        check_position_size(
+            &self.market,
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            &config,
+            size,
+        )?;
+
+        // Get the market price for entry
+        let oracle_price = read_entry_price(
+            &self.price_update,
+            &self.market,
+            &mut self.price_history,
+            self.instructions_sysvar.as_deref(),
+            &clock,
+            &config,
+        )?;
+
+        // Longs enter at the top of the confidence interval, shorts at the bottom
+        let entry_price = oracle_price.conservative(is_long, true);
+        msg!("Entry price: {}", entry_price);
+
+        // Create Position Account using derived PDA
+        self.position.set_inner(PositionState::new(
+            self.user.key(),
+            order_id,
+            is_long,
+            size,
+            entry_price,
+            collateral_amount,
+            leverage,
+            expires_at,
+            bumps.position,
+        ));
+        self.position.collateral_mint = self.collateral_mint.as_ref()
+            .map(|collateral_mint| collateral_mint.mint)
+            .unwrap_or_default();
+        self.position.market = self.market.key();
+
+        // Collateral must cover the initial margin, i.e. a health score of at least 100
+        let required_margin = self.position.required_margin(entry_price)?;
+        msg!("Required margin: {}", required_margin);
+        require!(
+            self.position.calculate_health_score(entry_price)? >= 100,
+            ErrorCode::InsufficientCollateral
+        );
+
+        let liquidation_price = self.position.calculate_liquidation_price(config.liquidation_threshold)?;
+        self.position.liquidation_price = Some(liquidation_price);
+        msg!("Liquidation price: {}", liquidation_price);
+
+        // Create Position Vault Account
+        self.position_vault.position = self.position.key();
+        self.position_vault.balance = 0;
+        self.position_vault.bump = bumps.position_vault;
+
+        // Transfer collateral to the trading pool
+        transfer_stake_to_pool(&self.stake_source(), collateral_amount)?;
+        self.position_vault.balance = collateral_amount;
+
+        // Update pool active positions
+        add_open_position(
+            self.collateral_mint.as_deref_mut().map(|collateral_mint| &mut **collateral_mint),
+            &mut self.trading_pool,
+            &mut self.user_vault_state,
+            size,
+            collateral_amount,
+        )?;
+
+        emit!(LeveragedPositionCreatedEvent {
+            position: self.position.key(),
+            user: self.position.user,
+            market: self.market.key(),
+            order_id,
+            is_long,
+            size,
+            leverage,
+            entry_price,
+            collateral_amount,
+            required_margin,
+            liquidation_price,
+            expires_at,
+            trading_pool: self.trading_pool.key(),
+        });
+
+        msg!("=== POSITION SUCCESSFULLY CREATED ===");
+        Ok(())
+    }
+
+    fn validate_leverage_ratio(&self, leverage: u8) -> Result<()> {
+        msg!("Validating leverage ratio: {}x", leverage);
+
+        // The market can only tighten the protocol-wide maximum
+        let config = &self.protocol_config.params;
+        require!(
+            leverage >= config.min_leverage && leverage <= self.market.max_leverage(config),
+            ErrorCode::InvalidLeverage
+        );
+
+        Ok(())
+    }
+
+    fn stake_source(&self) -> StakeSource<'_, 'info> {
+        StakeSource {
+            collateral_mint: self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            mint: self.mint.as_deref(),
+            collateral_authority: self.collateral_authority.as_ref(),
+            token_program: self.token_program.as_ref(),
+            vault_token_account: self.vault_token_account.as_deref(),
+            pool_token_vault: self.pool_token_vault.as_deref(),
+            user_vault: &self.user_vault,
+            user_vault_state: &self.user_vault_state,
+            trading_pool_vault: &self.trading_pool_vault,
+            system_program: &self.system_program,
+        }
+    }
+}
+
+#[event]
+pub struct LeveragedPositionCreatedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub market: Pubkey,
+    pub order_id: u64,
+    pub is_long: bool,
+    pub size: u64,
+    pub leverage: u8,
+    pub entry_price: u64,
+    pub collateral_amount: u64,
+    pub required_margin: u64,
+    pub liquidation_price: u64,
+    pub expires_at: i64,
+    pub trading_pool: Pubkey,
+}
\ No newline at end of file
@@ -1,22 +1,23 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
-use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, PriceUpdateV2, VerificationLevel};
-use crate::state::{PositionState, PositionStatus, TradingPool, VaultState, PositionVault};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use crate::oracle;
+use crate::state::{PositionState, PositionType, ProtocolConfig, ConfigParams, FreezeRecord, SanctionsPage, TradingPool, VaultState, PositionVault, CollateralMint, Market, PriceHistory};
+use crate::instructions::{record_market_price, TokenRoute};
 use crate::error::ErrorCode;
-use crate::constants::{BTC_FEED_ID, MAXIMUM_AGE, MIN_LEVERAGE, MAX_LEVERAGE, MIN_POSITION_SIZE};
 
 #[derive(Accounts)]
 #[instruction(
-    is_long: bool,
-    size: u64,
-    leverage: u8,
-    collateral_amount: u64,
+    position_type: PositionType,
+    lower_bound: u64,
+    upper_bound: u64,
     order_id: u64,
 )]
 pub struct CreatePosition<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    
+
     #[account(
         init,
         payer = user,
@@ -33,7 +34,7 @@ pub struct CreatePosition<'info> {
     #[account(
         init,
         payer = user,
-        space = 8 + PositionVault::LEN,
+        space = 8 + PositionVault::INIT_SPACE,
         seeds = [
             b"position_vault".as_ref(),
             position.key().as_ref()
@@ -41,7 +42,7 @@ pub struct CreatePosition<'info> {
         bump
     )]
     pub position_vault: Account<'info, PositionVault>,
-    
+
     // User's personal vault
     #[account(
         mut,
@@ -49,22 +50,22 @@ pub struct CreatePosition<'info> {
         bump = user_vault_state.vault_bump,
     )]
     pub user_vault: SystemAccount<'info>,
-    
+
     #[account(
         mut,
         seeds = [b"vault_state", user.key().as_ref()],
         bump = user_vault_state.state_bump
     )]
     pub user_vault_state: Account<'info, VaultState>,
-    
-    // Trading pool 
+
+    // Trading pool
     #[account(
         mut,
         seeds = [b"trading_pool"],
         bump = trading_pool.bump,
     )]
     pub trading_pool: Account<'info, TradingPool>,
-    
+
     // Trading pool vault
     #[account(
         mut,
@@ -72,105 +73,183 @@ pub struct CreatePosition<'info> {
         bump = trading_pool.vault_bump
     )]
     pub trading_pool_vault: SystemAccount<'info>,
-    
+
+    #[account(
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump,
+        constraint = market.params.is_active @ ErrorCode::MarketInactive
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
     // Pyth price update
     #[account(
         owner = pyth_solana_receiver_sdk::ID,
         constraint = price_update.verification_level == VerificationLevel::Full,
     )]
     pub price_update: Account<'info, PriceUpdateV2>,
-    
+
+    /// CHECK: Instructions sysvar, only needed to read the market's secondary signed price
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    /// CHECK: Sanctions page for `user`'s bucket, may not exist; checked in the handler
+    #[account(
+        seeds = [b"sanctions_page", &[SanctionsPage::bucket_for(&user.key())]],
+        bump
+    )]
+    pub sanctions_page: UncheckedAccount<'info>,
+
+    // SPL collateral; leave all unset to stake native SOL
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump,
+        constraint = collateral_mint.is_enabled @ ErrorCode::CollateralMintDisabled
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> CreatePosition<'info> {
     pub fn create_position(
-        &mut self, 
-        is_long: bool,
-        size: u64,
-        leverage: u8,
-        collateral_amount: u64,
+        &mut self,
+        position_type: PositionType,
+        lower_bound: u64,
+        upper_bound: u64,
         order_id: u64,
-        expires_at: i64,
+        amount: u64,
         bumps: &CreatePositionBumps
     ) -> Result<()> {
         let clock = Clock::get()?;
-        
-        msg!("=== LEVERAGED POSITION CREATION ===");
+        let config = self.protocol_config.params;
+
+        msg!("=== RANGE POSITION CREATION ===");
         msg!("User: {}", self.user.key());
         msg!("Order ID: {}", order_id);
-        msg!("Is Long: {}", is_long);
-        msg!("Size: {}", size);
-        msg!("Leverage: {}x", leverage);
-        msg!("Collateral: {}", collateral_amount);
-
-        // Validate leverage ratio (1-10x maximum)
-        self.validate_leverage_ratio(leverage)?;
-
-        // Verify price update is valid
+        msg!("Position type: {:?}", position_type);
+        msg!("Bounds: [{}, {}]", lower_bound, upper_bound);
+        msg!("Amount: {}", amount);
+
+        ensure_can_open_position(
+            &self.protocol_config,
+            &self.freeze_record,
+            &self.sanctions_page,
+            &self.user.key(),
+        )?;
+
+        // Leveraged positions go through create_leveraged_position
         require!(
-            self.price_update.verification_level == VerificationLevel::Full,
-            ErrorCode::UnverifiedPriceUpdate
+            position_type != PositionType::Leveraged,
+            ErrorCode::InvalidPositionType
         );
-        
-        // Get current BTC price for entry
-        let price_data = self.price_update.get_price_no_older_than(
-            &clock,
-            MAXIMUM_AGE,
-            &get_feed_id_from_hex(BTC_FEED_ID)?,
-        ).map_err(|_| error!(ErrorCode::StalePriceFeed))?;
-        
-        let entry_price = price_data.price as u64;
-        msg!("Entry price: {}", entry_price);
 
-        // Create Position Account using derived PDA
-        self.create_position_account(is_long, size, leverage, collateral_amount, entry_price, order_id, expires_at, bumps)?;
+        check_position_size(
+            &self.market,
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            &config,
+            amount,
+        )?;
+
+        // Get the market price for entry
+        let oracle_price = read_entry_price(
+            &self.price_update,
+            &self.market,
+            &mut self.price_history,
+            self.instructions_sysvar.as_deref(),
+            &clock,
+            &config,
+        )?;
 
-        // Create Position Vault Account
-        self.create_position_vault_account(bumps)?;
+        let entry_price = oracle_price.price;
+        msg!("Entry price: {}", entry_price);
 
-        // Transfer collateral to position vault
-        self.transfer_collateral_to_vault(collateral_amount)?;
+        // Validate the band against the whole confidence interval
+        self.validate_range(lower_bound, upper_bound, oracle_price.bid(), oracle_price.ask())?;
 
-        // Calculate margin requirements
-        let margin_requirements = self.calculate_margin_requirements(size, leverage, entry_price)?;
-        msg!("Required margin: {}", margin_requirements.required_margin);
-        msg!("Maintenance margin: {}", margin_requirements.maintenance_margin);
+        let expires_at = clock.unix_timestamp
+            .checked_add(config.range_position_duration)
+            .ok_or(ErrorCode::MathOverflow)?;
 
-        // Set liquidation thresholds
-        let liquidation_thresholds = self.set_liquidation_thresholds(&margin_requirements, entry_price, is_long)?;
-        msg!("Liquidation price: {}", liquidation_thresholds.liquidation_price);
+        // Create Position Account using derived PDA
+        self.position.set_inner(PositionState::new_range(
+            self.user.key(),
+            order_id,
+            position_type,
+            lower_bound,
+            upper_bound,
+            amount,
+            entry_price,
+            expires_at,
+            bumps.position,
+        ));
+        self.position.collateral_mint = self.collateral_mint.as_ref()
+            .map(|collateral_mint| collateral_mint.mint)
+            .unwrap_or_default();
+        self.position.market = self.market.key();
 
-        // Check if user has sufficient collateral
-        require!(
-            self.sufficient_collateral_check(collateral_amount, &margin_requirements)?,
-            ErrorCode::InsufficientFunds
-        );
+        // Create Position Vault Account
+        self.position_vault.position = self.position.key();
+        self.position_vault.balance = 0;
+        self.position_vault.bump = bumps.position_vault;
 
-        // Initialize health monitoring
-        self.initialize_health_monitoring()?;
+        // Transfer stake to the trading pool
+        transfer_stake_to_pool(&self.stake_source(), amount)?;
+        self.position_vault.balance = amount;
 
         // Update pool active positions
-        self.update_pool_active_positions(size)?;
-
-        // Update position with calculated values
-        let position = &mut self.position;
-        position.liquidation_price = Some(liquidation_thresholds.liquidation_price);
-        position.required_margin = margin_requirements.required_margin;
-        position.maintenance_margin = margin_requirements.maintenance_margin;
-
-        emit!(LeveragedPositionCreatedEvent {
-            position: position.key(),
-            user: position.user,
-            order_id: position.order_id,
-            is_long: position.is_long,
-            size: position.size,
-            leverage: position.leverage,
-            entry_price: position.entry_price,
-            collateral_amount: position.collateral_amount,
-            required_margin: margin_requirements.required_margin,
-            liquidation_price: liquidation_thresholds.liquidation_price,
-            expires_at: position.expires_at,
+        add_open_position(
+            self.collateral_mint.as_deref_mut().map(|collateral_mint| &mut **collateral_mint),
+            &mut self.trading_pool,
+            &mut self.user_vault_state,
+            amount,
+            amount,
+        )?;
+
+        emit!(RangePositionCreatedEvent {
+            position: self.position.key(),
+            user: self.position.user,
+            market: self.market.key(),
+            order_id,
+            position_type,
+            lower_bound,
+            upper_bound,
+            amount,
+            entry_price,
+            expires_at,
             trading_pool: self.trading_pool.key(),
         });
 
@@ -178,237 +257,227 @@ impl<'info> CreatePosition<'info> {
         Ok(())
     }
 
-    fn validate_leverage_ratio(&self, leverage: u8) -> Result<()> {
-        msg!("Validating leverage ratio: {}x", leverage);
-        
-        require!(
-            leverage >= MIN_LEVERAGE && leverage <= MAX_LEVERAGE,
-            ErrorCode::InvalidLeverage
-        );
-        
-        Ok(())
-    }
+    fn validate_range(&self, lower_bound: u64, upper_bound: u64, bid: u64, ask: u64) -> Result<()> {
+        msg!("Validating price range");
 
-    fn create_position_account(
-        &mut self,
-        is_long: bool,
-        size: u64,
-        leverage: u8,
-        collateral_amount: u64,
-        entry_price: u64,
-        order_id: u64,
-        expires_at: i64,
-        bumps: &CreatePositionBumps
-    ) -> Result<()> {
-        msg!("Creating position account with derived PDA");
-        
-        require!(size >= MIN_POSITION_SIZE, ErrorCode::PositionTooSmall);
-        require!(collateral_amount > 0, ErrorCode::InvalidCollateralAmount);
-        require!(expires_at > Clock::get()?.unix_timestamp, ErrorCode::InvalidExpirationTime);
-
-        // Initialize position state
-        let position = &mut self.position;
-        *position = PositionState::new(
-            self.user.key(),
-            order_id,
-            is_long,
-            size,
-            entry_price,
-            collateral_amount,
-            leverage,
-            expires_at,
-            bumps.position,
+        require!(
+            lower_bound > 0 && lower_bound < upper_bound,
+            ErrorCode::InvalidRange
         );
 
-        // Set additional leveraged position fields
-        position.status = PositionStatus::Active;
-        
-        Ok(())
-    }
-
-    fn create_position_vault_account(&mut self, bumps: &CreatePositionBumps) -> Result<()> {
-        msg!("Creating position vault account");
-        
-        let position_vault = &mut self.position_vault;
-        position_vault.position = self.position.key();
-        position_vault.balance = 0;
-        position_vault.bump = bumps.position_vault;
-        
-        Ok(())
-    }
-
-    fn transfer_collateral_to_vault(&mut self, collateral_amount: u64) -> Result<()> {
-        msg!("Transferring collateral to position vault");
-        
-        // Check user vault balance
-        let user_vault_balance = self.user_vault.lamports();
+        // The entry price, including its confidence interval, must sit inside
+        // the band, otherwise the outcome may already be decided at creation
         require!(
-            user_vault_balance >= collateral_amount,
-            ErrorCode::InsufficientVaultBalance
+            bid > lower_bound && ask < upper_bound,
+            ErrorCode::InvalidRange
         );
 
-        // Transfer from user vault to position vault (via trading pool for now)
-        let user_vault_seeds = &[
-            b"vault".as_ref(),
-            self.user_vault_state.to_account_info().key.as_ref(),
-            &[self.user_vault_state.vault_bump],
-        ];
-        let signer_seeds = &[&user_vault_seeds[..]];
-        
-        let cpi_ctx = CpiContext::new_with_signer(
-            self.system_program.to_account_info(),
-            Transfer {
-                from: self.user_vault.to_account_info(),
-                to: self.trading_pool_vault.to_account_info(),
-            },
-            signer_seeds,
-        );
-        
-        transfer(cpi_ctx, collateral_amount)?;
-        
-        // Update position vault balance tracking
-        self.position_vault.balance = collateral_amount;
-        
         Ok(())
     }
 
-    fn calculate_margin_requirements(&self, size: u64, leverage: u8, entry_price: u64) -> Result<MarginRequirements> {
-        msg!("Calculating margin requirements");
-        
-        // Position value = size * entry_price
-        let position_value = size.checked_mul(entry_price)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        // Required margin = position_value / leverage
-        let required_margin = position_value.checked_div(leverage as u64)
-            .ok_or(ErrorCode::DivisionByZero)?;
-        
-        // Maintenance margin = required_margin * 0.5 (50% of initial margin)
-        let maintenance_margin = required_margin.checked_div(2)
-            .ok_or(ErrorCode::DivisionByZero)?;
-        
-        Ok(MarginRequirements {
-            required_margin,
-            maintenance_margin,
-            position_value,
-        })
+    fn stake_source(&self) -> StakeSource<'_, 'info> {
+        StakeSource {
+            collateral_mint: self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            mint: self.mint.as_deref(),
+            collateral_authority: self.collateral_authority.as_ref(),
+            token_program: self.token_program.as_ref(),
+            vault_token_account: self.vault_token_account.as_deref(),
+            pool_token_vault: self.pool_token_vault.as_deref(),
+            user_vault: &self.user_vault,
+            user_vault_state: &self.user_vault_state,
+            trading_pool_vault: &self.trading_pool_vault,
+            system_program: &self.system_program,
+        }
     }
+}
 
-    fn set_liquidation_thresholds(&self, margin_req: &MarginRequirements, entry_price: u64, is_long: bool) -> Result<LiquidationThresholds> {
-        msg!("Setting liquidation thresholds");
-        
-        // Calculate liquidation price based on maintenance margin
-        // For long: liquidation_price = entry_price - (maintenance_margin / size)
-        // For short: liquidation_price = entry_price + (maintenance_margin / size)
-        
-        let size = self.position.size;
-        let price_impact = margin_req.maintenance_margin.checked_div(size)
-            .ok_or(ErrorCode::DivisionByZero)?;
-        
-        let liquidation_price = if is_long {
-            entry_price.checked_sub(price_impact)
-                .ok_or(ErrorCode::MathOverflow)?
-        } else {
-            entry_price.checked_add(price_impact)
-                .ok_or(ErrorCode::MathOverflow)?
-        };
-        
-        Ok(LiquidationThresholds {
-            liquidation_price,
-            maintenance_margin_ratio: 50, // 50% of initial margin
-        })
-    }
+// Checks every new position passes before a price is read or funds move
+pub fn ensure_can_open_position(
+    protocol_config: &ProtocolConfig,
+    freeze_record: &UncheckedAccount,
+    sanctions_page: &UncheckedAccount,
+    user: &Pubkey,
+) -> Result<()> {
+    // Reject while the protocol is paused
+    require!(!protocol_config.is_paused, ErrorCode::ProgramPaused);
 
-    fn sufficient_collateral_check(&self, collateral_amount: u64, margin_req: &MarginRequirements) -> Result<bool> {
-        msg!("Checking sufficient collateral");
-        
-        // Collateral must be at least equal to required margin
-        let is_sufficient = collateral_amount >= margin_req.required_margin;
-        
-        if !is_sufficient {
-            msg!("Insufficient collateral: {} < {}", collateral_amount, margin_req.required_margin);
-        }
-        
-        Ok(is_sufficient)
-    }
+    // No new positions in emergency withdraw-only mode
+    require!(!protocol_config.is_emergency, ErrorCode::EmergencyModeActive);
 
-    fn initialize_health_monitoring(&mut self) -> Result<()> {
-        msg!("Initializing health monitoring");
-        
-        let current_time = Clock::get()?.unix_timestamp;
-        let position = &mut self.position;
-        
-        // Set initial health check timestamp
-        position.last_health_check = current_time;
-        position.last_reward_claim = current_time;
-        
-        // Initial status is Active, will be updated by health monitoring
-        position.status = PositionStatus::Active;
-        
-        Ok(())
-    }
+    // Reject frozen wallets
+    FreezeRecord::ensure_not_frozen(&freeze_record.to_account_info())?;
 
-    fn update_pool_active_positions(&mut self, size: u64) -> Result<()> {
-        msg!("Updating pool active positions");
-        
-        // Update trading pool
-        self.trading_pool.total_active_amount = self.trading_pool.total_active_amount
-            .checked_add(size)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
-            .checked_add(self.position.collateral_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        // Update user vault state
-        self.user_vault_state.active_positions = self.user_vault_state.active_positions
-            .checked_add(1)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        Ok(())
-    }
+    // Reject sanctioned wallets
+    SanctionsPage::ensure_not_sanctioned(&sanctions_page.to_account_info(), user)
 }
 
-#[derive(Debug)]
-struct MarginRequirements {
-    required_margin: u64,
-    maintenance_margin: u64,
-    position_value: u64,
+// Size limits are configured in lamports, protocol-wide and per market
+pub fn check_position_size(
+    market: &Market,
+    collateral_mint: Option<&CollateralMint>,
+    config: &ConfigParams,
+    size: u64,
+) -> Result<()> {
+    let native_size = match collateral_mint {
+        Some(collateral_mint) => collateral_mint.to_native_units(size)?,
+        None => size,
+    };
+    market.check_position_size(config, native_size)
 }
 
-#[derive(Debug)]
-struct LiquidationThresholds {
-    liquidation_price: u64,
-    maintenance_margin_ratio: u8,
+// Market price a new position enters at
+pub fn read_entry_price(
+    price_update: &Account<PriceUpdateV2>,
+    market: &Account<Market>,
+    price_history: &mut PriceHistory,
+    instructions_sysvar: Option<&AccountInfo>,
+    clock: &Clock,
+    config: &ConfigParams,
+) -> Result<oracle::OraclePrice> {
+    let oracle_price = oracle::get_market_price_with_fallback(
+        price_update,
+        market,
+        instructions_sysvar,
+        clock,
+        config.maximum_age,
+    )?;
+    // New positions are paused while the market's circuit breaker is tripped, including
+    // by this price. The check records nothing, so a rejected create leaves no state
+    // behind; update_circuit_breaker and the other price readers persist the trip.
+    require!(
+        !price_history.is_tripped_after(market, &oracle_price),
+        ErrorCode::CircuitBreakerTripped
+    );
+    record_market_price(market, price_history, clock.slot, &oracle_price);
+
+    oracle_price.check_confidence(config.max_confidence_bps)?;
+
+    Ok(oracle_price)
 }
 
-// Position Vault to hold collateral for individual positions
-#[account]
-pub struct PositionVault {
-    pub position: Pubkey,
-    pub balance: u64,
-    pub bump: u8,
+// Accounts a new position's stake moves between: the owner's vault into the trading
+// pool vault, or for token collateral the owner's token vault into the mint's pool vault
+pub struct StakeSource<'a, 'info> {
+    pub collateral_mint: Option<&'a CollateralMint>,
+    pub mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    pub collateral_authority: Option<&'a UncheckedAccount<'info>>,
+    pub token_program: Option<&'a Interface<'info, TokenInterface>>,
+    pub vault_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub pool_token_vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub user_vault: &'a SystemAccount<'info>,
+    pub user_vault_state: &'a Account<'info, VaultState>,
+    pub trading_pool_vault: &'a SystemAccount<'info>,
+    pub system_program: &'a Program<'info, System>,
 }
 
-impl PositionVault {
-    pub const LEN: usize = 8 + // discriminator
-        32 + // position
-        8 + // balance
-        1; // bump
+pub fn transfer_stake_to_pool(source: &StakeSource, amount: u64) -> Result<()> {
+    msg!("Transferring stake to trading pool");
+
+    let token_route = TokenRoute::resolve(
+        source.collateral_mint,
+        source.mint,
+        source.collateral_authority,
+        source.token_program,
+    )?;
+
+    match token_route {
+        Some(route) => {
+            let vault_token_account = route.check_vault_token_account(
+                source.vault_token_account,
+                &source.user_vault_state.key(),
+            )?;
+            let pool_token_vault = route.check_program_vault(
+                source.pool_token_vault,
+                &source.collateral_mint.ok_or(ErrorCode::MissingCollateralAccounts)?.pool_token_vault,
+            )?;
+
+            let vault_balance = source.vault_token_account.map(|account| account.amount).unwrap_or(0);
+            require!(
+                vault_balance >= amount,
+                ErrorCode::InsufficientVaultBalance
+            );
+
+            route.transfer_from_program(vault_token_account, pool_token_vault, amount)?;
+        },
+        None => {
+            let user_vault_balance = source.user_vault.lamports();
+            require!(
+                user_vault_balance >= amount,
+                ErrorCode::InsufficientVaultBalance
+            );
+
+            let user_vault_seeds = &[
+                b"vault".as_ref(),
+                source.user_vault_state.to_account_info().key.as_ref(),
+                &[source.user_vault_state.vault_bump],
+            ];
+            let signer_seeds = &[&user_vault_seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                source.system_program.to_account_info(),
+                Transfer {
+                    from: source.user_vault.to_account_info(),
+                    to: source.trading_pool_vault.to_account_info(),
+                },
+                signer_seeds,
+            );
+
+            transfer(cpi_ctx, amount)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Books a new position on the trading pool, or on the mint's pool accounting for token
+// collateral, and counts it against the owner's vault
+pub fn add_open_position(
+    collateral_mint: Option<&mut CollateralMint>,
+    trading_pool: &mut TradingPool,
+    user_vault_state: &mut VaultState,
+    active_amount: u64,
+    pool_amount: u64,
+) -> Result<()> {
+    msg!("Updating pool active positions");
+
+    match collateral_mint {
+        Some(collateral_mint) => {
+            collateral_mint.total_active_amount = collateral_mint.total_active_amount
+                .checked_add(active_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+                .checked_add(pool_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        },
+        None => {
+            trading_pool.total_active_amount = trading_pool.total_active_amount
+                .checked_add(active_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            trading_pool.total_pool_amount = trading_pool.total_pool_amount
+                .checked_add(pool_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+    }
+
+    user_vault_state.active_positions = user_vault_state.active_positions
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(())
 }
 
 #[event]
-pub struct LeveragedPositionCreatedEvent {
+pub struct RangePositionCreatedEvent {
     pub position: Pubkey,
     pub user: Pubkey,
+    pub market: Pubkey,
     pub order_id: u64,
-    pub is_long: bool,
-    pub size: u64,
-    pub leverage: u8,
+    pub position_type: PositionType,
+    pub lower_bound: u64,
+    pub upper_bound: u64,
+    pub amount: u64,
     pub entry_price: u64,
-    pub collateral_amount: u64,
-    pub required_margin: u64,
-    pub liquidation_price: u64,
     pub expires_at: i64,
     pub trading_pool: Pubkey,
-}
\ No newline at end of file
+}
@@ -1,7 +1,9 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-use crate::state::{VaultState, PositionAccount, PoolLiquidity, TradingPool, StakeRewards};
+use crate::state::{VaultState, PositionAccount, PoolLiquidity, TradingPool, StakeRewards, RewardPool, ProtocolConfig, FreezeRecord, SanctionsPage, FeeLedger, FeeCategory, CollateralMint, ReferralRecord};
+use crate::instructions::{distribute_fee, ensure_collateral_matches, retain_token_fee, FeeRoute, TokenRoute};
 use crate::error::ErrorCode;
 
 #[derive(Accounts)]
@@ -35,11 +37,18 @@ pub struct Deposit<'info> {
 
     #[account(
         mut,
-        seeds = [b"trading_pool", &pool_id.to_le_bytes()],
+        seeds = [b"trading_pool"],
         bump = trading_pool.bump
     )]
     pub trading_pool: Account<'info, TradingPool>,
 
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
     #[account(
         mut,
         seeds = [b"pool_liquidity"],
@@ -54,6 +63,34 @@ pub struct Deposit<'info> {
     )]
     pub protocol_treasury: SystemAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault"],
+        bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    // Optional referrer, receives the referrer share of fees. Bound to `user` on the
+    // first deposit; later deposits may only pass the bound referrer.
+    #[account(mut)]
+    pub referrer: Option<SystemAccount<'info>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + ReferralRecord::INIT_SPACE,
+        seeds = [b"referral", user.key().as_ref()],
+        bump
+    )]
+    pub referral: Account<'info, ReferralRecord>,
+
     #[account(
         init_if_needed,
         payer = user,
@@ -63,6 +100,57 @@ pub struct Deposit<'info> {
     )]
     pub stake_rewards: Account<'info, StakeRewards>,
 
+    #[account(
+        mut,
+        seeds = [b"reward_pool"],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    /// CHECK: Sanctions page for `user`'s bucket, may not exist; checked in the handler
+    #[account(
+        seeds = [b"sanctions_page", &[SanctionsPage::bucket_for(&user.key())]],
+        bump
+    )]
+    pub sanctions_page: UncheckedAccount<'info>,
+
+    // SPL collateral; leave all unset to deposit native SOL
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub user_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -71,23 +159,50 @@ impl<'info> Deposit<'info> {
         let clock = Clock::get()?;
         let current_time = clock.unix_timestamp;
 
-        // OFAC Check - Basic validation (in production, this would integrate with actual OFAC service)
+        // Reject while the protocol is paused
+        require!(!self.protocol_config.is_paused, ErrorCode::ProgramPaused);
+
+        // Reject frozen wallets
+        FreezeRecord::ensure_not_frozen(&self.freeze_record.to_account_info())?;
+
+        // OFAC Check against the on-chain sanctions list
         self.validate_ofac_compliance()?;
+
+        // Bind the referrer on the wallet's first deposit
+        self.register_referral(bumps.referral, current_time)?;
         
+        let token_route = self.token_route()?;
+        if let Some(collateral_mint) = &self.collateral_mint {
+            require!(collateral_mint.is_enabled, ErrorCode::CollateralMintDisabled);
+        }
+
+        // Order limits and fee tiers are configured in lamports
+        let native_amount = match &self.collateral_mint {
+            Some(collateral_mint) => collateral_mint.to_native_units(amount)?,
+            None => amount,
+        };
+
         // Validate Deposit Amount
         require!(
-            amount >= VaultState::MIN_ORDER_AMOUNT,
+            native_amount >= VaultState::MIN_ORDER_AMOUNT,
             ErrorCode::AmountTooSmall
         );
 
         require!(
-            amount <= VaultState::MAX_ORDER_AMOUNT,
+            native_amount <= VaultState::MAX_ORDER_AMOUNT,
             ErrorCode::AmountTooLarge
         );
 
         // Validate user has sufficient balance
+        let user_balance = match &token_route {
+            Some(route) => {
+                route.check_owner_account(self.user_token_account.as_deref(), &self.user.key())?;
+                self.user_token_account.as_ref().map(|account| account.amount).unwrap_or(0)
+            },
+            None => self.user.lamports(),
+        };
         require!(
-            self.user.lamports() >= amount,
+            user_balance >= amount,
             ErrorCode::InsufficientBalance
         );
 
@@ -104,71 +219,30 @@ impl<'info> Deposit<'info> {
             self.position_account.lock_end_time = current_time + (30 * 24 * 60 * 60); // 30 days default
             self.position_account.is_active = true;
             self.position_account.created_at = current_time;
+            self.position_account.collateral_mint = token_route.as_ref()
+                .map(|route| route.mint_key)
+                .unwrap_or_default();
             self.position_account.bump = bumps.position_account;
         }
 
-        // Transfer Tokens to Pool Vault
-        let cpi_program = self.system_program.to_account_info();
-        let cpi_accounts = Transfer {
-            from: self.user.to_account_info(),
-            to: self.vault.to_account_info()
-        };
-
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer(cpi_ctx, amount)?;
+        // A position account holds a single collateral
+        ensure_collateral_matches(self.position_account.collateral_mint, &token_route)?;
 
-        // Calculate Deposit Fee (10-50 basis points)
-        let fee_rate = self.calculate_deposit_fee_rate(amount)?;
+        // Calculate Deposit Fee from the configured tiers
+        let fee_rate = self.calculate_deposit_fee_rate(native_amount)?;
         let fee_amount = (amount * fee_rate) / 10000; // basis points
         let net_deposit = amount - fee_amount;
 
-        // Send Fee to Protocol Treasury
-        if fee_amount > 0 {
-            let vault_seeds = &[
-                b"vault",
-                self.vault_state.key().as_ref(),
-                &[self.vault_state.vault_bump]
-            ];
-            let vault_signer_seeds = &[&vault_seeds[..]];
-
-            let fee_transfer_cpi = CpiContext::new_with_signer(
-                self.system_program.to_account_info(),
-                Transfer {
-                    from: self.vault.to_account_info(),
-                    to: self.protocol_treasury.to_account_info(),
-                },
-                vault_signer_seeds
-            );
-
-            transfer(fee_transfer_cpi, fee_amount)?;
-        }
-
-        // Calculate User Pool Shares
-        let user_shares = self.calculate_pool_shares(net_deposit)?;
+        let user_shares = match token_route {
+            Some(route) => self.deposit_tokens(&route, amount, fee_amount, net_deposit)?,
+            None => self.deposit_sol(amount, fee_amount, net_deposit, current_time)?,
+        };
 
         // Update Position Account
         self.position_account.amount += net_deposit;
         self.position_account.shares += user_shares;
         self.position_account.last_deposit_time = current_time;
 
-        // Auto-stake for Rewards
-        self.auto_stake_for_rewards(user_shares, current_time)?;
-
-        // Update Pool Liquidity State
-        self.pool_liquidity.total_liquidity += net_deposit;
-        self.pool_liquidity.available_liquidity += net_deposit;
-        self.pool_liquidity.total_shares += user_shares;
-        self.pool_liquidity.last_updated = current_time;
-
-        // Update Trading Pool
-        self.trading_pool.total_pool_amount += net_deposit;
-        self.trading_pool.total_active_amount += net_deposit;
-        self.trading_pool.last_updated = current_time;
-
-        // Update Vault State
-        self.vault_state.total_deposits += amount;
-        self.vault_state.last_updated = current_time;
-
         emit!(DepositEvent {
             user: self.user.key(),
             pool_id,
@@ -199,51 +273,193 @@ impl<'info> Deposit<'info> {
         Ok(())
     }
 
+    fn token_route(&self) -> Result<Option<TokenRoute<'info>>> {
+        TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )
+    }
+
+    // Returns the pool shares minted for `net_deposit`
+    fn deposit_sol(&mut self, amount: u64, fee_amount: u64, net_deposit: u64, current_time: i64) -> Result<u64> {
+        // Transfer Tokens to Pool Vault
+        let cpi_program = self.system_program.to_account_info();
+        let cpi_accounts = Transfer {
+            from: self.user.to_account_info(),
+            to: self.vault.to_account_info()
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer(cpi_ctx, amount)?;
+
+        // Split Fee between LPs, treasury, insurance fund and referrer
+        if fee_amount > 0 {
+            self.distribute_deposit_fee(fee_amount, current_time)?;
+        }
+
+        // Calculate User Pool Shares
+        let user_shares = self.pool_liquidity.shares_for_deposit(net_deposit)?;
+
+        // Auto-stake for Rewards
+        self.auto_stake_for_rewards(user_shares, current_time)?;
+
+        // Update Pool Liquidity State
+        self.pool_liquidity.mint_shares(user_shares, net_deposit, current_time)?;
+
+        // Update Trading Pool
+        self.trading_pool.total_pool_amount += net_deposit;
+        self.trading_pool.total_active_amount += net_deposit;
+        self.trading_pool.last_updated = current_time;
+
+        // Update Vault State
+        self.vault_state.total_deposits += amount;
+        self.vault_state.last_updated = current_time;
+
+        Ok(user_shares)
+    }
+
+    // Token deposits keep their own share accounting on CollateralMint and are not
+    // auto-staked: staking rewards are paid on SOL pool shares
+    fn deposit_tokens(&mut self, route: &TokenRoute<'info>, amount: u64, fee_amount: u64, net_deposit: u64) -> Result<u64> {
+        let vault_token_account = route.check_vault_token_account(
+            self.vault_token_account.as_deref(),
+            &self.vault_state.key(),
+        )?;
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        let pool_token_vault = route.check_program_vault(
+            self.pool_token_vault.as_deref(),
+            &collateral_mint.pool_token_vault,
+        )?;
+        let user_token_account = self.user_token_account.as_ref()
+            .ok_or(ErrorCode::MissingCollateralAccounts)?
+            .to_account_info();
+
+        route.transfer_from_user(
+            user_token_account,
+            vault_token_account.clone(),
+            self.user.to_account_info(),
+            amount,
+        )?;
+
+        // The whole fee moves to the pool token vault, see retain_token_fee
+        if fee_amount > 0 {
+            route.transfer_from_program(vault_token_account, pool_token_vault, fee_amount)?;
+            retain_token_fee(
+                collateral_mint,
+                FeeCategory::Deposit,
+                self.user.key(),
+                fee_amount,
+                &self.protocol_config.params.fee_split,
+            )?;
+            collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+                .checked_add(fee_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        let user_shares = if collateral_mint.total_liquidity == 0 {
+            net_deposit
+        } else {
+            ((net_deposit as u128 * collateral_mint.total_shares as u128)
+                / collateral_mint.total_liquidity as u128) as u64
+        };
+
+        collateral_mint.total_liquidity = collateral_mint.total_liquidity
+            .checked_add(net_deposit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        collateral_mint.total_shares = collateral_mint.total_shares
+            .checked_add(user_shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(user_shares)
+    }
+
     fn validate_ofac_compliance(&self) -> Result<()> {
-        // Basic OFAC validation - in production, integrate with actual OFAC service
-        // For now, just check if user is not a known blacklisted address
-        
-        // Example blacklist check (replace with actual OFAC integration)
-        let blacklisted_addresses = [
-            // Add known sanctioned addresses here
-        ];
+        // Check the user against the on-chain denylist maintained by the compliance authority
+        SanctionsPage::ensure_not_sanctioned(&self.sanctions_page.to_account_info(), &self.user.key())
+    }
 
-        for &blacklisted in blacklisted_addresses.iter() {
+    fn register_referral(&mut self, bump: u8, current_time: i64) -> Result<()> {
+        if !self.referral.is_initialized {
+            // No self-referral rebates
+            let referrer = self.referrer.as_ref()
+                .map(|referrer| referrer.key())
+                .filter(|referrer| *referrer != self.user.key())
+                .unwrap_or_default();
+
+            self.referral.set_inner(ReferralRecord {
+                user: self.user.key(),
+                referrer,
+                is_initialized: true,
+                created_at: current_time,
+                bump,
+            });
+
+            if self.referral.has_referrer() {
+                emit!(ReferrerRegisteredEvent {
+                    user: self.user.key(),
+                    referrer,
+                    timestamp: current_time,
+                });
+            }
+        }
+
+        if let Some(referrer) = &self.referrer {
             require!(
-                self.user.key() != blacklisted,
-                ErrorCode::OFACViolation
+                self.referral.has_referrer() && referrer.key() == self.referral.referrer,
+                ErrorCode::ReferrerNotRegistered
             );
         }
 
         Ok(())
     }
 
-    fn calculate_deposit_fee_rate(&self, amount: u64) -> Result<u64> {
-        // Calculate fee rate based on deposit amount (10-50 basis points)
-        let fee_rate = if amount >= 100_000_000_000 { // >= 100 SOL
-            10 // 0.1% for large deposits
-        } else if amount >= 10_000_000_000 { // >= 10 SOL
-            20 // 0.2% for medium deposits
-        } else if amount >= 1_000_000_000 { // >= 1 SOL
-            30 // 0.3% for small deposits
-        } else {
-            50 // 0.5% for very small deposits
+    fn distribute_deposit_fee(&mut self, fee_amount: u64, current_time: i64) -> Result<()> {
+        let vault_state_key = self.vault_state.key();
+        let vault_seeds = &[
+            b"vault",
+            vault_state_key.as_ref(),
+            &[self.vault_state.vault_bump]
+        ];
+        let vault_signer_seeds = &[&vault_seeds[..]];
+
+        // Checked against the registered referrer in register_referral
+        let referrer = self.referrer.as_ref().map(|referrer| referrer.to_account_info());
+
+        let route = FeeRoute {
+            source: self.vault.to_account_info(),
+            source_signer_seeds: vault_signer_seeds,
+            lp_destination: self.trading_pool_vault.to_account_info(),
+            treasury_destination: self.protocol_treasury.to_account_info(),
+            insurance_vault: self.insurance_vault.to_account_info(),
+            referrer,
+            system_program: self.system_program.to_account_info(),
         };
 
-        Ok(fee_rate)
+        let split = distribute_fee(
+            &route,
+            FeeCategory::Deposit,
+            self.user.key(),
+            fee_amount,
+            &self.protocol_config.params.fee_split,
+            &mut self.fee_ledger,
+        )?;
+
+        // LP share raises the value of every existing pool share
+        self.pool_liquidity.add_income(split.lp, current_time)?;
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .checked_add(split.lp)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
     }
 
-    fn calculate_pool_shares(&self, net_deposit: u64) -> Result<u64> {
-        // Calculate shares based on current pool state
-        let shares = if self.pool_liquidity.total_liquidity == 0 {
-            // First deposit - 1:1 ratio
-            net_deposit
-        } else {
-            // Calculate proportional shares
-            (net_deposit * self.pool_liquidity.total_shares) / self.pool_liquidity.total_liquidity
-        };
+    fn calculate_deposit_fee_rate(&self, amount: u64) -> Result<u64> {
+        // Fee rate based on deposit amount, from the tiers in ProtocolConfig
+        let fee_rate = self.protocol_config.params.deposit_fee_bps(amount);
 
-        Ok(shares)
+        Ok(fee_rate)
     }
 
     fn auto_stake_for_rewards(&mut self, shares: u64, current_time: i64) -> Result<()> {
@@ -251,14 +467,25 @@ impl<'info> Deposit<'info> {
         if !self.stake_rewards.is_initialized {
             self.stake_rewards.user = self.user.key();
             self.stake_rewards.total_staked = 0;
+            self.stake_rewards.unstaked_shares = 0;
             self.stake_rewards.pending_rewards = 0;
+            self.stake_rewards.reward_debt = 0;
+            self.stake_rewards.total_claimed = 0;
             self.stake_rewards.last_reward_time = current_time;
             self.stake_rewards.is_initialized = true;
         }
 
+        // Settle rewards earned on the existing stake before it changes
+        self.reward_pool.accrue(current_time)?;
+        self.stake_rewards.settle_pending(&self.reward_pool, current_time)?;
+
         // Auto-stake the new shares
         self.stake_rewards.total_staked += shares;
         self.stake_rewards.last_stake_time = current_time;
+        self.reward_pool.total_staked = self.reward_pool.total_staked
+            .checked_add(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.stake_rewards.checkpoint(&self.reward_pool)?;
 
         emit!(AutoStakeEvent {
             user: self.user.key(),
@@ -300,6 +527,13 @@ pub struct PositionUpdatedEvent {
     pub new_shares: u64,
 }
 
+#[event]
+pub struct ReferrerRegisteredEvent {
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct AutoStakeEvent {
     pub user: Pubkey,
@@ -307,66 +541,3 @@ pub struct AutoStakeEvent {
     pub total_staked: u64,
     pub timestamp: i64,
 }
-
-// Extended PositionAccount structure
-#[account]
-pub struct PositionAccount {
-    pub owner: Pubkey,              // 32 bytes
-    pub pool_id: u64,               // 8 bytes
-    pub amount: u64,                // 8 bytes
-    pub shares: u64,                // 8 bytes
-    pub lock_start_time: i64,       // 8 bytes
-    pub lock_end_time: i64,         // 8 bytes
-    pub last_deposit_time: i64,     // 8 bytes
-    pub is_active: bool,            // 1 byte
-    pub created_at: i64,            // 8 bytes
-    pub bump: u8,                   // 1 byte
-}
-
-impl PositionAccount {
-    pub const INIT_SPACE: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 1; // 90 bytes
-}
-
-// StakeRewards structure
-#[account]
-pub struct StakeRewards {
-    pub user: Pubkey,               // 32 bytes
-    pub total_staked: u64,          // 8 bytes
-    pub pending_rewards: u64,       // 8 bytes
-    pub last_reward_time: i64,      // 8 bytes
-    pub last_stake_time: i64,       // 8 bytes
-    pub is_initialized: bool,       // 1 byte
-}
-
-impl StakeRewards {
-    pub const INIT_SPACE: usize = 32 + 8 + 8 + 8 + 8 + 1; // 65 bytes
-}
-
-// Extended VaultState constants
-impl VaultState {
-    pub const MIN_ORDER_AMOUNT: u64 = 100_000; // 0.0001 SOL
-    pub const MAX_ORDER_AMOUNT: u64 = 1_000_000_000_000; // 1000 SOL
-}
-
-// Extended PoolLiquidity structure
-#[account]
-pub struct PoolLiquidity {
-    pub total_liquidity: u64,       // 8 bytes
-    pub available_liquidity: u64,   // 8 bytes
-    pub locked_liquidity: u64,      // 8 bytes
-    pub total_shares: u64,          // 8 bytes
-    pub last_updated: i64,          // 8 bytes
-    pub bump: u8,                   // 1 byte
-}
-
-impl PoolLiquidity {
-    pub const INIT_SPACE: usize = 8 + 8 + 8 + 8 + 8 + 1; // 41 bytes
-}
-
-// Additional error codes
-impl ErrorCode {
-    pub const AmountTooSmall: ErrorCode = ErrorCode::AmountTooSmall;
-    pub const AmountTooLarge: ErrorCode = ErrorCode::AmountTooLarge;
-    pub const InsufficientBalance: ErrorCode = ErrorCode::InsufficientBalance;
-    pub const OFACViolation: ErrorCode = ErrorCode::OFACViolation;
-}
\ No newline at end of file
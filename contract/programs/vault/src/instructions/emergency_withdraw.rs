@@ -0,0 +1,419 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::state::{
+    CollateralMint, FreezeRecord, PoolLiquidity, PositionAccount, PositionState, ProtocolConfig, RewardPool, StakeRewards, TradingPool, VaultState,
+};
+use crate::instructions::{ensure_collateral_matches, TokenRoute};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault_state.key().as_ref()],
+        bump = vault_state.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = vault_state.state_bump
+    )]
+    pub vault_state: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [b"position", user.key().as_ref()],
+        bump = position_account.bump,
+        constraint = position_account.owner == user.key() @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub position_account: Account<'info, PositionAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_liquidity"],
+        bump = pool_liquidity.bump
+    )]
+    pub pool_liquidity: Account<'info, PoolLiquidity>,
+
+    #[account(
+        mut,
+        seeds = [b"stake_rewards", user.key().as_ref()],
+        bump,
+        constraint = stake_rewards.user == user.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub stake_rewards: Account<'info, StakeRewards>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool"],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.is_emergency @ ErrorCode::EmergencyModeNotActive
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    // SPL collateral; required for token positions, unset for SOL positions
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub user_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> EmergencyWithdraw<'info> {
+    // Pays out the principal, ignoring the lock period and early withdrawal penalty.
+    // Works while paused so users always have an exit. If the vault cannot cover it all,
+    // the unpaid principal and its shares stay on the position.
+    pub fn emergency_withdraw(&mut self) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // Compliance freezes still apply in an emergency
+        FreezeRecord::ensure_not_frozen(&self.freeze_record.to_account_info())?;
+
+        let principal = self.position_account.amount;
+        require!(principal > 0, ErrorCode::InsufficientFunds);
+
+        let token_route = TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )?;
+        ensure_collateral_matches(self.position_account.collateral_mint, &token_route)?;
+
+        // Pay whatever the vault can cover, never more than the principal
+        let vault_balance = match &token_route {
+            Some(_) => self.vault_token_account.as_ref().map(|account| account.amount).unwrap_or(0),
+            None => self.vault.lamports(),
+        };
+        let payout = principal.min(vault_balance);
+        require!(payout > 0, ErrorCode::InsufficientFunds);
+
+        // Shares backing the principal paid out; the rest stay with the user so the
+        // shortfall can be withdrawn later
+        let shares_burned = if payout == principal {
+            self.position_account.shares
+        } else {
+            ((self.position_account.shares as u128 * payout as u128) / principal as u128) as u64
+        };
+
+        match &token_route {
+            Some(route) => {
+                let vault_token_account = route.check_vault_token_account(
+                    self.vault_token_account.as_deref(),
+                    &self.vault_state.key(),
+                )?;
+                let user_token_account = route.check_owner_account(
+                    self.user_token_account.as_deref(),
+                    &self.user.key(),
+                )?;
+
+                route.transfer_from_program(vault_token_account, user_token_account, payout)?;
+
+                let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+                collateral_mint.total_liquidity = collateral_mint.total_liquidity.saturating_sub(payout);
+                collateral_mint.total_shares = collateral_mint.total_shares.saturating_sub(shares_burned);
+            },
+            None => {
+                // Settle staking rewards and unstake the burned shares before the principal
+                // leaves; only SOL pool shares are staked
+                self.stake_rewards.release_shares(&mut self.reward_pool, shares_burned, current_time)?;
+
+                let vault_seeds = &[
+                    b"vault",
+                    self.vault_state.to_account_info().key.as_ref(),
+                    &[self.vault_state.vault_bump]
+                ];
+                let vault_signer_seeds = &[&vault_seeds[..]];
+
+                let cpi_ctx = CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.vault.to_account_info(),
+                        to: self.user.to_account_info(),
+                    },
+                    vault_signer_seeds
+                );
+
+                transfer(cpi_ctx, payout)?;
+
+                self.pool_liquidity.total_liquidity = self.pool_liquidity.total_liquidity.saturating_sub(payout);
+                self.pool_liquidity.available_liquidity = self.pool_liquidity.available_liquidity.saturating_sub(payout);
+                self.pool_liquidity.total_shares = self.pool_liquidity.total_shares.saturating_sub(shares_burned);
+                self.pool_liquidity.last_updated = current_time;
+            }
+        }
+
+        self.position_account.amount = principal - payout;
+        self.position_account.shares -= shares_burned;
+        self.position_account.is_active = self.position_account.amount > 0;
+
+        emit!(EmergencyWithdrawEvent {
+            user: self.user.key(),
+            principal,
+            amount_paid: payout,
+            shares_burned,
+            remaining_principal: self.position_account.amount,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct EmergencyClosePosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            user.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump = position.bump,
+        constraint = position.user == user.key() && position.order_id == order_id,
+        constraint = position.is_open() @ ErrorCode::PositionAlreadySettled,
+    )]
+    pub position: Account<'info, PositionState>,
+
+    #[account(
+        mut,
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = user_vault_state.state_bump
+    )]
+    pub user_vault_state: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user_vault_state.key().as_ref()],
+        bump = user_vault_state.vault_bump
+    )]
+    pub user_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.is_emergency @ ErrorCode::EmergencyModeNotActive
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    // SPL collateral; required for token positions, unset for SOL positions
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> EmergencyClosePosition<'info> {
+    // Settles an open position without touching the oracle: leveraged positions use the
+    // last price recorded by check_position, everything else is refunded at collateral value.
+    pub fn emergency_close_position(&mut self) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let position = &mut self.position;
+
+        let has_observation = position.last_observed_at > position.created_at;
+        let settlement_price = if has_observation {
+            position.last_observed_price
+        } else {
+            position.entry_price
+        };
+
+        let settlement_amount = if position.is_range() || !has_observation {
+            position.collateral_amount
+        } else {
+            Self::settle_at_price(position, settlement_price)?
+        };
+
+        let token_route = TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )?;
+        ensure_collateral_matches(position.collateral_mint, &token_route)?;
+
+        let payout = match &token_route {
+            Some(route) => {
+                let vault_token_account = route.check_vault_token_account(
+                    self.vault_token_account.as_deref(),
+                    &self.user_vault_state.key(),
+                )?;
+                let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+                let pool_token_vault = route.check_program_vault(
+                    self.pool_token_vault.as_deref(),
+                    &collateral_mint.pool_token_vault,
+                )?;
+
+                // Never pay more than the pool actually holds
+                let pool_balance = self.pool_token_vault.as_ref().map(|account| account.amount).unwrap_or(0);
+                let payout = settlement_amount.min(collateral_mint.payable_balance(pool_balance));
+
+                route.transfer_from_program(pool_token_vault, vault_token_account, payout)?;
+
+                collateral_mint.total_active_amount = collateral_mint.total_active_amount
+                    .saturating_sub(position.size);
+                collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+                    .saturating_sub(payout);
+                payout
+            },
+            None => {
+                // Never pay more than the pool actually holds
+                let payout = settlement_amount.min(self.trading_pool_vault.lamports());
+
+                if payout > 0 {
+                    let pool_seeds = &[
+                        b"trading_pool_vault",
+                        self.trading_pool.to_account_info().key.as_ref(),
+                        &[self.trading_pool.vault_bump],
+                    ];
+                    let signer_seeds = &[&pool_seeds[..]];
+
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        self.system_program.to_account_info(),
+                        Transfer {
+                            from: self.trading_pool_vault.to_account_info(),
+                            to: self.user_vault.to_account_info(),
+                        },
+                        signer_seeds,
+                    );
+
+                    transfer(cpi_ctx, payout)?;
+                }
+
+                self.trading_pool.total_active_amount = self.trading_pool.total_active_amount
+                    .saturating_sub(position.size);
+                self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+                    .saturating_sub(payout);
+                payout
+            }
+        };
+
+        let payout_percentage = if position.collateral_amount > 0 {
+            ((payout as u128 * 100) / position.collateral_amount as u128).min(u8::MAX as u128) as u8
+        } else {
+            0
+        };
+
+        // Paid out directly, so mark claimed to block a second payout through claim_position
+        position.settle(current_time, settlement_price, payout_percentage)?;
+        position.claim(current_time)?;
+
+        self.user_vault_state.active_positions = self.user_vault_state.active_positions
+            .saturating_sub(1);
+
+        emit!(EmergencyPositionClosedEvent {
+            position: position.key(),
+            user: position.user,
+            order_id: position.order_id,
+            settlement_price,
+            used_last_recorded_price: has_observation && !position.is_range(),
+            collateral_amount: position.collateral_amount,
+            amount_paid: payout,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Collateral plus P&L at `price`, floored at zero. P&L is price move × size scaled by
+    // PRICE_DECIMALS, the same unit close_position and the health score use.
+    fn settle_at_price(position: &PositionState, price: u64) -> Result<u64> {
+        let pnl = position.price_pnl(position.size, price)?;
+
+        let settlement = (position.collateral_amount as i128 + pnl).max(0);
+
+        Ok(settlement.min(u64::MAX as i128) as u64)
+    }
+}
+
+#[event]
+pub struct EmergencyWithdrawEvent {
+    pub user: Pubkey,
+    pub principal: u64,
+    pub amount_paid: u64,
+    pub shares_burned: u64,
+    pub remaining_principal: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyPositionClosedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub order_id: u64,
+    pub settlement_price: u64,
+    pub used_last_recorded_price: bool,
+    pub collateral_amount: u64,
+    pub amount_paid: u64,
+    pub timestamp: i64,
+}
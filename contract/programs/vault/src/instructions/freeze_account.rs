@@ -0,0 +1,150 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{FreezeRecord, ProtocolConfig};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct InitFreezeRecord<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + FreezeRecord::INIT_SPACE,
+        seeds = [b"freeze_record", wallet.as_ref()],
+        bump
+    )]
+    pub freeze_record: Account<'info, FreezeRecord>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitFreezeRecord<'info> {
+    // Creates the wallet's record unfrozen; `freeze_account` can run in the same transaction
+    pub fn init_freeze_record(&mut self, wallet: Pubkey, bumps: &InitFreezeRecordBumps) -> Result<()> {
+        self.freeze_record.set_inner(FreezeRecord {
+            wallet,
+            is_frozen: false,
+            reason_code: 0,
+            frozen_by: Pubkey::default(),
+            frozen_at: 0,
+            unfrozen_at: 0,
+            bump: bumps.freeze_record,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(account_to_freeze: Pubkey)]
+pub struct FreezeAccount<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"freeze_record", account_to_freeze.as_ref()],
+        bump = freeze_record.bump,
+        constraint = !freeze_record.is_frozen @ ErrorCode::AccountFrozen
+    )]
+    pub freeze_record: Account<'info, FreezeRecord>,
+}
+
+impl<'info> FreezeAccount<'info> {
+    pub fn freeze_account(&mut self, account_to_freeze: Pubkey, reason_code: u16) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let record = &mut self.freeze_record;
+
+        record.is_frozen = true;
+        record.reason_code = reason_code;
+        record.frozen_by = self.admin.key();
+        record.frozen_at = current_time;
+
+        emit!(AccountFrozenEvent {
+            wallet: account_to_freeze,
+            freeze_record: record.key(),
+            reason_code,
+            frozen_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(account_to_unfreeze: Pubkey)]
+pub struct UnfreezeAccount<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"freeze_record", account_to_unfreeze.as_ref()],
+        bump = freeze_record.bump,
+        constraint = freeze_record.is_frozen @ ErrorCode::AccountNotFrozen
+    )]
+    pub freeze_record: Account<'info, FreezeRecord>,
+}
+
+impl<'info> UnfreezeAccount<'info> {
+    pub fn unfreeze_account(&mut self, account_to_unfreeze: Pubkey) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let record = &mut self.freeze_record;
+
+        record.is_frozen = false;
+        record.unfrozen_at = current_time;
+
+        emit!(AccountUnfrozenEvent {
+            wallet: account_to_unfreeze,
+            freeze_record: record.key(),
+            reason_code: record.reason_code,
+            frozen_at: record.frozen_at,
+            unfrozen_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct AccountFrozenEvent {
+    pub wallet: Pubkey,
+    pub freeze_record: Pubkey,
+    pub reason_code: u16,
+    pub frozen_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountUnfrozenEvent {
+    pub wallet: Pubkey,
+    pub freeze_record: Pubkey,
+    pub reason_code: u16,
+    pub frozen_at: i64,
+    pub unfrozen_by: Pubkey,
+    pub timestamp: i64,
+}
@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+
+use crate::program::Vault;
+use crate::state::{ConfigParams, ProtocolConfig};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct InitConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    // There is no protocol admin before the config exists, so the first one must be
+    // the program's upgrade authority
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Vault>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::UnauthorizedAccess
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProtocolConfig::INIT_SPACE,
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitConfig<'info> {
+    pub fn init_config(&mut self, bumps: &InitConfigBumps) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // Seed with the compiled defaults from constants.rs
+        let params = ConfigParams::default();
+        params.validate()?;
+
+        self.protocol_config.set_inner(ProtocolConfig {
+            admin: self.admin.key(),
+            pending_admin: Pubkey::default(),
+            is_paused: false,
+            paused_at: 0,
+            is_emergency: false,
+            emergency_started_at: 0,
+            params,
+            last_updated: current_time,
+            bump: bumps.protocol_config,
+        });
+
+        emit!(ProtocolConfigInitializedEvent {
+            config: self.protocol_config.key(),
+            admin: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct ProtocolConfigInitializedEvent {
+    pub config: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
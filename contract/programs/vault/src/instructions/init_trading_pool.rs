@@ -111,7 +111,7 @@ impl<'info> InitTradingPool<'info> {
             self.pool_liquidity.total_liquidity = deposit_amount;
             self.pool_liquidity.available_liquidity = deposit_amount;
 
-            emit!(InitialDepositEvent {
+            emit!(PoolInitialDepositEvent {
                 pool: self.trading_pool.key(),
                 admin: self.admin.key(),
                 amount: deposit_amount,
@@ -162,7 +162,7 @@ pub struct TradingPoolCreatedEvent {
 }
 
 #[event]
-pub struct InitialDepositEvent {
+pub struct PoolInitialDepositEvent {
     pub pool: Pubkey,
     pub admin: Pubkey,
     pub amount: u64,
@@ -175,37 +175,3 @@ pub struct PoolStatusUpdatedEvent {
     pub updated_by: Pubkey,
     pub timestamp: i64,
 }
-
-// Extended TradingPool state structure
-#[account]
-pub struct TradingPool {
-    pub authority: Pubkey,
-    pub total_active_amount: u64,
-    pub total_pool_amount: u64,
-    pub is_active: bool,
-    pub created_at: i64,
-    pub last_updated: i64,
-    pub bump: u8,
-    pub vault_bump: u8,
-}
-
-impl TradingPool {
-    pub const INIT_SPACE: usize = 32 + 8 + 8 + 1 + 8 + 8 + 1 + 1; // 67 bytes
-    pub const MIN_INITIAL_DEPOSIT: u64 = 1_000_000; // 0.001 SOL minimum
-}
-
-// Extended VaultState for consistency
-impl VaultState {
-    pub const INIT_SPACE: usize = 32 + 1 + 1 + 8 + 8 + 1 + 8; // 59 bytes
-}
-
-// Extended PoolLiquidity for consistency  
-impl PoolLiquidity {
-    pub const INIT_SPACE: usize = 8 + 8 + 8 + 8 + 1; // 33 bytes
-}
-
-// Additional error codes
-impl ErrorCode {
-    pub const InvalidAuthority: ErrorCode = ErrorCode::InvalidAuthority;
-    pub const AmountTooSmall: ErrorCode = ErrorCode::AmountTooSmall;
-}
\ No newline at end of file
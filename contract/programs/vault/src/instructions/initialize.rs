@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
 
-use crate::state::{VaultState, PositionAccount, PoolLiquidity};
+use crate::state::{VaultState, PositionAccount, PoolLiquidity, SanctionsPage};
 use crate::error::ErrorCode;
 
 #[derive(Accounts)]
@@ -41,6 +41,13 @@ pub struct Initialize<'info> {
     )]
     pub pool_liquidity: Account<'info, PoolLiquidity>,
 
+    /// CHECK: Sanctions page for `user`'s bucket, may not exist; checked in the handler
+    #[account(
+        seeds = [b"sanctions_page", &[SanctionsPage::bucket_for(&user.key())]],
+        bump
+    )]
+    pub sanctions_page: UncheckedAccount<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -55,6 +62,9 @@ impl<'info> Initialize<'info> {
             ErrorCode::InvalidAuthority
         );
 
+        // Reject sanctioned wallets
+        SanctionsPage::ensure_not_sanctioned(&self.sanctions_page.to_account_info(), &self.user.key())?;
+
         // Initialize Vault State
         self.vault_state.authority = self.user.key();
         self.vault_state.owner = self.user.key();
@@ -225,49 +235,3 @@ pub struct LockPeriodExtendedEvent {
     pub new_lock_end_time: i64,
     pub timestamp: i64,
 }
-
-// Extended VaultState structure for consistency
-#[account]
-pub struct VaultState {
-    pub authority: Pubkey,        // 32 bytes
-    pub owner: Pubkey,           // 32 bytes
-    pub vault_bump: u8,          // 1 byte
-    pub state_bump: u8,          // 1 byte
-    pub total_deposits: u64,     // 8 bytes
-    pub total_withdrawals: u64,  // 8 bytes
-    pub is_initialized: bool,    // 1 byte
-    pub is_active: bool,         // 1 byte
-    pub created_at: i64,         // 8 bytes
-    pub last_updated: i64,       // 8 bytes
-}
-
-impl VaultState {
-    pub const INIT_SPACE: usize = 32 + 32 + 1 + 1 + 8 + 8 + 1 + 1 + 8 + 8; // 100 bytes
-    pub const MIN_ORDER_AMOUNT: u64 = 100_000; // 0.0001 SOL minimum
-}
-
-// Extended PositionAccount structure
-#[account]
-pub struct PositionAccount {
-    pub owner: Pubkey,           // 32 bytes
-    pub amount: u64,             // 8 bytes
-    pub lock_start_time: i64,    // 8 bytes
-    pub lock_end_time: i64,      // 8 bytes
-    pub is_active: bool,         // 1 byte
-    pub created_at: i64,         // 8 bytes
-    pub bump: u8,                // 1 byte
-}
-
-impl PositionAccount {
-    pub const INIT_SPACE: usize = 32 + 8 + 8 + 8 + 1 + 8 + 1; // 66 bytes
-}
-
-// Additional error codes
-impl ErrorCode {
-    pub const InvalidAuthority: ErrorCode = ErrorCode::InvalidAuthority;
-    pub const AmountTooSmall: ErrorCode = ErrorCode::AmountTooSmall;
-    pub const InsufficientBalance: ErrorCode = ErrorCode::InsufficientBalance;
-    pub const InvalidPositionOwnership: ErrorCode = ErrorCode::InvalidPositionOwnership;
-    pub const PositionNotActive: ErrorCode = ErrorCode::PositionNotActive;
-    pub const InvalidLockPeriod: ErrorCode = ErrorCode::InvalidLockPeriod;
-}
\ No newline at end of file
@@ -0,0 +1,656 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use crate::oracle;
+use crate::instructions::{distribute_fee, ensure_collateral_matches, pay_token_settlement, record_market_price, retain_token_fee, revalue_position_margin, FeeRoute, TokenRoute};
+use crate::state::{notional, CollateralMint, FeeCategory, FeeLedger, PoolLiquidity, PositionState, PositionStatus, PositionType, ProtocolConfig, TradingPool, VaultState, Market, PriceHistory, ReferralRecord};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct LiquidatePosition<'info> {
+    // Anyone can liquidate; the caller receives the bounty
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+
+    /// CHECK: Position owner, only used for seed derivation
+    pub user: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            user.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump = position.bump,
+        constraint = position.user == user.key() && position.order_id == order_id,
+        constraint = position.position_type == PositionType::Leveraged @ ErrorCode::InvalidPositionType,
+        constraint = position.is_open() @ ErrorCode::PositionAlreadySettled,
+    )]
+    pub position: Account<'info, PositionState>,
+
+    #[account(
+        mut,
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = user_vault_state.state_bump
+    )]
+    pub user_vault_state: Account<'info, VaultState>,
+
+    // Receives the margin released from closed slices
+    #[account(
+        mut,
+        seeds = [b"vault", user_vault_state.key().as_ref()],
+        bump = user_vault_state.vault_bump
+    )]
+    pub user_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump,
+        constraint = position.market == market.key() @ ErrorCode::MarketMismatch
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    #[account(
+        owner = pyth_solana_receiver_sdk::ID,
+        constraint = price_update.verification_level == VerificationLevel::Full,
+    )]
+    pub price_update: Account<'info, PriceUpdateV2>,
+
+    /// CHECK: Instructions sysvar, only needed to read the market's secondary signed price
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_liquidity"],
+        bump = pool_liquidity.bump
+    )]
+    pub pool_liquidity: Account<'info, PoolLiquidity>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault"],
+        bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    // Optional referrer, receives the referrer share of fees. Must be the referrer
+    // registered for `user` at their first deposit
+    #[account(mut)]
+    pub referrer: Option<SystemAccount<'info>>,
+
+    /// CHECK: Referral record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"referral", user.key().as_ref()],
+        bump
+    )]
+    pub referral: UncheckedAccount<'info>,
+
+    // SPL collateral; required for token positions, unset for SOL positions
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Owner's token vault, receives released margin for token positions
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Receives the bounty for token positions
+    #[account(mut)]
+    pub liquidator_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> LiquidatePosition<'info> {
+    pub fn liquidate_position(&mut self, _bumps: &LiquidatePositionBumps, remaining_accounts: &[AccountInfo]) -> Result<()> {
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+        let config = self.protocol_config.params;
+
+        msg!("=== POSITION LIQUIDATION ===");
+        msg!("Liquidator: {}", self.liquidator.key());
+        msg!("Position order_id: {}", self.position.order_id);
+
+        // Token positions are liquidated in their own mint
+        let token_route = TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )?;
+        ensure_collateral_matches(self.position.collateral_mint, &token_route)?;
+
+        // Liquidation is only ever decided on a fresh price
+        let oracle_price = oracle::get_market_price_with_fallback(
+            &self.price_update,
+            &self.market,
+            self.instructions_sysvar.as_deref(),
+            &clock,
+            config.maximum_age,
+        )?;
+        record_market_price(&self.market, &mut self.price_history, clock.slot, &oracle_price);
+
+        // Eligibility uses the same mark price and side as check_position, so both agree on health
+        let mark_price = oracle_price.mark(&self.market, &self.price_history, current_time)?;
+        mark_price.check_confidence(config.max_confidence_bps)?;
+        let health_price = mark_price.conservative(self.position.is_long, false);
+        msg!("Mark price: {}", health_price);
+
+        // Closed slices are realized at the spot price, on the side least favourable to the trader
+        oracle_price.check_confidence(config.max_confidence_bps)?;
+        let execution_price = oracle_price.conservative(self.position.is_long, false);
+        msg!("Execution price: {}", execution_price);
+
+        // Health is judged on freshly valued margin whenever the position holds extra
+        // margin, and on the raw collateral otherwise
+        self.position.expire_margin_valuation(current_time, config.maximum_age);
+        if self.position.has_margin_deposits() {
+            revalue_position_margin(&mut self.position, remaining_accounts, config.maximum_age, &clock)?;
+        }
+
+        let health_score = self.position.calculate_health_score(health_price)?;
+        msg!("Health score: {}", health_score);
+
+        require!(
+            health_score < config.liquidation_threshold,
+            ErrorCode::PositionNotLiquidatable
+        );
+
+        // Close the position in slices until it is healthy again or fully closed
+        let outcome = self.reduce_until_healthy(health_price, execution_price, current_time)?;
+        msg!("Closed size: {}", outcome.closed_size);
+        msg!("Seized collateral: {}", outcome.seized_collateral);
+
+        // The penalty part of the seized collateral is protocol revenue
+        match token_route {
+            Some(_) => {
+                let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+                retain_token_fee(
+                    collateral_mint,
+                    FeeCategory::Liquidation,
+                    self.user.key(),
+                    outcome.penalty_fee,
+                    &config.fee_split,
+                )?;
+            },
+            None => self.distribute_liquidation_fee(outcome.penalty_fee)?,
+        }
+
+        // Seized collateral already sits in the trading pool vault, so the pool
+        // keeps it minus the liquidator bounty
+        let bounty = self.calculate_liquidation_bounty(outcome.seized_collateral)?;
+        msg!("Liquidator bounty: {}", bounty);
+
+        if bounty > 0 {
+            match &token_route {
+                Some(route) => self.pay_token_liquidation_bounty(route, bounty)?,
+                None => self.pay_liquidation_bounty(bounty)?,
+            }
+        }
+
+        // Margin freed by the closed slices belongs to the owner
+        msg!("Released collateral: {}", outcome.released_collateral);
+        if outcome.released_collateral > 0 {
+            match &token_route {
+                Some(route) => self.release_token_margin(route, outcome.released_collateral)?,
+                None => self.release_margin(outcome.released_collateral)?,
+            }
+        }
+
+        self.position.last_health_check = current_time;
+
+        if outcome.fully_closed {
+            self.position.liquidate(current_time, execution_price)?;
+            self.position.liquidation_price = None;
+
+            self.user_vault_state.active_positions = self.user_vault_state.active_positions
+                .checked_sub(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            emit!(PositionLiquidatedEvent {
+                position: self.position.key(),
+                user: self.position.user,
+                liquidator: self.liquidator.key(),
+                order_id: self.position.order_id,
+                entry_price: self.position.entry_price,
+                liquidation_price: execution_price,
+                health_score,
+                seized_collateral: outcome.seized_collateral,
+                released_collateral: outcome.released_collateral,
+                liquidator_bounty: bounty,
+                timestamp: current_time,
+            });
+
+            msg!("=== POSITION LIQUIDATED ===");
+        } else {
+            self.position.status = PositionStatus::Healthy;
+            self.position.liquidation_price = Some(self.position.calculate_liquidation_price(config.liquidation_threshold)?);
+
+            emit!(PositionPartiallyLiquidatedEvent {
+                position: self.position.key(),
+                user: self.position.user,
+                liquidator: self.liquidator.key(),
+                order_id: self.position.order_id,
+                price: execution_price,
+                steps: outcome.steps,
+                closed_size: outcome.closed_size,
+                remaining_size: self.position.size,
+                penalty_fee: outcome.penalty_fee,
+                seized_collateral: outcome.seized_collateral,
+                released_collateral: outcome.released_collateral,
+                remaining_collateral: self.position.collateral_amount,
+                liquidator_bounty: bounty,
+                health_score_before: health_score,
+                health_score_after: outcome.health_score,
+                new_liquidation_price: self.position.liquidation_price.unwrap_or(0),
+                timestamp: current_time,
+            });
+
+            msg!("=== POSITION PARTIALLY LIQUIDATED ===");
+        }
+
+        Ok(())
+    }
+
+    // Each step closes `liquidation_step_bps` of the original size. The realized loss and
+    // penalty on the closed slice are seized from the slice's collateral. What is left of
+    // the slice's margin goes back to the owner, except the part the remaining size needs
+    // to be healthy again; a fully closed position releases everything left. Slices are
+    // realized at `execution_price`; health is measured at `health_price`.
+    fn reduce_until_healthy(&mut self, health_price: u64, execution_price: u64, current_time: i64) -> Result<LiquidationOutcome> {
+        let config = self.protocol_config.params;
+        let step_size = ((self.position.size as u128 * config.liquidation_step_bps as u128) / 10000)
+            .max(1) as u64;
+
+        let mut outcome = LiquidationOutcome {
+            steps: 0,
+            closed_size: 0,
+            penalty_fee: 0,
+            seized_collateral: 0,
+            released_collateral: 0,
+            health_score: 0,
+            fully_closed: false,
+        };
+        let mut releasable = 0u64;
+
+        while outcome.steps < config.max_liquidation_steps {
+            let closed = step_size.min(self.position.size);
+            let slice = self.close_slice(closed, execution_price)?;
+
+            outcome.steps += 1;
+            outcome.closed_size += closed;
+            outcome.penalty_fee += slice.penalty_fee;
+            outcome.seized_collateral += slice.seized;
+            releasable += slice.margin_left;
+
+            msg!("Liquidation step {}: closed {}, seized {}", outcome.steps, closed, slice.seized);
+
+            // No collateral left to back the rest: it is closed below
+            if self.position.size == 0 || self.position.collateral_amount == 0 {
+                outcome.health_score = 0;
+                break;
+            }
+
+            outcome.health_score = self.position.calculate_health_score(health_price)?;
+            if outcome.health_score >= config.healthy_threshold {
+                break;
+            }
+        }
+
+        // Still unhealthy after the maximum number of steps: close the remainder the same way
+        if self.position.size > 0 && outcome.health_score < config.healthy_threshold {
+            let closed = self.position.size;
+            let slice = self.close_slice(closed, execution_price)?;
+
+            outcome.closed_size += closed;
+            outcome.penalty_fee += slice.penalty_fee;
+            outcome.seized_collateral += slice.seized;
+
+            msg!("Liquidation closed the remaining {}, seized {}", closed, slice.seized);
+        }
+
+        let position = &mut self.position;
+        outcome.fully_closed = position.size == 0;
+
+        outcome.released_collateral = if outcome.fully_closed {
+            position.collateral_amount
+        } else {
+            releasable.min(position.excess_collateral(health_price, config.healthy_threshold)?)
+        };
+        position.release_collateral(outcome.released_collateral);
+
+        if outcome.fully_closed {
+            position.collateral_amount = 0;
+            position.effective_collateral = 0;
+            position.margin_value = 0;
+            position.unrealized_pnl = -(outcome.seized_collateral as i64);
+        }
+
+        position.last_health_check = current_time;
+
+        Ok(outcome)
+    }
+
+    // Closes `closed` of the position. Loss and penalty are taken on the slice's notional
+    // (size × price / 10^PRICE_DECIMALS), the same unit collateral and health are expressed in
+    fn close_slice(&mut self, closed: u64, execution_price: u64) -> Result<SliceOutcome> {
+        let config = self.protocol_config.params;
+        let position = &mut self.position;
+
+        let slice_margin = (position.collateral_amount as u128 * closed as u128
+            / position.size.max(1) as u128) as u64;
+
+        let realized_loss = (-position.price_pnl(closed, execution_price)?).max(0) as u128;
+        let penalty = notional(closed, execution_price)?
+            * config.liquidation_penalty_bps as u128
+            / 10000;
+
+        let seized = realized_loss
+            .checked_add(penalty)
+            .ok_or(ErrorCode::MathOverflow)?
+            .min(position.collateral_amount as u128) as u64;
+
+        position.size -= closed;
+        position.seize_collateral(seized);
+
+        // Release the closed slice from the pool's open interest
+        match self.collateral_mint.as_mut() {
+            Some(collateral_mint) => {
+                collateral_mint.total_active_amount = collateral_mint.total_active_amount
+                    .checked_sub(closed)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            },
+            None => {
+                self.trading_pool.total_active_amount = self.trading_pool.total_active_amount
+                    .checked_sub(closed)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+        }
+
+        Ok(SliceOutcome {
+            penalty_fee: (penalty as u64).min(seized),
+            seized,
+            margin_left: slice_margin.saturating_sub(seized),
+        })
+    }
+
+    // Returns released margin from the pool vault to the owner's vault
+    fn release_margin(&mut self, amount: u64) -> Result<()> {
+        let pool_vault_balance = self.trading_pool_vault.lamports();
+        require!(
+            pool_vault_balance >= amount,
+            ErrorCode::InsufficientPoolBalance
+        );
+
+        let pool_seeds = &[
+            b"trading_pool_vault",
+            self.trading_pool.to_account_info().key.as_ref(),
+            &[self.trading_pool.vault_bump],
+        ];
+        let signer_seeds = &[&pool_seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            Transfer {
+                from: self.trading_pool_vault.to_account_info(),
+                to: self.user_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        transfer(cpi_ctx, amount)?;
+
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    // Token positions get their released margin in the owner's token vault
+    fn release_token_margin(&mut self, route: &TokenRoute<'info>, amount: u64) -> Result<()> {
+        let vault_token_account = route.check_vault_token_account(
+            self.vault_token_account.as_deref(),
+            &self.user_vault_state.key(),
+        )?;
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        route.check_program_vault(self.pool_token_vault.as_deref(), &collateral_mint.pool_token_vault)?;
+        let pool_token_vault = self.pool_token_vault.as_ref().ok_or(ErrorCode::MissingCollateralAccounts)?;
+
+        pay_token_settlement(
+            route,
+            collateral_mint,
+            pool_token_vault,
+            vault_token_account,
+            amount,
+            self.position.key(),
+            self.position.user,
+        )?;
+
+        Ok(())
+    }
+
+    fn distribute_liquidation_fee(&mut self, penalty_fee: u64) -> Result<()> {
+        let trading_pool_key = self.trading_pool.key();
+        let pool_seeds = &[
+            b"trading_pool_vault",
+            trading_pool_key.as_ref(),
+            &[self.trading_pool.vault_bump],
+        ];
+        let signer_seeds = &[&pool_seeds[..]];
+
+        // Only the owner's registered referrer can take the referrer share
+        let referrer = ReferralRecord::registered_referrer(
+            &self.referral.to_account_info(),
+            self.referrer.as_ref(),
+        )?;
+
+        // LP and treasury shares stay in the pool vault; the treasury share is swept later
+        let route = FeeRoute {
+            source: self.trading_pool_vault.to_account_info(),
+            source_signer_seeds: signer_seeds,
+            lp_destination: self.trading_pool_vault.to_account_info(),
+            treasury_destination: self.trading_pool_vault.to_account_info(),
+            insurance_vault: self.insurance_vault.to_account_info(),
+            referrer,
+            system_program: self.system_program.to_account_info(),
+        };
+
+        let split = distribute_fee(
+            &route,
+            FeeCategory::Liquidation,
+            self.user.key(),
+            penalty_fee,
+            &self.protocol_config.params.fee_split,
+            &mut self.fee_ledger,
+        )?;
+
+        let paid_out = split.insurance
+            .checked_add(split.referrer)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // LP share raises the value of every pool share
+        self.pool_liquidity.add_income(split.lp, Clock::get()?.unix_timestamp)?;
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .saturating_sub(paid_out);
+        self.trading_pool.total_fees_collected = self.trading_pool.total_fees_collected
+            .checked_add(penalty_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    fn calculate_liquidation_bounty(&self, collateral: u64) -> Result<u64> {
+        let bounty = collateral
+            .checked_mul(self.protocol_config.params.liquidation_bounty_bps as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::DivisionByZero)?;
+
+        Ok(bounty)
+    }
+
+    fn pay_liquidation_bounty(&mut self, bounty: u64) -> Result<()> {
+        let pool_vault_balance = self.trading_pool_vault.lamports();
+        require!(
+            pool_vault_balance >= bounty,
+            ErrorCode::InsufficientPoolBalance
+        );
+
+        let pool_seeds = &[
+            b"trading_pool_vault",
+            self.trading_pool.to_account_info().key.as_ref(),
+            &[self.trading_pool.vault_bump],
+        ];
+        let signer_seeds = &[&pool_seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            Transfer {
+                from: self.trading_pool_vault.to_account_info(),
+                to: self.liquidator.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        transfer(cpi_ctx, bounty)?;
+
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .checked_sub(bounty)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    fn pay_token_liquidation_bounty(&mut self, route: &TokenRoute<'info>, bounty: u64) -> Result<()> {
+        let liquidator_token_account = route.check_owner_account(
+            self.liquidator_token_account.as_deref(),
+            &self.liquidator.key(),
+        )?;
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        let pool_token_vault = route.check_program_vault(
+            self.pool_token_vault.as_deref(),
+            &collateral_mint.pool_token_vault,
+        )?;
+
+        let pool_balance = self.pool_token_vault.as_ref().map(|account| account.amount).unwrap_or(0);
+        require!(
+            collateral_mint.payable_balance(pool_balance) >= bounty,
+            ErrorCode::InsufficientPoolBalance
+        );
+
+        route.transfer_from_program(pool_token_vault, liquidator_token_account, bounty)?;
+
+        collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+            .checked_sub(bounty)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+}
+
+#[derive(Debug)]
+struct LiquidationOutcome {
+    steps: u8,
+    closed_size: u64,
+    penalty_fee: u64,
+    seized_collateral: u64,
+    released_collateral: u64,
+    health_score: u16,
+    fully_closed: bool,
+}
+
+#[derive(Debug)]
+struct SliceOutcome {
+    penalty_fee: u64,
+    seized: u64,
+    margin_left: u64, // The slice's share of collateral left after the seizure
+}
+
+#[event]
+pub struct PositionLiquidatedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub liquidator: Pubkey,
+    pub order_id: u64,
+    pub entry_price: u64,
+    pub liquidation_price: u64,
+    pub health_score: u16,
+    pub seized_collateral: u64,
+    pub released_collateral: u64, // Returned to the owner's vault
+    pub liquidator_bounty: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionPartiallyLiquidatedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub liquidator: Pubkey,
+    pub order_id: u64,
+    pub price: u64,
+    pub steps: u8,
+    pub closed_size: u64,
+    pub remaining_size: u64,
+    pub penalty_fee: u64,
+    pub seized_collateral: u64,
+    pub released_collateral: u64, // Returned to the owner's vault
+    pub remaining_collateral: u64,
+    pub liquidator_bounty: u64,
+    pub health_score_before: u16,
+    pub health_score_after: u16,
+    pub new_liquidation_price: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,369 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::{FeeCategory, FeeLedger, FeeSplit, FeeSplitAmounts, ProtocolConfig, TradingPool};
+use crate::constants::MAX_CONFIG_FEE_BPS;
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct InitFeeLedger<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + FeeLedger::INIT_SPACE,
+        seeds = [b"fee_ledger"],
+        bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitFeeLedger<'info> {
+    pub fn init_fee_ledger(&mut self, fee_recipient: Pubkey, bumps: &InitFeeLedgerBumps) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        self.fee_ledger.set_inner(FeeLedger {
+            admin: self.admin.key(),
+            fee_recipient,
+            deposit_fees: 0,
+            withdrawal_fees: 0,
+            trading_fees: 0,
+            closing_fees: 0,
+            liquidation_fees: 0,
+            lp_fees: 0,
+            treasury_fees: 0,
+            insurance_fees: 0,
+            referrer_fees: 0,
+            uncollected_treasury_fees: 0,
+            uncollected_pool_fees: 0,
+            total_collected: 0,
+            last_collected_at: 0,
+            bump: bumps.fee_ledger,
+        });
+
+        emit!(FeeRecipientUpdatedEvent {
+            fee_ledger: self.fee_ledger.key(),
+            fee_recipient,
+            updated_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeStructure<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+}
+
+impl<'info> UpdateFeeStructure<'info> {
+    // Shortcut over `update_config` for the two user-facing fees. `deposit_fee_bps` sets the
+    // catch-all (smallest deposits) tier; the discounted tiers are left as configured.
+    pub fn update_fees(&mut self, deposit_fee_bps: u64, withdrawal_fee_bps: u64) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(deposit_fee_bps <= MAX_CONFIG_FEE_BPS as u64, ErrorCode::InvalidConfig);
+        require!(withdrawal_fee_bps <= MAX_CONFIG_FEE_BPS as u64, ErrorCode::InvalidConfig);
+
+        let mut params = self.protocol_config.params;
+        let previous_deposit_fee_bps = params.base_deposit_fee_bps();
+        let previous_withdrawal_fee_bps = params.withdrawal_fee_bps;
+
+        let base_tier = params.deposit_fee_tiers.len() - 1;
+        params.deposit_fee_tiers[base_tier].fee_bps = deposit_fee_bps as u16;
+        params.withdrawal_fee_bps = withdrawal_fee_bps as u16;
+        params.validate()?;
+
+        self.protocol_config.params = params;
+        self.protocol_config.last_updated = current_time;
+
+        emit!(FeeStructureUpdatedEvent {
+            admin: self.admin.key(),
+            previous_deposit_fee_bps,
+            deposit_fee_bps: deposit_fee_bps as u16,
+            previous_withdrawal_fee_bps,
+            withdrawal_fee_bps: withdrawal_fee_bps as u16,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_fee_recipient(&mut self, fee_recipient: Pubkey) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        self.fee_ledger.fee_recipient = fee_recipient;
+
+        emit!(FeeRecipientUpdatedEvent {
+            fee_ledger: self.fee_ledger.key(),
+            fee_recipient,
+            updated_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    // Permissionless: fees can only ever go to the configured recipient
+    pub collector: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_treasury"],
+        bump
+    )]
+    pub protocol_treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = fee_recipient.key() == fee_ledger.fee_recipient @ ErrorCode::UnauthorizedAccess
+    )]
+    pub fee_recipient: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CollectFees<'info> {
+    pub fn collect_fees(&mut self, bumps: &CollectFeesBumps) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let rent_floor = Rent::get()?.minimum_balance(0);
+
+        // Both sources are system accounts and must stay rent exempt
+        let treasury_amount = self.fee_ledger.uncollected_treasury_fees
+            .min(self.protocol_treasury.lamports().saturating_sub(rent_floor));
+        let pool_amount = self.fee_ledger.uncollected_pool_fees
+            .min(self.trading_pool_vault.lamports().saturating_sub(rent_floor));
+
+        require!(treasury_amount + pool_amount > 0, ErrorCode::InsufficientFunds);
+
+        if treasury_amount > 0 {
+            let treasury_seeds = &[b"protocol_treasury".as_ref(), &[bumps.protocol_treasury]];
+            let signer_seeds = &[&treasury_seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                Transfer {
+                    from: self.protocol_treasury.to_account_info(),
+                    to: self.fee_recipient.to_account_info(),
+                },
+                signer_seeds,
+            );
+
+            transfer(cpi_ctx, treasury_amount)?;
+        }
+
+        if pool_amount > 0 {
+            let pool_seeds = &[
+                b"trading_pool_vault",
+                self.trading_pool.to_account_info().key.as_ref(),
+                &[self.trading_pool.vault_bump],
+            ];
+            let signer_seeds = &[&pool_seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                Transfer {
+                    from: self.trading_pool_vault.to_account_info(),
+                    to: self.fee_recipient.to_account_info(),
+                },
+                signer_seeds,
+            );
+
+            transfer(cpi_ctx, pool_amount)?;
+
+            self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+                .saturating_sub(pool_amount);
+        }
+
+        let ledger = &mut self.fee_ledger;
+        ledger.uncollected_treasury_fees -= treasury_amount;
+        ledger.uncollected_pool_fees -= pool_amount;
+        ledger.total_collected = ledger.total_collected
+            .checked_add(treasury_amount + pool_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        ledger.last_collected_at = current_time;
+
+        emit!(ProtocolFeesCollectedEvent {
+            fee_ledger: ledger.key(),
+            fee_recipient: self.fee_recipient.key(),
+            collector: self.collector.key(),
+            treasury_amount,
+            pool_amount,
+            deposit_fees: ledger.deposit_fees,
+            withdrawal_fees: ledger.withdrawal_fees,
+            trading_fees: ledger.trading_fees,
+            closing_fees: ledger.closing_fees,
+            liquidation_fees: ledger.liquidation_fees,
+            total_collected: ledger.total_collected,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+// Where a fee currently sits and where each share of it goes. Shares whose destination
+// is the source itself (e.g. the LP share of a fee already in the pool vault) stay put.
+pub struct FeeRoute<'a, 'info> {
+    pub source: AccountInfo<'info>,
+    pub source_signer_seeds: &'a [&'a [&'a [u8]]],
+    pub lp_destination: AccountInfo<'info>,
+    pub treasury_destination: AccountInfo<'info>,
+    pub insurance_vault: AccountInfo<'info>,
+    pub referrer: Option<AccountInfo<'info>>,
+    pub system_program: AccountInfo<'info>,
+}
+
+// Splits `amount` by the configured weights, moves each share, records it in the
+// fee ledger and logs the split. Callers update pool totals from the returned shares.
+pub fn distribute_fee(
+    route: &FeeRoute,
+    category: FeeCategory,
+    payer: Pubkey,
+    amount: u64,
+    fee_split: &FeeSplit,
+    fee_ledger: &mut FeeLedger,
+) -> Result<FeeSplitAmounts> {
+    let split = fee_split.split(amount, route.referrer.is_some());
+    if amount == 0 {
+        return Ok(split);
+    }
+
+    move_fee_share(route, &route.lp_destination, split.lp)?;
+    move_fee_share(route, &route.treasury_destination, split.treasury)?;
+    move_fee_share(route, &route.insurance_vault, split.insurance)?;
+    if let Some(referrer) = &route.referrer {
+        move_fee_share(route, referrer, split.referrer)?;
+    }
+
+    fee_ledger.record(category, &split)?;
+
+    emit!(FeeSplitEvent {
+        category,
+        payer,
+        amount,
+        lp_amount: split.lp,
+        treasury_amount: split.treasury,
+        insurance_amount: split.insurance,
+        referrer: route.referrer.as_ref().map(|r| r.key()).unwrap_or_default(),
+        referrer_amount: split.referrer,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(split)
+}
+
+fn move_fee_share<'info>(route: &FeeRoute<'_, 'info>, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+    if amount == 0 || to.key() == route.source.key() {
+        return Ok(());
+    }
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        route.system_program.clone(),
+        Transfer {
+            from: route.source.clone(),
+            to: to.clone(),
+        },
+        route.source_signer_seeds,
+    );
+
+    transfer(cpi_ctx, amount)
+}
+
+#[event]
+pub struct FeeSplitEvent {
+    pub category: FeeCategory,
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub lp_amount: u64,
+    pub treasury_amount: u64,
+    pub insurance_amount: u64,
+    pub referrer: Pubkey, // Pubkey::default() when there was no referrer
+    pub referrer_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeStructureUpdatedEvent {
+    pub admin: Pubkey,
+    pub previous_deposit_fee_bps: u16,
+    pub deposit_fee_bps: u16,
+    pub previous_withdrawal_fee_bps: u16,
+    pub withdrawal_fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeRecipientUpdatedEvent {
+    pub fee_ledger: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub updated_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolFeesCollectedEvent {
+    pub fee_ledger: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub collector: Pubkey,
+    pub treasury_amount: u64,
+    pub pool_amount: u64,
+    pub deposit_fees: u64,
+    pub withdrawal_fees: u64,
+    pub trading_fees: u64,
+    pub closing_fees: u64,
+    pub liquidation_fees: u64,
+    pub total_collected: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,251 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::{InsuranceFund, ProtocolConfig};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct InitInsuranceFund<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + InsuranceFund::INIT_SPACE,
+        seeds = [b"insurance_fund"],
+        bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        seeds = [b"insurance_vault"],
+        bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitInsuranceFund<'info> {
+    pub fn init_insurance_fund(&mut self, bumps: &InitInsuranceFundBumps) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        self.insurance_fund.set_inner(InsuranceFund {
+            admin: self.admin.key(),
+            total_admin_deposits: 0,
+            total_covered: 0,
+            total_uncovered_bad_debt: 0,
+            bad_debt_events: 0,
+            last_covered_at: 0,
+            vault_bump: bumps.insurance_vault,
+            bump: bumps.insurance_fund,
+        });
+
+        emit!(InsuranceFundInitializedEvent {
+            insurance_fund: self.insurance_fund.key(),
+            insurance_vault: self.insurance_vault.key(),
+            admin: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct DepositInsuranceFund<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault"],
+        bump = insurance_fund.vault_bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> DepositInsuranceFund<'info> {
+    pub fn deposit_insurance_fund(&mut self, amount: u64) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(amount > 0, ErrorCode::AmountTooSmall);
+
+        let cpi_ctx = CpiContext::new(
+            self.system_program.to_account_info(),
+            Transfer {
+                from: self.admin.to_account_info(),
+                to: self.insurance_vault.to_account_info(),
+            },
+        );
+
+        transfer(cpi_ctx, amount)?;
+
+        self.insurance_fund.total_admin_deposits = self.insurance_fund.total_admin_deposits
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(InsuranceFundDepositEvent {
+            insurance_fund: self.insurance_fund.key(),
+            admin: self.admin.key(),
+            amount,
+            vault_balance: self.insurance_vault.lamports(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+// Accounts a trading pool payout can be drawn from, in order
+pub struct PayoutSources<'a, 'info> {
+    pub trading_pool_vault: AccountInfo<'info>,
+    pub pool_signer_seeds: &'a [&'a [&'a [u8]]],
+    pub insurance_vault: AccountInfo<'info>,
+    pub insurance_signer_seeds: &'a [&'a [&'a [u8]]],
+    pub recipient: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PayoutBreakdown {
+    pub from_pool: u64,
+    pub from_insurance: u64,
+    pub shortfall: u64, // Left unpaid after both sources
+}
+
+// Pays `amount` from the trading pool vault and draws any shortfall from the insurance
+// fund. Any remaining shortfall is returned to the caller and recorded as uncovered bad debt.
+pub fn pay_with_insurance_backstop(
+    sources: &PayoutSources,
+    amount: u64,
+    insurance_fund: &mut InsuranceFund,
+    position: Pubkey,
+    user: Pubkey,
+) -> Result<PayoutBreakdown> {
+    let breakdown = draw_payout(sources, amount)?;
+    let from_pool = breakdown.from_pool;
+    let from_insurance = breakdown.from_insurance;
+
+    if from_insurance > 0 || breakdown.shortfall > 0 {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        insurance_fund.total_covered = insurance_fund.total_covered
+            .checked_add(from_insurance)
+            .ok_or(ErrorCode::MathOverflow)?;
+        insurance_fund.total_uncovered_bad_debt = insurance_fund.total_uncovered_bad_debt
+            .checked_add(breakdown.shortfall)
+            .ok_or(ErrorCode::MathOverflow)?;
+        insurance_fund.bad_debt_events = insurance_fund.bad_debt_events
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        insurance_fund.last_covered_at = current_time;
+
+        emit!(BadDebtEvent {
+            position,
+            user,
+            payout_amount: amount,
+            paid_from_pool: from_pool,
+            covered_by_insurance: from_insurance,
+            uncovered_shortfall: breakdown.shortfall,
+            insurance_vault_balance: sources.insurance_vault.lamports(),
+            timestamp: current_time,
+        });
+    }
+
+    Ok(breakdown)
+}
+
+// Moves up to `amount` to the recipient, pool first, then insurance. Both vaults keep
+// their rent-exempt minimum. No bad-debt bookkeeping.
+pub fn draw_payout(sources: &PayoutSources, amount: u64) -> Result<PayoutBreakdown> {
+    let rent_floor = Rent::get()?.minimum_balance(0);
+
+    let pool_available = sources.trading_pool_vault.lamports().saturating_sub(rent_floor);
+    let from_pool = amount.min(pool_available);
+
+    let insurance_available = sources.insurance_vault.lamports().saturating_sub(rent_floor);
+    let from_insurance = (amount - from_pool).min(insurance_available);
+
+    if from_pool > 0 {
+        let cpi_ctx = CpiContext::new_with_signer(
+            sources.system_program.clone(),
+            Transfer {
+                from: sources.trading_pool_vault.clone(),
+                to: sources.recipient.clone(),
+            },
+            sources.pool_signer_seeds,
+        );
+        transfer(cpi_ctx, from_pool)?;
+    }
+
+    if from_insurance > 0 {
+        let cpi_ctx = CpiContext::new_with_signer(
+            sources.system_program.clone(),
+            Transfer {
+                from: sources.insurance_vault.clone(),
+                to: sources.recipient.clone(),
+            },
+            sources.insurance_signer_seeds,
+        );
+        transfer(cpi_ctx, from_insurance)?;
+    }
+
+    Ok(PayoutBreakdown {
+        from_pool,
+        from_insurance,
+        shortfall: amount - from_pool - from_insurance,
+    })
+}
+
+#[event]
+pub struct InsuranceFundInitializedEvent {
+    pub insurance_fund: Pubkey,
+    pub insurance_vault: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceFundDepositEvent {
+    pub insurance_fund: Pubkey,
+    pub admin: Pubkey,
+    pub amount: u64,
+    pub vault_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BadDebtEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub payout_amount: u64,
+    pub paid_from_pool: u64,
+    pub covered_by_insurance: u64,
+    pub uncovered_shortfall: u64,
+    pub insurance_vault_balance: u64,
+    pub timestamp: i64,
+}
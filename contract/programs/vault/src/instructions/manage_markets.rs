@@ -0,0 +1,175 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Market, MarketParams, PriceHistory, PriceObservation, ProtocolConfig, PRICE_HISTORY_CAPACITY};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(symbol: [u8; 8])]
+pub struct CreateMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [b"market", symbol.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateMarket<'info> {
+    pub fn create_market(&mut self, symbol: [u8; 8], params: MarketParams, bumps: &CreateMarketBumps) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        params.validate()?;
+
+        self.market.set_inner(Market {
+            symbol,
+            params,
+            last_updated: current_time,
+            bump: bumps.market,
+        });
+
+        emit!(MarketCreatedEvent {
+            market: self.market.key(),
+            symbol,
+            params,
+            admin: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarket<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> UpdateMarket<'info> {
+    pub fn update_market(&mut self, params: MarketParams) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // Reject the whole update if any field is out of bounds
+        params.validate()?;
+
+        self.market.params = params;
+        self.market.last_updated = current_time;
+
+        emit!(MarketUpdatedEvent {
+            market: self.market.key(),
+            symbol: self.market.symbol,
+            params,
+            admin: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitPriceHistory<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [b"market", market.symbol.as_ref()],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PriceHistory::INIT_SPACE,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitPriceHistory<'info> {
+    pub fn init_price_history(&mut self, bumps: &InitPriceHistoryBumps) -> Result<()> {
+        self.price_history.set_inner(PriceHistory {
+            market: self.market.key(),
+            head: 0,
+            count: 0,
+            observations: [PriceObservation::default(); PRICE_HISTORY_CAPACITY],
+            breaker_tripped_at: 0,
+            bump: bumps.price_history,
+        });
+
+        emit!(PriceHistoryInitializedEvent {
+            market: self.market.key(),
+            price_history: self.price_history.key(),
+            capacity: PRICE_HISTORY_CAPACITY as u16,
+            admin: self.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct MarketCreatedEvent {
+    pub market: Pubkey,
+    pub symbol: [u8; 8],
+    pub params: MarketParams,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketUpdatedEvent {
+    pub market: Pubkey,
+    pub symbol: [u8; 8],
+    pub params: MarketParams,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PriceHistoryInitializedEvent {
+    pub market: Pubkey,
+    pub price_history: Pubkey,
+    pub capacity: u16,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
@@ -0,0 +1,150 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{CurveKind, PayoutCurve, PositionType, ProtocolConfig};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(position_type: PositionType)]
+pub struct InitPayoutCurve<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PayoutCurve::INIT_SPACE,
+        seeds = [b"payout_curve".as_ref(), &[position_type as u8]],
+        bump
+    )]
+    pub payout_curve: Account<'info, PayoutCurve>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitPayoutCurve<'info> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_payout_curve(
+        &mut self,
+        position_type: PositionType,
+        kind: CurveKind,
+        max_payout_percentage: u8,
+        min_payout_percentage: u8,
+        step_count: u8,
+        half_life_secs: i64,
+        bumps: &InitPayoutCurveBumps
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // Curves only apply to range products
+        require!(
+            position_type != PositionType::Leveraged,
+            ErrorCode::InvalidPositionType
+        );
+
+        self.payout_curve.set_inner(PayoutCurve {
+            authority: self.admin.key(),
+            position_type,
+            kind,
+            max_payout_percentage,
+            min_payout_percentage,
+            step_count,
+            half_life_secs,
+            last_updated: current_time,
+            bump: bumps.payout_curve,
+        });
+
+        self.payout_curve.validate()?;
+
+        emit!(PayoutCurveUpdatedEvent {
+            payout_curve: self.payout_curve.key(),
+            position_type,
+            kind,
+            max_payout_percentage,
+            min_payout_percentage,
+            step_count,
+            half_life_secs,
+            updated_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdatePayoutCurve<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"payout_curve".as_ref(), &[payout_curve.position_type as u8]],
+        bump = payout_curve.bump
+    )]
+    pub payout_curve: Account<'info, PayoutCurve>,
+}
+
+impl<'info> UpdatePayoutCurve<'info> {
+    pub fn update_payout_curve(
+        &mut self,
+        kind: CurveKind,
+        max_payout_percentage: u8,
+        min_payout_percentage: u8,
+        step_count: u8,
+        half_life_secs: i64,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let payout_curve = &mut self.payout_curve;
+        payout_curve.authority = self.admin.key();
+        payout_curve.kind = kind;
+        payout_curve.max_payout_percentage = max_payout_percentage;
+        payout_curve.min_payout_percentage = min_payout_percentage;
+        payout_curve.step_count = step_count;
+        payout_curve.half_life_secs = half_life_secs;
+        payout_curve.last_updated = current_time;
+
+        // Reject the whole update if any field is out of bounds
+        payout_curve.validate()?;
+
+        emit!(PayoutCurveUpdatedEvent {
+            payout_curve: payout_curve.key(),
+            position_type: payout_curve.position_type,
+            kind,
+            max_payout_percentage,
+            min_payout_percentage,
+            step_count,
+            half_life_secs,
+            updated_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct PayoutCurveUpdatedEvent {
+    pub payout_curve: Pubkey,
+    pub position_type: PositionType,
+    pub kind: CurveKind,
+    pub max_payout_percentage: u8,
+    pub min_payout_percentage: u8,
+    pub step_count: u8,
+    pub half_life_secs: i64,
+    pub updated_by: Pubkey,
+    pub timestamp: i64,
+}
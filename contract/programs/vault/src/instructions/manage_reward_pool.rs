@@ -0,0 +1,258 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::{ProtocolConfig, RewardPool};
+use crate::constants::{BASE_REWARD_RATE_BPS, MAX_CONFIG_FEE_BPS};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct InitRewardPool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = RewardPool::LEN,
+        seeds = [b"reward_pool"],
+        bump
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(
+        seeds = [b"reward_pool_vault", reward_pool.key().as_ref()],
+        bump
+    )]
+    pub reward_pool_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitRewardPool<'info> {
+    pub fn init_reward_pool(
+        &mut self,
+        emission_rate_per_second: u64,
+        emission_start_time: i64,
+        emission_end_time: i64,
+        bumps: &InitRewardPoolBumps
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        validate_emission_schedule(emission_start_time, emission_end_time, current_time)?;
+
+        // Time-based position rewards start at the default rate; the reward pool is the
+        // only place the live rate is kept, see update_reward_schedule
+        let base_reward_rate = BASE_REWARD_RATE_BPS;
+
+        self.reward_pool.set_inner(RewardPool {
+            authority: self.admin.key(),
+            total_reward_amount: 0,
+            total_distributed: 0,
+            base_reward_rate,
+            performance_pool_amount: 0,
+            last_distribution_time: current_time,
+            total_staked: 0,
+            acc_reward_per_share: 0,
+            emission_rate_per_second,
+            emission_start_time,
+            emission_end_time,
+            last_accrual_time: current_time,
+            total_accrued: 0,
+            vault_bump: bumps.reward_pool_vault,
+            bump: bumps.reward_pool,
+        });
+
+        emit!(RewardPoolInitializedEvent {
+            reward_pool: self.reward_pool.key(),
+            reward_pool_vault: self.reward_pool_vault.key(),
+            authority: self.admin.key(),
+            base_reward_rate,
+            emission_rate_per_second,
+            emission_start_time,
+            emission_end_time,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FundRewardPool<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool"],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool_vault", reward_pool.key().as_ref()],
+        bump = reward_pool.vault_bump
+    )]
+    pub reward_pool_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FundRewardPool<'info> {
+    // Anyone may fund. `emission_amount` backs the staking schedule,
+    // `performance_amount` backs performance rewards paid in claim_position.
+    pub fn fund_reward_pool(&mut self, emission_amount: u64, performance_amount: u64) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let total = emission_amount
+            .checked_add(performance_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(total > 0, ErrorCode::AmountTooSmall);
+
+        // Checkpoint so new funds only extend emissions from now on
+        self.reward_pool.accrue(current_time)?;
+
+        let cpi_ctx = CpiContext::new(
+            self.system_program.to_account_info(),
+            Transfer {
+                from: self.funder.to_account_info(),
+                to: self.reward_pool_vault.to_account_info(),
+            },
+        );
+
+        transfer(cpi_ctx, total)?;
+
+        self.reward_pool.total_reward_amount = self.reward_pool.total_reward_amount
+            .checked_add(emission_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.reward_pool.performance_pool_amount = self.reward_pool.performance_pool_amount
+            .checked_add(performance_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(RewardPoolFundedEvent {
+            reward_pool: self.reward_pool.key(),
+            funder: self.funder.key(),
+            emission_amount,
+            performance_amount,
+            total_reward_amount: self.reward_pool.total_reward_amount,
+            performance_pool_amount: self.reward_pool.performance_pool_amount,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateRewardSchedule<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool"],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+}
+
+impl<'info> UpdateRewardSchedule<'info> {
+    // Accrues everything earned under the old schedule before switching,
+    // so a rate change never applies retroactively.
+    pub fn update_reward_schedule(
+        &mut self,
+        emission_rate_per_second: u64,
+        emission_start_time: i64,
+        emission_end_time: i64,
+        base_reward_rate: u16
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        validate_emission_schedule(emission_start_time, emission_end_time, current_time)?;
+        require!(base_reward_rate <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+
+        self.reward_pool.accrue(current_time)?;
+
+        let previous_emission_rate = self.reward_pool.emission_rate_per_second;
+        let previous_base_reward_rate = self.reward_pool.base_reward_rate;
+
+        self.reward_pool.emission_rate_per_second = emission_rate_per_second;
+        self.reward_pool.emission_start_time = emission_start_time;
+        self.reward_pool.emission_end_time = emission_end_time;
+        self.reward_pool.base_reward_rate = base_reward_rate;
+
+        emit!(RewardScheduleUpdatedEvent {
+            reward_pool: self.reward_pool.key(),
+            admin: self.admin.key(),
+            previous_emission_rate,
+            emission_rate_per_second,
+            emission_start_time,
+            emission_end_time,
+            previous_base_reward_rate,
+            base_reward_rate,
+            acc_reward_per_share: self.reward_pool.acc_reward_per_share,
+            total_accrued: self.reward_pool.total_accrued,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+fn validate_emission_schedule(start_time: i64, end_time: i64, current_time: i64) -> Result<()> {
+    require!(end_time > start_time, ErrorCode::InvalidEmissionSchedule);
+    require!(end_time > current_time, ErrorCode::InvalidEmissionSchedule);
+    Ok(())
+}
+
+#[event]
+pub struct RewardPoolInitializedEvent {
+    pub reward_pool: Pubkey,
+    pub reward_pool_vault: Pubkey,
+    pub authority: Pubkey,
+    pub base_reward_rate: u16,
+    pub emission_rate_per_second: u64,
+    pub emission_start_time: i64,
+    pub emission_end_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardPoolFundedEvent {
+    pub reward_pool: Pubkey,
+    pub funder: Pubkey,
+    pub emission_amount: u64,
+    pub performance_amount: u64,
+    pub total_reward_amount: u64,
+    pub performance_pool_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardScheduleUpdatedEvent {
+    pub reward_pool: Pubkey,
+    pub admin: Pubkey,
+    pub previous_emission_rate: u64,
+    pub emission_rate_per_second: u64,
+    pub emission_start_time: i64,
+    pub emission_end_time: i64,
+    pub previous_base_reward_rate: u16,
+    pub base_reward_rate: u16,
+    pub acc_reward_per_share: u128,
+    pub total_accrued: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,222 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ProtocolConfig, SanctionsPage, SanctionsRegistry};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct InitSanctionsRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + SanctionsRegistry::INIT_SPACE,
+        seeds = [b"sanctions_registry"],
+        bump
+    )]
+    pub sanctions_registry: Account<'info, SanctionsRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitSanctionsRegistry<'info> {
+    pub fn init_sanctions_registry(
+        &mut self,
+        compliance_authority: Pubkey,
+        bumps: &InitSanctionsRegistryBumps
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        self.sanctions_registry.set_inner(SanctionsRegistry {
+            admin: self.admin.key(),
+            compliance_authority,
+            total_entries: 0,
+            last_updated: current_time,
+            bump: bumps.sanctions_registry,
+        });
+
+        emit!(ComplianceAuthorityUpdatedEvent {
+            registry: self.sanctions_registry.key(),
+            compliance_authority,
+            updated_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetComplianceAuthority<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"sanctions_registry"],
+        bump = sanctions_registry.bump
+    )]
+    pub sanctions_registry: Account<'info, SanctionsRegistry>,
+}
+
+impl<'info> SetComplianceAuthority<'info> {
+    pub fn set_compliance_authority(&mut self, compliance_authority: Pubkey) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        self.sanctions_registry.admin = self.admin.key();
+        self.sanctions_registry.compliance_authority = compliance_authority;
+        self.sanctions_registry.last_updated = current_time;
+
+        emit!(ComplianceAuthorityUpdatedEvent {
+            registry: self.sanctions_registry.key(),
+            compliance_authority,
+            updated_by: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(bucket: u8)]
+pub struct InitSanctionsPage<'info> {
+    #[account(mut)]
+    pub compliance_authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"sanctions_registry"],
+        bump = sanctions_registry.bump,
+        constraint = sanctions_registry.compliance_authority == compliance_authority.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub sanctions_registry: Account<'info, SanctionsRegistry>,
+
+    #[account(
+        init,
+        payer = compliance_authority,
+        space = 8 + SanctionsPage::INIT_SPACE,
+        seeds = [b"sanctions_page".as_ref(), &[bucket]],
+        bump
+    )]
+    pub sanctions_page: Account<'info, SanctionsPage>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitSanctionsPage<'info> {
+    // Pages are created before the first address in their bucket is listed
+    pub fn init_sanctions_page(&mut self, bucket: u8, bumps: &InitSanctionsPageBumps) -> Result<()> {
+        self.sanctions_page.set_inner(SanctionsPage {
+            bucket,
+            entries: Vec::new(),
+            bump: bumps.sanctions_page,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct UpdateSanctionsList<'info> {
+    #[account(mut)]
+    pub compliance_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"sanctions_registry"],
+        bump = sanctions_registry.bump,
+        constraint = sanctions_registry.compliance_authority == compliance_authority.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub sanctions_registry: Account<'info, SanctionsRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"sanctions_page", &[SanctionsPage::bucket_for(&address)]],
+        bump = sanctions_page.bump
+    )]
+    pub sanctions_page: Account<'info, SanctionsPage>,
+}
+
+impl<'info> UpdateSanctionsList<'info> {
+    pub fn add_sanctioned_address(&mut self, address: Pubkey) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        self.sanctions_page.insert(address)?;
+
+        self.sanctions_registry.total_entries = self.sanctions_registry.total_entries
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.sanctions_registry.last_updated = current_time;
+
+        emit!(SanctionsListUpdatedEvent {
+            address,
+            page: self.sanctions_page.key(),
+            is_sanctioned: true,
+            updated_by: self.compliance_authority.key(),
+            total_entries: self.sanctions_registry.total_entries,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_sanctioned_address(&mut self, address: Pubkey) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        self.sanctions_page.remove(&address)?;
+
+        self.sanctions_registry.total_entries = self.sanctions_registry.total_entries
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.sanctions_registry.last_updated = current_time;
+
+        emit!(SanctionsListUpdatedEvent {
+            address,
+            page: self.sanctions_page.key(),
+            is_sanctioned: false,
+            updated_by: self.compliance_authority.key(),
+            total_entries: self.sanctions_registry.total_entries,
+            timestamp: current_time,
+        });
+
+        // An empty page goes back to the authority that paid for it
+        if self.sanctions_page.entries.is_empty() {
+            self.sanctions_page.close(self.compliance_authority.to_account_info())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct ComplianceAuthorityUpdatedEvent {
+    pub registry: Pubkey,
+    pub compliance_authority: Pubkey,
+    pub updated_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SanctionsListUpdatedEvent {
+    pub address: Pubkey,
+    pub page: Pubkey,
+    pub is_sanctioned: bool,
+    pub updated_by: Pubkey,
+    pub total_entries: u32,
+    pub timestamp: i64,
+}
@@ -0,0 +1,181 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::{FreezeRecord, ProtocolConfig, RewardPool, StakeRewards};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct ManageStake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"stake_rewards", user.key().as_ref()],
+        bump,
+        constraint = stake_rewards.user == user.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub stake_rewards: Account<'info, StakeRewards>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool"],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool_vault", reward_pool.key().as_ref()],
+        bump = reward_pool.vault_bump
+    )]
+    pub reward_pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ManageStake<'info> {
+    // Re-stakes shares previously removed with `unstake`
+    pub fn stake(&mut self, amount: u64) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(amount > 0, ErrorCode::AmountTooSmall);
+        require!(
+            self.stake_rewards.unstaked_shares >= amount,
+            ErrorCode::InsufficientStake
+        );
+
+        let earned = self.settle(current_time)?;
+
+        self.stake_rewards.unstaked_shares -= amount;
+        self.stake_rewards.total_staked = self.stake_rewards.total_staked
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.stake_rewards.last_stake_time = current_time;
+        self.reward_pool.total_staked = self.reward_pool.total_staked
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.stake_rewards.checkpoint(&self.reward_pool)?;
+
+        emit!(StakeUpdatedEvent {
+            user: self.user.key(),
+            shares_staked: amount,
+            shares_unstaked: 0,
+            total_staked: self.stake_rewards.total_staked,
+            rewards_settled: earned,
+            pending_rewards: self.stake_rewards.pending_rewards,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Stops shares from earning; they stay owned by the user
+    pub fn unstake(&mut self, amount: u64) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(amount > 0, ErrorCode::AmountTooSmall);
+
+        let earned = self.stake_rewards.unstake(&mut self.reward_pool, amount, current_time)?;
+
+        emit!(StakeUpdatedEvent {
+            user: self.user.key(),
+            shares_staked: 0,
+            shares_unstaked: amount,
+            total_staked: self.stake_rewards.total_staked,
+            rewards_settled: earned,
+            pending_rewards: self.stake_rewards.pending_rewards,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_rewards(&mut self) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(!self.protocol_config.is_paused, ErrorCode::ProgramPaused);
+        FreezeRecord::ensure_not_frozen(&self.freeze_record.to_account_info())?;
+
+        self.settle(current_time)?;
+
+        let amount = self.stake_rewards.pending_rewards;
+        require!(amount > 0, ErrorCode::NoRewardsToClaim);
+        require!(
+            self.reward_pool_vault.lamports() >= amount,
+            ErrorCode::InsufficientRewardReserves
+        );
+
+        let reward_pool_seeds = &[
+            b"reward_pool_vault",
+            self.reward_pool.to_account_info().key.as_ref(),
+            &[self.reward_pool.vault_bump],
+        ];
+        let signer_seeds = &[&reward_pool_seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            Transfer {
+                from: self.reward_pool_vault.to_account_info(),
+                to: self.user.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        transfer(cpi_ctx, amount)?;
+
+        self.stake_rewards.pending_rewards = 0;
+        self.stake_rewards.total_claimed = self.stake_rewards.total_claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        self.reward_pool.total_distributed = self.reward_pool.total_distributed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.reward_pool.last_distribution_time = current_time;
+
+        emit!(StakingRewardsClaimedEvent {
+            user: self.user.key(),
+            amount,
+            total_claimed: self.stake_rewards.total_claimed,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    fn settle(&mut self, current_time: i64) -> Result<u64> {
+        self.reward_pool.accrue(current_time)?;
+        self.stake_rewards.settle_pending(&self.reward_pool, current_time)
+    }
+}
+
+#[event]
+pub struct StakeUpdatedEvent {
+    pub user: Pubkey,
+    pub shares_staked: u64,
+    pub shares_unstaked: u64,
+    pub total_staked: u64,
+    pub rewards_settled: u64,
+    pub pending_rewards: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakingRewardsClaimedEvent {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+    pub timestamp: i64,
+}
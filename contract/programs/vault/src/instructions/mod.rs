@@ -10,6 +10,12 @@ pub use withdraw::*;
 pub mod close_vault;
 pub use close_vault::*;
 
+pub mod manage_stake;
+pub use manage_stake::*;
+
+pub mod manage_reward_pool;
+pub use manage_reward_pool::*;
+
 
 // <---------------- Position Management ----------------------->
 
@@ -17,12 +23,63 @@ pub use close_vault::*;
 pub mod create_position;
 pub use create_position::*;
 
+pub mod create_leveraged_position;
+pub use create_leveraged_position::*;
+
 pub mod check_position;
 pub use check_position::*;
 
 pub mod claim_position;
 pub use claim_position::*;
 
+pub mod manage_payout_curve;
+pub use manage_payout_curve::*;
+
+pub mod liquidate_position;
+pub use liquidate_position::*;
+
+pub mod position_margin;
+pub use position_margin::*;
+
+
+// <---------------- Config ----------------------->
+
+pub mod init_config;
+pub use init_config::*;
+
+pub mod update_config;
+pub use update_config::*;
+
+pub mod manage_markets;
+pub use manage_markets::*;
+
+pub mod circuit_breaker;
+pub use circuit_breaker::*;
+
+pub mod manage_fees;
+pub use manage_fees::*;
+
+pub mod manage_insurance_fund;
+pub use manage_insurance_fund::*;
+
+pub mod bad_debt;
+pub use bad_debt::*;
+
+pub mod token_collateral;
+pub use token_collateral::*;
+
+pub mod admin_control;
+pub use admin_control::*;
+
+pub mod freeze_account;
+pub use freeze_account::*;
+
+pub mod manage_sanctions;
+pub use manage_sanctions::*;
+
+pub mod emergency_withdraw;
+pub use emergency_withdraw::*;
+
 
 // <---------------- Pool ----------------------->
 
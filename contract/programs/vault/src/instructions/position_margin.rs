@@ -0,0 +1,462 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+use crate::state::{CollateralMint, PoolLiquidity, PositionState, PositionStatus, PositionType, ProtocolConfig, TradingPool, VaultState};
+use crate::instructions::TokenRoute;
+use crate::oracle;
+use crate::error::ErrorCode;
+
+// Values every margin asset of a position from the collateral registry and stores the
+// haircut total. `accounts` holds one [CollateralMint, PriceUpdateV2] pair per asset: the
+// position's own collateral first, then each non-empty margin deposit in slot order.
+pub fn revalue_position_margin(
+    position: &mut Account<PositionState>,
+    accounts: &[AccountInfo],
+    maximum_age: u64,
+    clock: &Clock,
+) -> Result<()> {
+    let mut assets = vec![(position.collateral_mint, position.collateral_amount)];
+    assets.extend(
+        position.margin_deposits.iter()
+            .filter(|deposit| deposit.amount > 0)
+            .map(|deposit| (deposit.mint, deposit.amount)),
+    );
+
+    require!(accounts.len() == assets.len() * 2, ErrorCode::MissingMarginAccounts);
+
+    let mut margin_value: u64 = 0;
+    let mut collateral_value: u64 = 0;
+
+    for (index, (mint, amount)) in assets.iter().enumerate() {
+        let registry = load_registry_entry(&accounts[index * 2], *mint)?;
+        let price_update = load_price_update(&accounts[index * 2 + 1])?;
+
+        let price = oracle::get_feed_price(&price_update, &registry.price_feed_id, clock, maximum_age)?;
+
+        let usd_value = registry.usd_value(*amount, price.price)?;
+        margin_value = margin_value
+            .checked_add(registry.margin_value(usd_value))
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // The position's own collateral also converts the total back into its units
+        if index == 0 {
+            collateral_value = usd_value;
+        }
+    }
+
+    let effective_collateral = if collateral_value > 0 {
+        (position.collateral_amount as u128 * margin_value as u128 / collateral_value as u128)
+            .min(u64::MAX as u128) as u64
+    } else {
+        0
+    };
+
+    position.margin_value = margin_value;
+    position.effective_collateral = effective_collateral;
+    position.margin_valued_at = clock.unix_timestamp;
+
+    emit!(PositionMarginValuedEvent {
+        position: position.key(),
+        user: position.user,
+        margin_value,
+        effective_collateral,
+        collateral_amount: position.collateral_amount,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn load_registry_entry(account: &AccountInfo, mint: Pubkey) -> Result<CollateralMint> {
+    require_keys_eq!(*account.owner, crate::ID, ErrorCode::InvalidCollateralAccount);
+
+    let registry = CollateralMint::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    require_keys_eq!(registry.mint, CollateralMint::registry_mint(mint), ErrorCode::CollateralMintMismatch);
+
+    Ok(registry)
+}
+
+fn load_price_update(account: &AccountInfo) -> Result<PriceUpdateV2> {
+    require_keys_eq!(*account.owner, pyth_solana_receiver_sdk::ID, ErrorCode::InvalidPriceFeed);
+
+    let price_update = PriceUpdateV2::try_deserialize(&mut &account.try_borrow_data()?[..])
+        .map_err(|_| error!(ErrorCode::InvalidPriceFeed))?;
+
+    Ok(price_update)
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct AddPositionMargin<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            user.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump = position.bump,
+        constraint = position.user == user.key() && position.order_id == order_id,
+        constraint = position.position_type == PositionType::Leveraged @ ErrorCode::InvalidPositionType,
+        constraint = position.is_open() @ ErrorCode::PositionAlreadySettled,
+    )]
+    pub position: Account<'info, PositionState>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user_vault_state.key().as_ref()],
+        bump = user_vault_state.vault_bump,
+    )]
+    pub user_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = user_vault_state.state_bump
+    )]
+    pub user_vault_state: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    // SPL margin; leave all unset to post native SOL
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump,
+        constraint = collateral_mint.is_enabled @ ErrorCode::CollateralMintDisabled
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AddPositionMargin<'info> {
+    pub fn add_position_margin(&mut self, amount: u64, remaining_accounts: &[AccountInfo]) -> Result<()> {
+        let clock = Clock::get()?;
+
+        require!(!self.protocol_config.is_paused, ErrorCode::ProgramPaused);
+        require!(amount > 0, ErrorCode::InvalidCollateralAmount);
+
+        let token_route = TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )?;
+
+        // Margin sits in the pool next to the position's collateral
+        match &token_route {
+            Some(route) => {
+                let vault_token_account = route.check_vault_token_account(
+                    self.vault_token_account.as_deref(),
+                    &self.user_vault_state.key(),
+                )?;
+                let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+                let pool_token_vault = route.check_program_vault(
+                    self.pool_token_vault.as_deref(),
+                    &collateral_mint.pool_token_vault,
+                )?;
+
+                let vault_balance = self.vault_token_account.as_ref().map(|account| account.amount).unwrap_or(0);
+                require!(vault_balance >= amount, ErrorCode::InsufficientVaultBalance);
+
+                route.transfer_from_program(vault_token_account, pool_token_vault, amount)?;
+
+                collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            },
+            None => {
+                require!(self.user_vault.lamports() >= amount, ErrorCode::InsufficientVaultBalance);
+
+                let user_vault_seeds = &[
+                    b"vault".as_ref(),
+                    self.user_vault_state.to_account_info().key.as_ref(),
+                    &[self.user_vault_state.vault_bump],
+                ];
+                let signer_seeds = &[&user_vault_seeds[..]];
+
+                let cpi_ctx = CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.user_vault.to_account_info(),
+                        to: self.trading_pool_vault.to_account_info(),
+                    },
+                    signer_seeds,
+                );
+                transfer(cpi_ctx, amount)?;
+
+                self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+        }
+
+        let mint = token_route.as_ref().map(|route| route.mint_key).unwrap_or_default();
+        let slot = self.position.add_margin_deposit(mint, amount)?;
+
+        // New margin only counts once it is valued against the registry
+        revalue_position_margin(
+            &mut self.position,
+            remaining_accounts,
+            self.protocol_config.params.maximum_age,
+            &clock,
+        )?;
+
+        if self.position.status == PositionStatus::LiquidationRisk {
+            self.position.status = PositionStatus::Active;
+        }
+
+        emit!(PositionMarginAddedEvent {
+            position: self.position.key(),
+            user: self.user.key(),
+            mint,
+            slot: slot as u8,
+            amount,
+            margin_value: self.position.margin_value,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ReleasePositionMargin<'info> {
+    // Permissionless: margin only ever goes back to the owner's vault or to LPs
+    pub caller: Signer<'info>,
+
+    /// CHECK: Position owner, only used for seed derivation
+    pub user: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            user.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump = position.bump,
+        constraint = position.user == user.key() && position.order_id == order_id,
+        constraint = !position.is_open() @ ErrorCode::PositionStillOpen,
+    )]
+    pub position: Account<'info, PositionState>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user_vault_state.key().as_ref()],
+        bump = user_vault_state.vault_bump,
+    )]
+    pub user_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = user_vault_state.state_bump
+    )]
+    pub user_vault_state: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_liquidity"],
+        bump = pool_liquidity.bump
+    )]
+    pub pool_liquidity: Account<'info, PoolLiquidity>,
+
+    // SPL margin; required when the slot holds a token, unset for SOL
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ReleasePositionMargin<'info> {
+    // Settled positions get their margin back; a liquidated position forfeits it to LPs
+    pub fn release_position_margin(&mut self, slot: u8) -> Result<()> {
+        let slot = slot as usize;
+        require!(slot < self.position.margin_deposits.len(), ErrorCode::EmptyMarginSlot);
+
+        let deposit = self.position.margin_deposits[slot];
+        require!(deposit.amount > 0, ErrorCode::EmptyMarginSlot);
+
+        let token_route = TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )?;
+        let provided = token_route.as_ref().map(|route| route.mint_key).unwrap_or_default();
+        require_keys_eq!(provided, deposit.mint, ErrorCode::CollateralMintMismatch);
+
+        let forfeited = self.position.status == PositionStatus::Liquidated;
+
+        match &token_route {
+            Some(route) => {
+                let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+
+                if forfeited {
+                    collateral_mint.total_liquidity = collateral_mint.total_liquidity
+                        .checked_add(deposit.amount)
+                        .ok_or(ErrorCode::MathOverflow)?;
+                } else {
+                    let pool_token_vault = route.check_program_vault(
+                        self.pool_token_vault.as_deref(),
+                        &collateral_mint.pool_token_vault,
+                    )?;
+                    let vault_token_account = route.check_vault_token_account(
+                        self.vault_token_account.as_deref(),
+                        &self.user_vault_state.key(),
+                    )?;
+
+                    route.transfer_from_program(pool_token_vault, vault_token_account, deposit.amount)?;
+
+                    collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+                        .saturating_sub(deposit.amount);
+                }
+            },
+            None => {
+                if forfeited {
+                    self.pool_liquidity.total_liquidity = self.pool_liquidity.total_liquidity
+                        .checked_add(deposit.amount)
+                        .ok_or(ErrorCode::MathOverflow)?;
+                } else {
+                    require!(
+                        self.trading_pool_vault.lamports() >= deposit.amount,
+                        ErrorCode::InsufficientPoolBalance
+                    );
+
+                    let pool_seeds = &[
+                        b"trading_pool_vault",
+                        self.trading_pool.to_account_info().key.as_ref(),
+                        &[self.trading_pool.vault_bump],
+                    ];
+                    let signer_seeds = &[&pool_seeds[..]];
+
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        self.system_program.to_account_info(),
+                        Transfer {
+                            from: self.trading_pool_vault.to_account_info(),
+                            to: self.user_vault.to_account_info(),
+                        },
+                        signer_seeds,
+                    );
+                    transfer(cpi_ctx, deposit.amount)?;
+
+                    self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+                        .saturating_sub(deposit.amount);
+                }
+            }
+        }
+
+        self.position.margin_deposits[slot].amount = 0;
+
+        emit!(PositionMarginReleasedEvent {
+            position: self.position.key(),
+            user: self.position.user,
+            mint: deposit.mint,
+            slot: slot as u8,
+            amount: deposit.amount,
+            forfeited,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct PositionMarginValuedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub margin_value: u64,
+    pub effective_collateral: u64,
+    pub collateral_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionMarginAddedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub slot: u8,
+    pub amount: u64,
+    pub margin_value: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionMarginReleasedEvent {
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub slot: u8,
+    pub amount: u64,
+    pub forfeited: bool,
+    pub timestamp: i64,
+}
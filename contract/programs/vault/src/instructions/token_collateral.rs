@@ -0,0 +1,728 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+use anchor_spl::token_2022::spl_token_2022;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+
+use crate::state::{CollateralMint, FeeCategory, FeeSplit, FeeSplitAmounts, FeeLedger, ProtocolConfig, VaultState};
+use crate::instructions::FeeSplitEvent;
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct RegisterCollateralMint<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + CollateralMint::INIT_SPACE,
+        seeds = [b"collateral_mint", mint.key().as_ref()],
+        bump
+    )]
+    pub collateral_mint: Box<Account<'info, CollateralMint>>,
+
+    /// CHECK: PDA that owns every program token account of `mint`; holds no data
+    #[account(
+        seeds = [b"collateral_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub collateral_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"trading_pool_token_vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = collateral_authority,
+        token::token_program = token_program,
+    )]
+    pub pool_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"reward_pool_token_vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = collateral_authority,
+        token::token_program = token_program,
+    )]
+    pub reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Token-2022 mint extensions the pool accounting cannot live with: transfer fees make the
+// amount received differ from the amount credited, hooks run foreign code on every
+// transfer, confidential transfers move balances the program cannot see, and a permanent
+// delegate can drain program-owned vaults
+const UNSUPPORTED_MINT_EXTENSIONS: [ExtensionType; 5] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::TransferHook,
+    ExtensionType::ConfidentialTransferMint,
+    ExtensionType::ConfidentialTransferFeeConfig,
+    ExtensionType::PermanentDelegate,
+];
+
+fn ensure_supported_extensions(mint: &AccountInfo) -> Result<()> {
+    // SPL Token mints carry no extensions
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let extensions = mint_state.get_extension_types()?;
+
+    require!(
+        !extensions.iter().any(|extension| UNSUPPORTED_MINT_EXTENSIONS.contains(extension)),
+        ErrorCode::UnsupportedMintExtension
+    );
+
+    Ok(())
+}
+
+impl<'info> RegisterCollateralMint<'info> {
+    pub fn register_collateral_mint(
+        &mut self,
+        price_feed_id: [u8; 32],
+        haircut_bps: u16,
+        bumps: &RegisterCollateralMintBumps,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(haircut_bps < 10000, ErrorCode::InvalidHaircut);
+        ensure_supported_extensions(&self.mint.to_account_info())?;
+
+        self.collateral_mint.set_inner(CollateralMint {
+            mint: self.mint.key(),
+            token_program: self.token_program.key(),
+            decimals: self.mint.decimals,
+            is_enabled: true,
+            price_feed_id,
+            haircut_bps,
+            pool_token_vault: self.pool_token_vault.key(),
+            reward_token_vault: self.reward_token_vault.key(),
+            total_liquidity: 0,
+            total_shares: 0,
+            total_pool_amount: 0,
+            total_active_amount: 0,
+            total_fees_collected: 0,
+            protocol_fees: 0,
+            deferred_payouts: 0,
+            total_rewards_funded: 0,
+            total_rewards_distributed: 0,
+            authority_bump: bumps.collateral_authority,
+            bump: bumps.collateral_mint,
+        });
+
+        emit!(CollateralMintRegisteredEvent {
+            mint: self.mint.key(),
+            token_program: self.token_program.key(),
+            decimals: self.mint.decimals,
+            price_feed_id,
+            haircut_bps,
+            pool_token_vault: self.pool_token_vault.key(),
+            reward_token_vault: self.reward_token_vault.key(),
+            admin: self.admin.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetCollateralMintEnabled<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Account<'info, CollateralMint>,
+}
+
+impl<'info> SetCollateralMintEnabled<'info> {
+    pub fn set_collateral_mint_enabled(&mut self, is_enabled: bool) -> Result<()> {
+        self.collateral_mint.is_enabled = is_enabled;
+
+        emit!(CollateralMintStatusUpdatedEvent {
+            mint: self.collateral_mint.mint,
+            is_enabled,
+            admin: self.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetCollateralRisk<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Account<'info, CollateralMint>,
+}
+
+impl<'info> SetCollateralRisk<'info> {
+    // Open positions pick up the new feed and haircut on their next revaluation
+    pub fn set_collateral_risk(&mut self, price_feed_id: [u8; 32], haircut_bps: u16) -> Result<()> {
+        require!(haircut_bps < 10000, ErrorCode::InvalidHaircut);
+
+        let old_haircut_bps = self.collateral_mint.haircut_bps;
+        self.collateral_mint.price_feed_id = price_feed_id;
+        self.collateral_mint.haircut_bps = haircut_bps;
+
+        emit!(CollateralRiskUpdatedEvent {
+            mint: self.collateral_mint.mint,
+            price_feed_id,
+            old_haircut_bps,
+            new_haircut_bps: haircut_bps,
+            admin: self.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct OpenTokenVault<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [b"vault_state", user.key().as_ref()],
+        bump = vault_state.state_bump
+    )]
+    pub vault_state: Account<'info, VaultState>,
+
+    #[account(address = collateral_mint.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        seeds = [b"collateral_mint", mint.key().as_ref()],
+        bump = collateral_mint.bump,
+        constraint = collateral_mint.is_enabled @ ErrorCode::CollateralMintDisabled
+    )]
+    pub collateral_mint: Box<Account<'info, CollateralMint>>,
+
+    /// CHECK: PDA that owns every program token account of `mint`; holds no data
+    #[account(
+        seeds = [b"collateral_authority", mint.key().as_ref()],
+        bump = collateral_mint.authority_bump
+    )]
+    pub collateral_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"vault_token", vault_state.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = collateral_authority,
+        token::token_program = token_program,
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = collateral_mint.token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> OpenTokenVault<'info> {
+    pub fn open_token_vault(&mut self) -> Result<()> {
+        emit!(TokenVaultOpenedEvent {
+            user: self.user.key(),
+            mint: self.mint.key(),
+            vault_token_account: self.vault_token_account.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FundTokenRewards<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = funder,
+        token::token_program = token_program,
+    )]
+    pub funder_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = collateral_mint.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", mint.key().as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Box<Account<'info, CollateralMint>>,
+
+    #[account(
+        mut,
+        address = collateral_mint.reward_token_vault
+    )]
+    pub reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = collateral_mint.token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundTokenRewards<'info> {
+    pub fn fund_token_rewards(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::AmountTooSmall);
+
+        let cpi_ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.funder_token_account.to_account_info(),
+                mint: self.mint.to_account_info(),
+                to: self.reward_token_vault.to_account_info(),
+                authority: self.funder.to_account_info(),
+            },
+        );
+        transfer_checked(cpi_ctx, amount, self.mint.decimals)?;
+
+        self.collateral_mint.total_rewards_funded = self.collateral_mint.total_rewards_funded
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(TokenRewardsFundedEvent {
+            mint: self.mint.key(),
+            funder: self.funder.key(),
+            amount,
+            total_rewards_funded: self.collateral_mint.total_rewards_funded,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CollectTokenFees<'info> {
+    #[account(
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    #[account(address = collateral_mint.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", mint.key().as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Box<Account<'info, CollateralMint>>,
+
+    /// CHECK: PDA that owns every program token account of `mint`; holds no data
+    #[account(
+        seeds = [b"collateral_authority", mint.key().as_ref()],
+        bump = collateral_mint.authority_bump
+    )]
+    pub collateral_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = collateral_mint.pool_token_vault
+    )]
+    pub pool_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = fee_ledger.fee_recipient,
+        token::token_program = token_program,
+    )]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = collateral_mint.token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CollectTokenFees<'info> {
+    // Permissionless: fees can only ever reach the fee recipient's token account
+    pub fn collect_token_fees(&mut self) -> Result<()> {
+        let amount = self.collateral_mint.protocol_fees.min(self.pool_token_vault.amount);
+        require!(amount > 0, ErrorCode::AmountTooSmall);
+
+        let route = TokenRoute {
+            mint: self.mint.to_account_info(),
+            authority: self.collateral_authority.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            mint_key: self.mint.key(),
+            decimals: self.mint.decimals,
+            authority_bump: self.collateral_mint.authority_bump,
+        };
+        route.transfer_from_program(
+            self.pool_token_vault.to_account_info(),
+            self.recipient_token_account.to_account_info(),
+            amount,
+        )?;
+
+        self.collateral_mint.protocol_fees -= amount;
+        self.collateral_mint.total_pool_amount = self.collateral_mint.total_pool_amount
+            .saturating_sub(amount);
+
+        emit!(TokenFeesCollectedEvent {
+            mint: self.mint.key(),
+            recipient_token_account: self.recipient_token_account.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+// Token-side accounts of an instruction that can move SPL collateral
+pub struct TokenRoute<'info> {
+    pub mint: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub mint_key: Pubkey,
+    pub decimals: u8,
+    pub authority_bump: u8,
+}
+
+impl<'info> TokenRoute<'info> {
+    // Builds the route from an instruction's optional collateral accounts. Returns None
+    // for native SOL (no collateral_mint passed); otherwise every account must be present
+    // and match the registry entry.
+    pub fn resolve(
+        collateral_mint: Option<&CollateralMint>,
+        mint: Option<&InterfaceAccount<'info, Mint>>,
+        collateral_authority: Option<&UncheckedAccount<'info>>,
+        token_program: Option<&Interface<'info, TokenInterface>>,
+    ) -> Result<Option<Self>> {
+        let Some(collateral_mint) = collateral_mint else {
+            return Ok(None);
+        };
+
+        let (Some(mint), Some(collateral_authority), Some(token_program)) =
+            (mint, collateral_authority, token_program)
+        else {
+            return Err(ErrorCode::MissingCollateralAccounts.into());
+        };
+
+        require_keys_eq!(mint.key(), collateral_mint.mint, ErrorCode::InvalidCollateralAccount);
+        require_keys_eq!(token_program.key(), collateral_mint.token_program, ErrorCode::InvalidCollateralAccount);
+
+        let expected_authority = Pubkey::create_program_address(
+            &[
+                b"collateral_authority",
+                collateral_mint.mint.as_ref(),
+                &[collateral_mint.authority_bump],
+            ],
+            &crate::ID,
+        ).map_err(|_| error!(ErrorCode::InvalidCollateralAccount))?;
+        require_keys_eq!(collateral_authority.key(), expected_authority, ErrorCode::InvalidCollateralAccount);
+
+        Ok(Some(Self {
+            mint: mint.to_account_info(),
+            authority: collateral_authority.to_account_info(),
+            token_program: token_program.to_account_info(),
+            mint_key: collateral_mint.mint,
+            decimals: collateral_mint.decimals,
+            authority_bump: collateral_mint.authority_bump,
+        }))
+    }
+
+    // The user's program-owned token vault, PDA ["vault_token", vault_state, mint]
+    pub fn check_vault_token_account(
+        &self,
+        account: Option<&InterfaceAccount<'info, TokenAccount>>,
+        vault_state: &Pubkey,
+    ) -> Result<AccountInfo<'info>> {
+        let account = account.ok_or(ErrorCode::MissingCollateralAccounts)?;
+        let (expected, _) = Pubkey::find_program_address(
+            &[b"vault_token", vault_state.as_ref(), self.mint_key.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(account.key(), expected, ErrorCode::InvalidCollateralAccount);
+
+        Ok(account.to_account_info())
+    }
+
+    // A registry-owned vault (pool or reward) recorded on CollateralMint
+    pub fn check_program_vault(
+        &self,
+        account: Option<&InterfaceAccount<'info, TokenAccount>>,
+        expected: &Pubkey,
+    ) -> Result<AccountInfo<'info>> {
+        let account = account.ok_or(ErrorCode::MissingCollateralAccounts)?;
+        require_keys_eq!(account.key(), *expected, ErrorCode::InvalidCollateralAccount);
+
+        Ok(account.to_account_info())
+    }
+
+    // A token account owned by `owner`, e.g. the user's wallet account
+    pub fn check_owner_account(
+        &self,
+        account: Option<&InterfaceAccount<'info, TokenAccount>>,
+        owner: &Pubkey,
+    ) -> Result<AccountInfo<'info>> {
+        let account = account.ok_or(ErrorCode::MissingCollateralAccounts)?;
+        require_keys_eq!(account.mint, self.mint_key, ErrorCode::InvalidCollateralAccount);
+        require_keys_eq!(account.owner, *owner, ErrorCode::InvalidCollateralAccount);
+
+        Ok(account.to_account_info())
+    }
+
+    pub fn transfer_from_user(
+        &self,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        user: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            self.token_program.clone(),
+            TransferChecked {
+                from,
+                mint: self.mint.clone(),
+                to,
+                authority: user,
+            },
+        );
+        transfer_checked(cpi_ctx, amount, self.decimals)
+    }
+
+    // Moves tokens out of a program-owned token account, signed by the collateral authority
+    pub fn transfer_from_program(
+        &self,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 || from.key() == to.key() {
+            return Ok(());
+        }
+
+        let authority_seeds = &[
+            b"collateral_authority".as_ref(),
+            self.mint_key.as_ref(),
+            &[self.authority_bump],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.clone(),
+            TransferChecked {
+                from,
+                mint: self.mint.clone(),
+                to,
+                authority: self.authority.clone(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, self.decimals)
+    }
+}
+
+// Rejects SOL accounts for token positions and vice versa
+pub fn ensure_collateral_matches(expected_mint: Pubkey, route: &Option<TokenRoute>) -> Result<()> {
+    let provided = route.as_ref().map(|route| route.mint_key).unwrap_or_default();
+    require_keys_eq!(provided, expected_mint, ErrorCode::CollateralMintMismatch);
+    Ok(())
+}
+
+// Token fees are not split across separate accounts: the whole fee sits in the pool token
+// vault. The LP share raises the mint's liquidity and the treasury and insurance shares are
+// held as `protocol_fees` for collect_token_fees. There is no referrer share for tokens.
+pub fn retain_token_fee(
+    collateral_mint: &mut CollateralMint,
+    category: FeeCategory,
+    payer: Pubkey,
+    amount: u64,
+    fee_split: &FeeSplit,
+) -> Result<FeeSplitAmounts> {
+    let split = fee_split.split(amount, false);
+    if amount == 0 {
+        return Ok(split);
+    }
+
+    collateral_mint.total_liquidity = collateral_mint.total_liquidity
+        .checked_add(split.lp)
+        .ok_or(ErrorCode::MathOverflow)?;
+    collateral_mint.protocol_fees = collateral_mint.protocol_fees
+        .checked_add(split.treasury + split.insurance)
+        .ok_or(ErrorCode::MathOverflow)?;
+    collateral_mint.total_fees_collected = collateral_mint.total_fees_collected
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(FeeSplitEvent {
+        category,
+        payer,
+        amount,
+        lp_amount: split.lp,
+        treasury_amount: split.treasury,
+        insurance_amount: split.insurance,
+        referrer: Pubkey::default(),
+        referrer_amount: 0,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(split)
+}
+
+// Pays a settlement from the pool token vault. The insurance fund only holds SOL, so any
+// shortfall is written down against the mint's LP liquidity at once and stays owed to the
+// trader; returns that shortfall.
+pub fn pay_token_settlement<'info>(
+    route: &TokenRoute<'info>,
+    collateral_mint: &mut CollateralMint,
+    pool_token_vault: &InterfaceAccount<'info, TokenAccount>,
+    recipient: AccountInfo<'info>,
+    amount: u64,
+    position: Pubkey,
+    user: Pubkey,
+) -> Result<u64> {
+    let paid = amount.min(collateral_mint.payable_balance(pool_token_vault.amount));
+    route.transfer_from_program(pool_token_vault.to_account_info(), recipient, paid)?;
+
+    collateral_mint.total_pool_amount = collateral_mint.total_pool_amount.saturating_sub(paid);
+
+    let shortfall = amount - paid;
+    if shortfall > 0 {
+        socialize_token_bad_debt(collateral_mint, shortfall, position, user)?;
+    }
+
+    Ok(shortfall)
+}
+
+pub fn socialize_token_bad_debt(
+    collateral_mint: &mut CollateralMint,
+    shortfall: u64,
+    position: Pubkey,
+    user: Pubkey,
+) -> Result<()> {
+    let total_liquidity_before = collateral_mint.total_liquidity;
+    collateral_mint.total_liquidity = total_liquidity_before.saturating_sub(shortfall);
+    collateral_mint.deferred_payouts = collateral_mint.deferred_payouts
+        .checked_add(shortfall)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(TokenBadDebtEvent {
+        mint: collateral_mint.mint,
+        position,
+        user,
+        shortfall,
+        total_liquidity_before,
+        total_liquidity_after: collateral_mint.total_liquidity,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event]
+pub struct CollateralMintRegisteredEvent {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    pub price_feed_id: [u8; 32],
+    pub haircut_bps: u16,
+    pub pool_token_vault: Pubkey,
+    pub reward_token_vault: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CollateralMintStatusUpdatedEvent {
+    pub mint: Pubkey,
+    pub is_enabled: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CollateralRiskUpdatedEvent {
+    pub mint: Pubkey,
+    pub price_feed_id: [u8; 32],
+    pub old_haircut_bps: u16,
+    pub new_haircut_bps: u16,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenVaultOpenedEvent {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub vault_token_account: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenRewardsFundedEvent {
+    pub mint: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub total_rewards_funded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenFeesCollectedEvent {
+    pub mint: Pubkey,
+    pub recipient_token_account: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenBadDebtEvent {
+    pub mint: Pubkey,
+    pub position: Pubkey,
+    pub user: Pubkey,
+    pub shortfall: u64,
+    pub total_liquidity_before: u64,
+    pub total_liquidity_after: u64,
+    pub timestamp: i64,
+}
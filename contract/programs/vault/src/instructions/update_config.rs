@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ConfigParams, ProtocolConfig};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.admin == admin.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn update_config(&mut self, params: ConfigParams) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // Reject the whole update if any field is out of bounds
+        params.validate()?;
+
+        self.protocol_config.params = params;
+        self.protocol_config.last_updated = current_time;
+
+        emit!(ProtocolConfigUpdatedEvent {
+            config: self.protocol_config.key(),
+            updated_by: self.admin.key(),
+            params,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct ProtocolConfigUpdatedEvent {
+    pub config: Pubkey,
+    pub updated_by: Pubkey,
+    pub params: ConfigParams,
+    pub timestamp: i64,
+}
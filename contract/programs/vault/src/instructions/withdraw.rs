@@ -1,7 +1,9 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-use crate::state::{VaultState, PositionAccount, PoolLiquidity};
+use crate::state::{VaultState, PositionAccount, PoolLiquidity, share_value, shares_for_value, TradingPool, ProtocolConfig, FreezeRecord, FeeLedger, FeeCategory, CollateralMint, ReferralRecord, StakeRewards, RewardPool};
+use crate::instructions::{distribute_fee, ensure_collateral_matches, retain_token_fee, FeeRoute, TokenRoute};
 use crate::error::ErrorCode;
 
 #[derive(Accounts)]
@@ -38,6 +40,35 @@ pub struct Withdraw<'info> {
     )]
     pub pool_liquidity: Account<'info, PoolLiquidity>,
 
+    #[account(
+        mut,
+        seeds = [b"stake_rewards", user.key().as_ref()],
+        bump,
+        constraint = stake_rewards.user == user.key() @ ErrorCode::UnauthorizedAccess
+    )]
+    pub stake_rewards: Account<'info, StakeRewards>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool"],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool"],
+        bump = trading_pool.bump,
+    )]
+    pub trading_pool: Account<'info, TradingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_pool_vault", trading_pool.key().as_ref()],
+        bump = trading_pool.vault_bump
+    )]
+    pub trading_pool_vault: SystemAccount<'info>,
+
     #[account(
         mut,
         seeds = [b"protocol_treasury"],
@@ -45,47 +76,240 @@ pub struct Withdraw<'info> {
     )]
     pub protocol_treasury: SystemAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault"],
+        bump
+    )]
+    pub insurance_vault: SystemAccount<'info>,
+
+    // Optional referrer, receives the referrer share of fees. Must be the referrer
+    // registered for `user` at their first deposit
+    #[account(mut)]
+    pub referrer: Option<SystemAccount<'info>>,
+
+    /// CHECK: Referral record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"referral", user.key().as_ref()],
+        bump
+    )]
+    pub referral: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Freeze record PDA for `user`, may not exist; checked in the handler
+    #[account(
+        seeds = [b"freeze_record", user.key().as_ref()],
+        bump
+    )]
+    pub freeze_record: UncheckedAccount<'info>,
+
+    // SPL collateral; leave all unset to withdraw native SOL
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint.mint.as_ref()],
+        bump = collateral_mint.bump
+    )]
+    pub collateral_mint: Option<Box<Account<'info, CollateralMint>>>,
+
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// CHECK: Collateral authority PDA for `mint`; checked in the handler
+    pub collateral_authority: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub user_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub pool_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Withdraw<'info> {
+    // `amount` is the value to take out; a full withdrawal redeems every share. Shares are
+    // valued at today's pool value, so LPs receive their share of fee income and absorb
+    // their share of socialized bad debt.
     pub fn withdraw(&mut self, amount: u64, is_full_withdrawal: bool) -> Result<()> {
         let clock = Clock::get()?;
         let current_time = clock.unix_timestamp;
+
+        // Reject frozen wallets
+        FreezeRecord::ensure_not_frozen(&self.freeze_record.to_account_info())?;
         
         // Validate Position Ownership (already done via constraint)
+
+        let token_route = self.token_route()?;
+
+        // Position accounts only pay out in the collateral they were funded with
+        ensure_collateral_matches(self.position_account.collateral_mint, &token_route)?;
+
+        let (total_liquidity, total_shares) = match &self.collateral_mint {
+            Some(collateral_mint) => (collateral_mint.total_liquidity, collateral_mint.total_shares),
+            None => (self.pool_liquidity.total_liquidity, self.pool_liquidity.total_shares),
+        };
+
+        // Shares redeemed and what they are worth
+        let (shares_burned, withdrawal_value) = if is_full_withdrawal {
+            let shares = self.position_account.shares;
+            (shares, share_value(shares, total_liquidity, total_shares)?)
+        } else {
+            (shares_for_value(amount, total_liquidity, total_shares)?, amount)
+        };
+
+        // Validate sufficient balance
+        require!(
+            shares_burned > 0 && shares_burned <= self.position_account.shares,
+            ErrorCode::InsufficientBalance
+        );
+
+        // Principal that leaves with the shares, pro rata
+        let principal_released = if shares_burned == self.position_account.shares {
+            self.position_account.amount
+        } else {
+            ((self.position_account.amount as u128 * shares_burned as u128)
+                / self.position_account.shares as u128) as u64
+        };
         
         // Check if lock period has elapsed
         let lock_period_elapsed = current_time >= self.position_account.lock_end_time;
         
-        let (final_amount, fee_amount) = if lock_period_elapsed {
-            // Calculate Full Withdrawal Amount
-            let withdrawal_amount = if is_full_withdrawal {
-                self.position_account.amount
-            } else {
-                amount
-            };
-            (withdrawal_amount, 0u64)
+        let withdrawal_fee_rate = self.protocol_config.params.withdrawal_fee_bps as u64;
+
+        let fee_rate = if lock_period_elapsed {
+            withdrawal_fee_rate
         } else {
-            // Calculate Early Withdrawal Fee (2-5% penalty)
-            let withdrawal_amount = if is_full_withdrawal {
-                self.position_account.amount
-            } else {
-                amount
-            };
+            // Early Withdrawal Fee (configured penalty tiers) on top of the flat fee
+            self.calculate_early_withdrawal_penalty()? + withdrawal_fee_rate
+        };
+        let fee_amount = ((withdrawal_value as u128 * fee_rate as u128) / 10000) as u64; // basis points
+        let final_amount = withdrawal_value - fee_amount;
+
+        match token_route {
+            Some(route) => {
+                self.withdraw_tokens(&route, principal_released, withdrawal_value, final_amount, fee_amount)?;
+                let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+                collateral_mint.burn_shares(shares_burned, withdrawal_value)?;
+            },
+            None => {
+                // Settle staking rewards and unstake before the shares are burned
+                self.stake_rewards.release_shares(&mut self.reward_pool, shares_burned, current_time)?;
+
+                self.withdraw_sol(principal_released, withdrawal_value, final_amount, fee_amount)?;
+                self.pool_liquidity.burn_shares(shares_burned, withdrawal_value, current_time)?;
+            }
+        }
+
+        // Update Position Account
+        self.position_account.amount -= principal_released;
+        self.position_account.shares -= shares_burned;
+
+        if is_full_withdrawal {
+            // Close Position - Return Rent to User
+            let position_lamports = self.position_account.to_account_info().lamports();
             
-            let penalty_rate = self.calculate_early_withdrawal_penalty()?;
-            let fee = (withdrawal_amount * penalty_rate) / 10000; // basis points
-            let remaining_amount = withdrawal_amount - fee;
+            **self.position_account.to_account_info().try_borrow_mut_lamports()? -= position_lamports;
+            **self.user.to_account_info().try_borrow_mut_lamports()? += position_lamports;
             
-            (remaining_amount, fee)
-        };
+            // Mark position as closed
+            self.position_account.is_active = false;
+        }
 
-        // Validate sufficient balance
-        require!(
-            self.position_account.amount >= amount,
-            ErrorCode::InsufficientBalance
-        );
+        emit!(WithdrawalEvent {
+            user: self.user.key(),
+            amount: final_amount,
+            fee_amount,
+            shares_burned,
+            principal_released,
+            is_full_withdrawal,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    fn token_route(&self) -> Result<Option<TokenRoute<'info>>> {
+        TokenRoute::resolve(
+            self.collateral_mint.as_deref().map(|collateral_mint| &**collateral_mint),
+            self.mint.as_deref(),
+            self.collateral_authority.as_ref(),
+            self.token_program.as_ref(),
+        )
+    }
+
+    fn withdraw_sol(&mut self, principal_released: u64, withdrawal_value: u64, final_amount: u64, fee_amount: u64) -> Result<()> {
+        let vault_state_key = self.vault_state.key();
+        let vault_seeds = &[
+            b"vault",
+            vault_state_key.as_ref(),
+            &[self.vault_state.vault_bump]
+        ];
+        let vault_signer_seeds = &[&vault_seeds[..]];
+
+        // Principal sits in the user's vault and fee income in the trading pool vault, so
+        // settle the difference between the principal and what the shares are worth
+        if withdrawal_value > principal_released {
+            let gain = withdrawal_value - principal_released;
+            require!(
+                self.trading_pool_vault.lamports() >= gain,
+                ErrorCode::InsufficientPoolBalance
+            );
+
+            let trading_pool_key = self.trading_pool.key();
+            let pool_seeds = &[
+                b"trading_pool_vault",
+                trading_pool_key.as_ref(),
+                &[self.trading_pool.vault_bump],
+            ];
+            let pool_signer_seeds = &[&pool_seeds[..]];
+
+            transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.trading_pool_vault.to_account_info(),
+                        to: self.vault.to_account_info(),
+                    },
+                    pool_signer_seeds,
+                ),
+                gain,
+            )?;
+            self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount.saturating_sub(gain);
+        } else if principal_released > withdrawal_value {
+            // Socialized losses stay behind to fund deferred payouts
+            let loss = principal_released - withdrawal_value;
+
+            transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.vault.to_account_info(),
+                        to: self.trading_pool_vault.to_account_info(),
+                    },
+                    vault_signer_seeds,
+                ),
+                loss,
+            )?;
+            self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+                .checked_add(loss)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
 
         // Validate vault has sufficient funds
         require!(
@@ -94,34 +318,11 @@ impl<'info> Withdraw<'info> {
         );
 
         if fee_amount > 0 {
-            // Send Fee to Protocol Treasury
-            let vault_seeds = &[
-                b"vault",
-                self.vault_state.key().as_ref(),
-                &[self.vault_state.vault_bump]
-            ];
-            let vault_signer_seeds = &[&vault_seeds[..]];
-
-            let fee_transfer_cpi = CpiContext::new_with_signer(
-                self.system_program.to_account_info(),
-                Transfer {
-                    from: self.vault.to_account_info(),
-                    to: self.protocol_treasury.to_account_info(),
-                },
-                vault_signer_seeds
-            );
-
-            transfer(fee_transfer_cpi, fee_amount)?;
+            // Split Fee between LPs, treasury, insurance fund and referrer
+            self.distribute_withdrawal_fee(fee_amount)?;
         }
 
         // Transfer remaining amount to user
-        let vault_seeds = &[
-            b"vault",
-            self.vault_state.key().as_ref(),
-            &[self.vault_state.vault_bump]
-        ];
-        let vault_signer_seeds = &[&vault_seeds[..]];
-
         let user_transfer_cpi = CpiContext::new_with_signer(
             self.system_program.to_account_info(),
             Transfer {
@@ -131,34 +332,116 @@ impl<'info> Withdraw<'info> {
             vault_signer_seeds
         );
 
-        transfer(user_transfer_cpi, final_amount)?;
+        transfer(user_transfer_cpi, final_amount)
+    }
 
-        if is_full_withdrawal {
-            // Close Position - Return Rent to User
-            let position_lamports = self.position_account.to_account_info().lamports();
-            
-            **self.position_account.to_account_info().try_borrow_mut_lamports()? -= position_lamports;
-            **self.user.to_account_info().try_borrow_mut_lamports()? += position_lamports;
-            
-            // Mark position as closed
-            self.position_account.amount = 0;
-            self.position_account.is_active = false;
-        } else {
-            // Update Position Account
-            self.position_account.amount -= amount;
+    fn withdraw_tokens(
+        &mut self,
+        route: &TokenRoute<'info>,
+        principal_released: u64,
+        withdrawal_value: u64,
+        final_amount: u64,
+        fee_amount: u64,
+    ) -> Result<()> {
+        let vault_token_account = route.check_vault_token_account(
+            self.vault_token_account.as_deref(),
+            &self.vault_state.key(),
+        )?;
+        let user_token_account = route.check_owner_account(
+            self.user_token_account.as_deref(),
+            &self.user.key(),
+        )?;
+
+        // Validate vault has sufficient funds: the settlement below turns the principal it
+        // holds into exactly `final_amount + fee_amount`
+        let vault_balance = self.vault_token_account.as_ref().map(|account| account.amount).unwrap_or(0);
+        require!(
+            vault_balance >= principal_released,
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        let collateral_mint = self.collateral_mint.as_mut().ok_or(ErrorCode::MissingCollateralAccounts)?;
+        let pool_token_vault = route.check_program_vault(
+            self.pool_token_vault.as_deref(),
+            &collateral_mint.pool_token_vault,
+        )?;
+
+        // Same settlement as withdraw_sol, against the pool token vault
+        if withdrawal_value > principal_released {
+            let gain = withdrawal_value - principal_released;
+            let pool_balance = self.pool_token_vault.as_ref().map(|account| account.amount).unwrap_or(0);
+            require!(
+                collateral_mint.payable_balance(pool_balance) >= gain,
+                ErrorCode::InsufficientPoolBalance
+            );
+
+            route.transfer_from_program(pool_token_vault.clone(), vault_token_account.clone(), gain)?;
+            collateral_mint.total_pool_amount = collateral_mint.total_pool_amount.saturating_sub(gain);
+        } else if principal_released > withdrawal_value {
+            let loss = principal_released - withdrawal_value;
+
+            route.transfer_from_program(vault_token_account.clone(), pool_token_vault.clone(), loss)?;
+            collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+                .checked_add(loss)
+                .ok_or(ErrorCode::MathOverflow)?;
         }
 
-        // Update Pool Liquidity State
-        self.pool_liquidity.total_liquidity -= amount;
-        self.pool_liquidity.last_updated = current_time;
+        // The whole fee moves to the pool token vault, see retain_token_fee
+        if fee_amount > 0 {
+            route.transfer_from_program(vault_token_account.clone(), pool_token_vault, fee_amount)?;
+            retain_token_fee(
+                collateral_mint,
+                FeeCategory::Withdrawal,
+                self.user.key(),
+                fee_amount,
+                &self.protocol_config.params.fee_split,
+            )?;
+            collateral_mint.total_pool_amount = collateral_mint.total_pool_amount
+                .checked_add(fee_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
 
-        emit!(WithdrawalEvent {
-            user: self.user.key(),
-            amount: final_amount,
+        route.transfer_from_program(vault_token_account, user_token_account, final_amount)
+    }
+
+    fn distribute_withdrawal_fee(&mut self, fee_amount: u64) -> Result<()> {
+        let vault_state_key = self.vault_state.key();
+        let vault_seeds = &[
+            b"vault",
+            vault_state_key.as_ref(),
+            &[self.vault_state.vault_bump]
+        ];
+        let vault_signer_seeds = &[&vault_seeds[..]];
+
+        let referrer = ReferralRecord::registered_referrer(
+            &self.referral.to_account_info(),
+            self.referrer.as_ref(),
+        )?;
+
+        let route = FeeRoute {
+            source: self.vault.to_account_info(),
+            source_signer_seeds: vault_signer_seeds,
+            lp_destination: self.trading_pool_vault.to_account_info(),
+            treasury_destination: self.protocol_treasury.to_account_info(),
+            insurance_vault: self.insurance_vault.to_account_info(),
+            referrer,
+            system_program: self.system_program.to_account_info(),
+        };
+
+        let split = distribute_fee(
+            &route,
+            FeeCategory::Withdrawal,
+            self.user.key(),
             fee_amount,
-            is_full_withdrawal,
-            timestamp: current_time,
-        });
+            &self.protocol_config.params.fee_split,
+            &mut self.fee_ledger,
+        )?;
+
+        // LP share raises the value of every remaining pool share
+        self.pool_liquidity.add_income(split.lp, Clock::get()?.unix_timestamp)?;
+        self.trading_pool.total_pool_amount = self.trading_pool.total_pool_amount
+            .checked_add(split.lp)
+            .ok_or(ErrorCode::MathOverflow)?;
 
         Ok(())
     }
@@ -170,17 +453,18 @@ impl<'info> Withdraw<'info> {
         let time_remaining = self.position_account.lock_end_time - current_time;
         let total_lock_duration = self.position_account.lock_end_time - self.position_account.lock_start_time;
         
-        // Calculate penalty based on remaining time (2-5% range)
+        // Calculate penalty based on remaining time
         // More time remaining = higher penalty
+        let config = self.protocol_config.params;
         let penalty_basis_points = if time_remaining >= total_lock_duration / 2 {
-            500 // 5% if more than half the lock period remains
+            config.early_withdrawal_penalty_half_bps
         } else if time_remaining >= total_lock_duration / 4 {
-            350 // 3.5% if more than quarter remains
+            config.early_withdrawal_penalty_quarter_bps
         } else {
-            200 // 2% if less than quarter remains
+            config.early_withdrawal_penalty_min_bps
         };
         
-        Ok(penalty_basis_points)
+        Ok(penalty_basis_points as u64)
     }
 }
 
@@ -190,31 +474,8 @@ pub struct WithdrawalEvent {
     pub user: Pubkey,
     pub amount: u64,
     pub fee_amount: u64,
+    pub shares_burned: u64,
+    pub principal_released: u64,
     pub is_full_withdrawal: bool,
     pub timestamp: i64,
 }
-
-// Additional state structs that would be needed
-#[account]
-pub struct PositionAccount {
-    pub owner: Pubkey,
-    pub amount: u64,
-    pub lock_start_time: i64,
-    pub lock_end_time: i64,
-    pub is_active: bool,
-    pub bump: u8,
-}
-
-#[account]
-pub struct PoolLiquidity {
-    pub total_liquidity: u64,
-    pub last_updated: i64,
-    pub bump: u8,
-}
-
-// Error codes specific to withdrawal
-impl ErrorCode {
-    pub const InvalidPositionOwnership: ErrorCode = ErrorCode::InvalidPositionOwnership;
-    pub const InsufficientBalance: ErrorCode = ErrorCode::InsufficientBalance;
-    pub const InsufficientVaultFunds: ErrorCode = ErrorCode::InsufficientVaultFunds;
-}
\ No newline at end of file
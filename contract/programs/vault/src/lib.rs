@@ -3,6 +3,7 @@
 pub mod constants;
 pub mod error;
 pub mod instructions;
+pub mod oracle;
 pub mod state;
 
 use anchor_lang::prelude::*;
@@ -43,11 +44,6 @@ pub mod vault {
         Ok(())
     }
 
-    pub fn close(ctx: Context<Close>) -> Result<()> {
-        ctx.accounts.close_vault()?;
-        Ok(())
-    }
-
     // === Trading Pool Instructions ===
     pub fn init_trading_pool(ctx: Context<InitTradingPool>, initial_deposit: Option<u64>) -> Result<()> {
         ctx.accounts.initialize(&ctx.bumps, initial_deposit)?;
@@ -59,6 +55,37 @@ pub mod vault {
         Ok(())
     }
 
+    // === Protocol Config Instructions ===
+    pub fn init_config(ctx: Context<InitConfig>) -> Result<()> {
+        ctx.accounts.init_config(&ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+        ctx.accounts.update_config(params)?;
+        Ok(())
+    }
+
+    pub fn create_market(ctx: Context<CreateMarket>, symbol: [u8; 8], params: MarketParams) -> Result<()> {
+        ctx.accounts.create_market(symbol, params, &ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn update_market(ctx: Context<UpdateMarket>, params: MarketParams) -> Result<()> {
+        ctx.accounts.update_market(params)?;
+        Ok(())
+    }
+
+    pub fn init_price_history(ctx: Context<InitPriceHistory>) -> Result<()> {
+        ctx.accounts.init_price_history(&ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn update_circuit_breaker(ctx: Context<UpdateCircuitBreaker>) -> Result<()> {
+        ctx.accounts.update_circuit_breaker()?;
+        Ok(())
+    }
+
     // === Position Management Instructions ===
     pub fn create_position(
         ctx: Context<CreatePosition>,
@@ -78,9 +105,30 @@ pub mod vault {
         )?;
         Ok(())
     }
+
+    pub fn create_leveraged_position(
+        ctx: Context<CreateLeveragedPosition>,
+        is_long: bool,
+        size: u64,
+        leverage: u8,
+        collateral_amount: u64,
+        order_id: u64,
+        expires_at: i64
+    ) -> Result<()> {
+        ctx.accounts.create_leveraged_position(
+            is_long,
+            size,
+            leverage,
+            collateral_amount,
+            order_id,
+            expires_at,
+            &ctx.bumps
+        )?;
+        Ok(())
+    }
     
-    pub fn check_position(ctx: Context<CheckPosition>) -> Result<()> {
-        ctx.accounts.check_position(&ctx.bumps)?;
+    pub fn check_position(ctx: Context<CheckPosition>, _order_id: u64) -> Result<()> {
+        ctx.accounts.check_position(&ctx.bumps, ctx.remaining_accounts)?;
         Ok(())
     }
     
@@ -89,12 +137,66 @@ pub mod vault {
         Ok(())
     }
 
+    pub fn liquidate_position(ctx: Context<LiquidatePosition>, _order_id: u64) -> Result<()> {
+        ctx.accounts.liquidate_position(&ctx.bumps, ctx.remaining_accounts)?;
+        Ok(())
+    }
+
+    pub fn add_position_margin(ctx: Context<AddPositionMargin>, _order_id: u64, amount: u64) -> Result<()> {
+        ctx.accounts.add_position_margin(amount, ctx.remaining_accounts)?;
+        Ok(())
+    }
+
+    pub fn release_position_margin(ctx: Context<ReleasePositionMargin>, _order_id: u64, slot: u8) -> Result<()> {
+        ctx.accounts.release_position_margin(slot)?;
+        Ok(())
+    }
+
+    pub fn init_payout_curve(
+        ctx: Context<InitPayoutCurve>,
+        position_type: PositionType,
+        kind: CurveKind,
+        max_payout_percentage: u8,
+        min_payout_percentage: u8,
+        step_count: u8,
+        half_life_secs: i64
+    ) -> Result<()> {
+        ctx.accounts.init_payout_curve(
+            position_type,
+            kind,
+            max_payout_percentage,
+            min_payout_percentage,
+            step_count,
+            half_life_secs,
+            &ctx.bumps
+        )?;
+        Ok(())
+    }
+
+    pub fn update_payout_curve(
+        ctx: Context<UpdatePayoutCurve>,
+        kind: CurveKind,
+        max_payout_percentage: u8,
+        min_payout_percentage: u8,
+        step_count: u8,
+        half_life_secs: i64
+    ) -> Result<()> {
+        ctx.accounts.update_payout_curve(
+            kind,
+            max_payout_percentage,
+            min_payout_percentage,
+            step_count,
+            half_life_secs,
+        )?;
+        Ok(())
+    }
+
     // === Liquidity Management Instructions ===
     pub fn add_liquidity(
         ctx: Context<Deposit>, 
         amount: u64, 
         pool_id: u64,
-        lock_period_days: Option<u32>
+        _lock_period_days: Option<u32>
     ) -> Result<()> {
         ctx.accounts.deposit(amount, pool_id, &ctx.bumps)?;
         Ok(())
@@ -110,33 +212,67 @@ pub mod vault {
     }
 
     // === Staking & Rewards Instructions ===
-    pub fn stake_rewards(ctx: Context<StakeRewards>, amount: u64) -> Result<()> {
+    pub fn stake_rewards(ctx: Context<ManageStake>, amount: u64) -> Result<()> {
         ctx.accounts.stake(amount)?;
         Ok(())
     }
 
-    pub fn unstake_rewards(ctx: Context<StakeRewards>, amount: u64) -> Result<()> {
+    pub fn unstake_rewards(ctx: Context<ManageStake>, amount: u64) -> Result<()> {
         ctx.accounts.unstake(amount)?;
         Ok(())
     }
 
-    pub fn claim_rewards(ctx: Context<StakeRewards>) -> Result<()> {
+    pub fn claim_rewards(ctx: Context<ManageStake>) -> Result<()> {
         ctx.accounts.claim_rewards()?;
         Ok(())
     }
 
-    // === Pool Liquidity Management ===
-    pub fn update_pool_liquidity(ctx: Context<UpdatePoolLiquidity>) -> Result<()> {
-        ctx.accounts.update_liquidity_state()?;
+    pub fn init_reward_pool(
+        ctx: Context<InitRewardPool>,
+        emission_rate_per_second: u64,
+        emission_start_time: i64,
+        emission_end_time: i64
+    ) -> Result<()> {
+        ctx.accounts.init_reward_pool(
+            emission_rate_per_second,
+            emission_start_time,
+            emission_end_time,
+            &ctx.bumps
+        )?;
         Ok(())
     }
 
-    pub fn rebalance_pool(ctx: Context<RebalancePool>, target_ratio: u64) -> Result<()> {
-        ctx.accounts.rebalance(target_ratio)?;
+    pub fn fund_reward_pool(
+        ctx: Context<FundRewardPool>,
+        emission_amount: u64,
+        performance_amount: u64
+    ) -> Result<()> {
+        ctx.accounts.fund_reward_pool(emission_amount, performance_amount)?;
+        Ok(())
+    }
+
+    pub fn update_reward_schedule(
+        ctx: Context<UpdateRewardSchedule>,
+        emission_rate_per_second: u64,
+        emission_start_time: i64,
+        emission_end_time: i64,
+        base_reward_rate: u16
+    ) -> Result<()> {
+        ctx.accounts.update_reward_schedule(
+            emission_rate_per_second,
+            emission_start_time,
+            emission_end_time,
+            base_reward_rate
+        )?;
         Ok(())
     }
 
     // === Fee Management Instructions ===
+    pub fn init_fee_ledger(ctx: Context<InitFeeLedger>, fee_recipient: Pubkey) -> Result<()> {
+        ctx.accounts.init_fee_ledger(fee_recipient, &ctx.bumps)?;
+        Ok(())
+    }
+
     pub fn update_fee_structure(
         ctx: Context<UpdateFeeStructure>, 
         deposit_fee_bps: u64, 
@@ -146,8 +282,73 @@ pub mod vault {
         Ok(())
     }
 
+    pub fn set_fee_recipient(ctx: Context<UpdateFeeStructure>, fee_recipient: Pubkey) -> Result<()> {
+        ctx.accounts.set_fee_recipient(fee_recipient)?;
+        Ok(())
+    }
+
     pub fn collect_protocol_fees(ctx: Context<CollectFees>) -> Result<()> {
-        ctx.accounts.collect_fees()?;
+        ctx.accounts.collect_fees(&ctx.bumps)?;
+        Ok(())
+    }
+
+    // === Insurance Fund Instructions ===
+    pub fn init_insurance_fund(ctx: Context<InitInsuranceFund>) -> Result<()> {
+        ctx.accounts.init_insurance_fund(&ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn deposit_insurance_fund(ctx: Context<DepositInsuranceFund>, amount: u64) -> Result<()> {
+        ctx.accounts.deposit_insurance_fund(amount)?;
+        Ok(())
+    }
+
+    pub fn init_loss_ledger(ctx: Context<InitLossLedger>) -> Result<()> {
+        ctx.accounts.init_loss_ledger(&ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn claim_deferred_payout(ctx: Context<ClaimDeferredPayout>, _order_id: u64) -> Result<()> {
+        ctx.accounts.claim_deferred_payout()?;
+        Ok(())
+    }
+
+    // === Token Collateral Instructions ===
+    pub fn register_collateral_mint(
+        ctx: Context<RegisterCollateralMint>,
+        price_feed_id: [u8; 32],
+        haircut_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.register_collateral_mint(price_feed_id, haircut_bps, &ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn set_collateral_risk(
+        ctx: Context<SetCollateralRisk>,
+        price_feed_id: [u8; 32],
+        haircut_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.set_collateral_risk(price_feed_id, haircut_bps)?;
+        Ok(())
+    }
+
+    pub fn set_collateral_mint_enabled(ctx: Context<SetCollateralMintEnabled>, is_enabled: bool) -> Result<()> {
+        ctx.accounts.set_collateral_mint_enabled(is_enabled)?;
+        Ok(())
+    }
+
+    pub fn open_token_vault(ctx: Context<OpenTokenVault>) -> Result<()> {
+        ctx.accounts.open_token_vault()?;
+        Ok(())
+    }
+
+    pub fn fund_token_rewards(ctx: Context<FundTokenRewards>, amount: u64) -> Result<()> {
+        ctx.accounts.fund_token_rewards(amount)?;
+        Ok(())
+    }
+
+    pub fn collect_token_fees(ctx: Context<CollectTokenFees>) -> Result<()> {
+        ctx.accounts.collect_token_fees()?;
         Ok(())
     }
 
@@ -167,75 +368,75 @@ pub mod vault {
         Ok(())
     }
 
-    // === Emergency Instructions ===
-    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>) -> Result<()> {
-        ctx.accounts.emergency_withdraw()?;
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        ctx.accounts.accept_admin()?;
         Ok(())
     }
 
-    pub fn freeze_account(ctx: Context<AdminControl>, account_to_freeze: Pubkey) -> Result<()> {
-        ctx.accounts.freeze_account(account_to_freeze)?;
+    // === Compliance Instructions ===
+    pub fn init_sanctions_registry(
+        ctx: Context<InitSanctionsRegistry>,
+        compliance_authority: Pubkey
+    ) -> Result<()> {
+        ctx.accounts.init_sanctions_registry(compliance_authority, &ctx.bumps)?;
         Ok(())
     }
 
-    pub fn unfreeze_account(ctx: Context<AdminControl>, account_to_unfreeze: Pubkey) -> Result<()> {
-        ctx.accounts.unfreeze_account(account_to_unfreeze)?;
+    pub fn set_compliance_authority(
+        ctx: Context<SetComplianceAuthority>,
+        compliance_authority: Pubkey
+    ) -> Result<()> {
+        ctx.accounts.set_compliance_authority(compliance_authority)?;
         Ok(())
     }
 
-    // === View/Query Instructions (Read-only) ===
-    pub fn get_position_info(ctx: Context<GetPositionInfo>) -> Result<PositionInfo> {
-        ctx.accounts.get_position_info()
+    pub fn init_sanctions_page(ctx: Context<InitSanctionsPage>, bucket: u8) -> Result<()> {
+        ctx.accounts.init_sanctions_page(bucket, &ctx.bumps)?;
+        Ok(())
     }
 
-    pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
-        ctx.accounts.get_pool_stats()
+    pub fn add_sanctioned_address(ctx: Context<UpdateSanctionsList>, address: Pubkey) -> Result<()> {
+        ctx.accounts.add_sanctioned_address(address)?;
+        Ok(())
     }
 
-    pub fn get_user_rewards(ctx: Context<GetUserRewards>) -> Result<RewardsInfo> {
-        ctx.accounts.get_user_rewards()
+    pub fn remove_sanctioned_address(ctx: Context<UpdateSanctionsList>, address: Pubkey) -> Result<()> {
+        ctx.accounts.remove_sanctioned_address(address)?;
+        Ok(())
     }
 
-    // === Migration Instructions ===
-    pub fn migrate_position(
-        ctx: Context<MigratePosition>, 
-        old_position: Pubkey, 
-        new_pool_id: u64
-    ) -> Result<()> {
-        ctx.accounts.migrate_position(old_position, new_pool_id)?;
+    // === Emergency Instructions ===
+    pub fn set_emergency_mode(ctx: Context<AdminControl>, enabled: bool) -> Result<()> {
+        ctx.accounts.set_emergency_mode(enabled)?;
         Ok(())
     }
 
-    pub fn upgrade_account(ctx: Context<UpgradeAccount>) -> Result<()> {
-        ctx.accounts.upgrade_account(&ctx.bumps)?;
+    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>) -> Result<()> {
+        ctx.accounts.emergency_withdraw()?;
         Ok(())
     }
-}
 
-// === Return Types for View Functions ===
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct PositionInfo {
-    pub owner: Pubkey,
-    pub amount: u64,
-    pub shares: u64,
-    pub lock_end_time: i64,
-    pub is_active: bool,
-    pub pending_rewards: u64,
-}
+    pub fn emergency_close_position(ctx: Context<EmergencyClosePosition>, _order_id: u64) -> Result<()> {
+        ctx.accounts.emergency_close_position()?;
+        Ok(())
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct PoolStats {
-    pub total_liquidity: u64,
-    pub available_liquidity: u64,
-    pub total_shares: u64,
-    pub apy: u64, // Annual Percentage Yield in basis points
-    pub fee_rate: u64,
-}
+    pub fn init_freeze_record(ctx: Context<InitFreezeRecord>, wallet: Pubkey) -> Result<()> {
+        ctx.accounts.init_freeze_record(wallet, &ctx.bumps)?;
+        Ok(())
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct RewardsInfo {
-    pub total_earned: u64,
-    pub pending_rewards: u64,
-    pub staked_amount: u64,
-    pub last_claim_time: i64,
-}
\ No newline at end of file
+    pub fn freeze_account(
+        ctx: Context<FreezeAccount>,
+        account_to_freeze: Pubkey,
+        reason_code: u16
+    ) -> Result<()> {
+        ctx.accounts.freeze_account(account_to_freeze, reason_code)?;
+        Ok(())
+    }
+
+    pub fn unfreeze_account(ctx: Context<UnfreezeAccount>, account_to_unfreeze: Pubkey) -> Result<()> {
+        ctx.accounts.unfreeze_account(account_to_unfreeze)?;
+        Ok(())
+    }
+}
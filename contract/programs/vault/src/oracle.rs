@@ -0,0 +1,325 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use crate::state::{MarkPriceMode, Market, PriceHistory};
+use crate::error::ErrorCode;
+
+// Every price inside the program is fixed point with PRICE_DECIMALS decimals, whatever
+// exponent the Pyth feed publishes with. Bounds, entry and settlement prices all use it.
+pub const PRICE_DECIMALS: u8 = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct OraclePrice {
+    pub price: u64,        // PRICE_DECIMALS fixed point, always > 0
+    pub conf: u64,         // Pyth confidence interval, same precision as `price`
+    pub ema_price: u64,    // Pyth EMA price, 0 if the feed published a non-positive one
+    pub ema_conf: u64,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    // The price to mark positions at under the market's mode. EMA replaces the spot fields
+    // with the EMA ones; TWAP keeps the spot confidence around the history's average.
+    pub fn mark(&self, market: &Market, history: &PriceHistory, now: i64) -> Result<OraclePrice> {
+        match market.params.mark_price_mode {
+            MarkPriceMode::Spot => Ok(*self),
+            MarkPriceMode::Ema => {
+                require!(self.ema_price > 0, ErrorCode::NonPositivePrice);
+                Ok(OraclePrice {
+                    price: self.ema_price,
+                    conf: self.ema_conf,
+                    ..*self
+                })
+            },
+            MarkPriceMode::Twap => self.at_twap(market, history, now),
+        }
+    }
+
+    // This price with the average of `history` over the market's TWAP window as its centre
+    pub fn at_twap(&self, market: &Market, history: &PriceHistory, now: i64) -> Result<OraclePrice> {
+        let twap = history.twap(now, &market.params)?;
+        require!(twap > 0, ErrorCode::NonPositivePrice);
+        Ok(OraclePrice {
+            price: twap,
+            ..*self
+        })
+    }
+
+    // Rejects prices Pyth itself flags as uncertain
+    pub fn check_confidence(&self, max_confidence_bps: u16) -> Result<()> {
+        let confidence_bps = (self.conf as u128 * 10000) / self.price as u128;
+        require!(
+            confidence_bps <= max_confidence_bps as u128,
+            ErrorCode::PriceConfidenceTooWide
+        );
+        Ok(())
+    }
+
+    // Top of the confidence interval
+    pub fn ask(&self) -> u64 {
+        self.price.saturating_add(self.conf)
+    }
+
+    // Bottom of the confidence interval, never zero
+    pub fn bid(&self) -> u64 {
+        self.price.saturating_sub(self.conf).max(1)
+    }
+
+    // Price least favourable to the trader: longs open at the ask and close at the bid,
+    // shorts open at the bid and close at the ask
+    pub fn conservative(&self, is_long: bool, is_entry: bool) -> u64 {
+        if is_long == is_entry {
+            self.ask()
+        } else {
+            self.bid()
+        }
+    }
+}
+
+// Price message from a market's secondary source. It is signed with ed25519 by
+// `MarketParams::secondary_signer` and verified by an Ed25519Program instruction placed
+// right before the instruction that reads it; the message is the borsh encoding of this struct.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct SignedPriceMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+// Price for a market with the secondary source as backup: the Pyth price while it is
+// fresh, otherwise the secondary price. A signed price is optional while Pyth is fresh;
+// when one is supplied it must be within `max_deviation_bps` of the Pyth price. A stale
+// signed price counts as absent.
+pub fn get_market_price_with_fallback(
+    price_update: &PriceUpdateV2,
+    market: &Market,
+    instructions_sysvar: Option<&AccountInfo>,
+    clock: &Clock,
+    maximum_age: u64,
+) -> Result<OraclePrice> {
+    market.check_price_update(price_update)?;
+
+    let secondary = match instructions_sysvar {
+        Some(sysvar) if market.has_secondary_source() => {
+            get_signed_price(sysvar, market, clock, maximum_age)?
+        },
+        _ => None,
+    };
+
+    let primary_is_fresh = price_update.price_message.publish_time
+        .saturating_add(maximum_age as i64) >= clock.unix_timestamp;
+
+    // Fall back to the secondary price only once Pyth is stale
+    if !primary_is_fresh {
+        return secondary.ok_or(error!(ErrorCode::StalePriceFeed));
+    }
+
+    let primary = get_market_price(price_update, market, clock, maximum_age)?;
+
+    if let Some(secondary) = secondary {
+        check_deviation(&primary, &secondary, market.params.max_deviation_bps)?;
+    }
+
+    Ok(primary)
+}
+
+// Rejects a secondary price more than `max_deviation_bps` away from the primary price
+fn check_deviation(primary: &OraclePrice, secondary: &OraclePrice, max_deviation_bps: u16) -> Result<()> {
+    let deviation = primary.price.abs_diff(secondary.price) as u128;
+    require!(
+        deviation * 10000 <= primary.price as u128 * max_deviation_bps as u128,
+        ErrorCode::PriceDeviationTooLarge
+    );
+
+    Ok(())
+}
+
+// Reads the market's signed price from the Ed25519Program instruction preceding the
+// current one. The signature, key and message must all sit in that instruction's data.
+// Returns None when no Ed25519Program instruction precedes the current one or the signed
+// price is older than `maximum_age`.
+fn get_signed_price(
+    instructions_sysvar: &AccountInfo,
+    market: &Market,
+    clock: &Clock,
+    maximum_age: u64,
+) -> Result<Option<OraclePrice>> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    if current_index == 0 {
+        return Ok(None);
+    }
+
+    let ed25519_ix = load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    if ed25519_ix.program_id != ed25519_program::ID {
+        return Ok(None);
+    }
+
+    // Header: signature count, padding, then one 14-byte offsets record
+    let data = &ed25519_ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidSecondaryPrice);
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+    let public_key_offset = read_u16(6);
+    let message_offset = read_u16(10);
+    let message_size = read_u16(12);
+
+    // u16::MAX means "this instruction"; anything else could point at unsigned data
+    for instruction_index_at in [4, 8, 14] {
+        require!(read_u16(instruction_index_at) == u16::MAX as usize, ErrorCode::InvalidSecondaryPrice);
+    }
+
+    let public_key = data.get(public_key_offset..public_key_offset + 32)
+        .ok_or(ErrorCode::InvalidSecondaryPrice)?;
+    require!(
+        public_key == market.params.secondary_signer.as_ref(),
+        ErrorCode::InvalidSecondaryPrice
+    );
+
+    let message = data.get(message_offset..message_offset + message_size)
+        .ok_or(ErrorCode::InvalidSecondaryPrice)?;
+    let message = SignedPriceMessage::try_from_slice(message)
+        .map_err(|_| error!(ErrorCode::InvalidSecondaryPrice))?;
+
+    require!(message.feed_id == market.params.price_feed_id, ErrorCode::PriceFeedMismatch);
+    if message.publish_time.saturating_add(maximum_age as i64) < clock.unix_timestamp {
+        return Ok(None);
+    }
+    require!(message.price > 0, ErrorCode::NonPositivePrice);
+
+    let price = normalize(message.price as u64, message.exponent)?;
+    require!(price > 0, ErrorCode::NonPositivePrice);
+    let conf = normalize(message.conf, message.exponent)?;
+
+    // The secondary source has no EMA; its spot price stands in for it
+    Ok(Some(OraclePrice {
+        price,
+        conf,
+        ema_price: price,
+        ema_conf: conf,
+        publish_time: message.publish_time,
+    }))
+}
+
+// Fresh, fully verified price for a market, checked against the market's feed
+pub fn get_market_price(
+    price_update: &PriceUpdateV2,
+    market: &Market,
+    clock: &Clock,
+    maximum_age: u64,
+) -> Result<OraclePrice> {
+    market.check_price_update(price_update)?;
+    get_feed_price(price_update, &market.params.price_feed_id, clock, maximum_age)
+}
+
+// Fresh, fully verified price for any Pyth feed
+pub fn get_feed_price(
+    price_update: &PriceUpdateV2,
+    feed_id: &[u8; 32],
+    clock: &Clock,
+    maximum_age: u64,
+) -> Result<OraclePrice> {
+    require!(
+        price_update.verification_level == VerificationLevel::Full,
+        ErrorCode::UnverifiedPriceUpdate
+    );
+
+    let price = price_update.get_price_no_older_than(clock, maximum_age, feed_id)
+        .map_err(|_| error!(ErrorCode::StalePriceFeed))?;
+
+    // A negative or zero price would wrap into a huge u64
+    require!(price.price > 0, ErrorCode::NonPositivePrice);
+
+    let normalized = normalize(price.price as u64, price.exponent)?;
+    require!(normalized > 0, ErrorCode::NonPositivePrice);
+
+    // The EMA shares the spot price's exponent and freshness
+    let message = &price_update.price_message;
+    let ema_price = if message.ema_price > 0 {
+        normalize(message.ema_price as u64, message.exponent)?
+    } else {
+        0
+    };
+
+    Ok(OraclePrice {
+        price: normalized,
+        conf: normalize(price.conf, price.exponent)?,
+        ema_price,
+        ema_conf: normalize(message.ema_conf, message.exponent)?,
+        publish_time: price.publish_time,
+    })
+}
+
+// Converts `value * 10^exponent` to PRICE_DECIMALS fixed point
+pub fn normalize(value: u64, exponent: i32) -> Result<u64> {
+    let shift = PRICE_DECIMALS as i32 + exponent;
+    let factor = 10u128
+        .checked_pow(shift.unsigned_abs())
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let scaled = if shift >= 0 {
+        (value as u128).checked_mul(factor).ok_or(ErrorCode::MathOverflow)?
+    } else {
+        value as u128 / factor
+    };
+
+    u64::try_from(scaled).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_scales_any_exponent_to_price_decimals() {
+        // $65,000.12345678 published with an exponent of -8 is already normalized
+        assert_eq!(normalize(6_500_012_345_678, -8).unwrap(), 6_500_012_345_678);
+        // Fewer decimals are scaled up
+        assert_eq!(normalize(6_500_012, -2).unwrap(), 6_500_012_000_000);
+        assert_eq!(normalize(65_000, 0).unwrap(), 6_500_000_000_000);
+        // Extra decimals are truncated
+        assert_eq!(normalize(650_001_234_567_891, -10).unwrap(), 6_500_012_345_678);
+    }
+
+    #[test]
+    fn normalize_rejects_values_that_overflow() {
+        assert!(normalize(u64::MAX, -2).is_err());
+        assert!(normalize(1, 40).is_err());
+    }
+
+    fn price(price: u64, conf: u64) -> OraclePrice {
+        OraclePrice { price, conf, ema_price: price, ema_conf: conf, publish_time: 0 }
+    }
+
+    #[test]
+    fn conservative_prices_the_worse_side_of_the_interval() {
+        let quote = price(10_000, 50);
+
+        // Longs open at the ask and close at the bid
+        assert_eq!(quote.conservative(true, true), 10_050);
+        assert_eq!(quote.conservative(true, false), 9_950);
+        // Shorts open at the bid and close at the ask
+        assert_eq!(quote.conservative(false, true), 9_950);
+        assert_eq!(quote.conservative(false, false), 10_050);
+    }
+
+    #[test]
+    fn bid_never_reaches_zero() {
+        assert_eq!(price(100, 500).bid(), 1);
+    }
+
+    #[test]
+    fn deviation_is_bounded_in_basis_points_of_the_primary_price() {
+        assert!(check_deviation(&price(10_000, 0), &price(10_100, 0), 100).is_ok());
+        assert!(check_deviation(&price(10_000, 0), &price(9_900, 0), 100).is_ok());
+        assert!(check_deviation(&price(10_000, 0), &price(10_101, 0), 100).is_err());
+    }
+
+    #[test]
+    fn check_confidence_rejects_wide_intervals() {
+        assert!(price(10_000, 100).check_confidence(100).is_ok());
+        assert!(price(10_000, 101).check_confidence(100).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use anchor_lang::prelude::*;
+use crate::oracle::PRICE_DECIMALS;
+use crate::error::ErrorCode;
+
+// SPL Token / Token-2022 mint accepted as collateral next to native SOL, PDA
+// ["collateral_mint", mint]. Tokens are held in program-owned token accounts whose
+// authority is the PDA ["collateral_authority", mint]:
+//   ["trading_pool_token_vault", mint]         - pool side, replaces trading_pool_vault
+//   ["reward_pool_token_vault", mint]          - position rewards, replaces reward_pool_vault
+//   ["vault_token", vault_state, mint]         - per user, replaces vault
+// Amounts on this account are in the mint's own units; SOL figures stay on
+// TradingPool / PoolLiquidity.
+//
+// Each entry also prices its mint for margin: a Pyth feed and a haircut in basis points.
+// Native SOL margin is priced by the entry registered for the wrapped SOL mint.
+#[account]
+#[derive(InitSpace)]
+pub struct CollateralMint {
+    pub mint: Pubkey,
+    pub token_program: Pubkey, // SPL Token or Token-2022
+    pub decimals: u8,
+    pub is_enabled: bool,      // Disabled mints only allow withdrawals and settlement
+    pub price_feed_id: [u8; 32], // Pyth feed quoting the mint in USD
+    pub haircut_bps: u16,        // Share of the USD value not counted as margin
+    pub pool_token_vault: Pubkey,
+    pub reward_token_vault: Pubkey,
+    // LP accounting for this mint
+    pub total_liquidity: u64,
+    pub total_shares: u64,
+    // Trading pool accounting for this mint
+    pub total_pool_amount: u64,
+    pub total_active_amount: u64,
+    pub total_fees_collected: u64,
+    pub protocol_fees: u64,        // Non-LP fee shares held in the pool token vault until swept
+    pub deferred_payouts: u64,     // Settlements owed but unpaid, see claim_deferred_payout
+    pub total_rewards_funded: u64,
+    pub total_rewards_distributed: u64,
+    pub authority_bump: u8,
+    pub bump: u8,
+}
+
+impl CollateralMint {
+    // Protocol limits (order sizes, fee tiers, minimum position size) are configured in lamports
+    pub const NATIVE_DECIMALS: u8 = 9;
+    // Margin values are USD with 6 decimals
+    pub const USD_DECIMALS: u8 = 6;
+
+    // Converts a mint-denominated amount to 9-decimal units so it can be checked
+    // against limits configured in lamports
+    pub fn to_native_units(&self, amount: u64) -> Result<u64> {
+        scale_decimals(amount, self.decimals, Self::NATIVE_DECIMALS)
+    }
+
+    // Pool token balance the protocol can pay out without touching swept-fee reserves
+    pub fn payable_balance(&self, pool_token_balance: u64) -> u64 {
+        pool_token_balance.saturating_sub(self.protocol_fees)
+    }
+
+    // USD value of `amount` at an oracle price (PRICE_DECIMALS fixed point), scaled by 10^USD_DECIMALS
+    pub fn usd_value(&self, amount: u64, price: u64) -> Result<u64> {
+        let value = (amount as u128)
+            .checked_mul(price as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // amount / 10^decimals * price / 10^PRICE_DECIMALS * 10^USD_DECIMALS
+        let shift = Self::USD_DECIMALS as i32 - PRICE_DECIMALS as i32 - self.decimals as i32;
+        let factor = 10u128
+            .checked_pow(shift.unsigned_abs())
+            .ok_or(ErrorCode::MathOverflow)?;
+        let value = if shift >= 0 {
+            value.checked_mul(factor).ok_or(ErrorCode::MathOverflow)?
+        } else {
+            value / factor
+        };
+
+        u64::try_from(value).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    // USD value after the haircut; this is what the amount counts for as margin
+    pub fn margin_value(&self, usd_value: u64) -> u64 {
+        ((usd_value as u128 * (10000 - self.haircut_bps.min(10000)) as u128) / 10000) as u64
+    }
+
+    // Takes `shares` worth `value` out of the mint's LP liquidity
+    pub fn burn_shares(&mut self, shares: u64, value: u64) -> Result<()> {
+        self.total_liquidity = self.total_liquidity
+            .checked_sub(value)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_shares = self.total_shares
+            .checked_sub(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    // Registry key for a position's collateral: native SOL is priced as wrapped SOL
+    pub fn registry_mint(collateral_mint: Pubkey) -> Pubkey {
+        if collateral_mint == Pubkey::default() {
+            anchor_spl::token::spl_token::native_mint::ID
+        } else {
+            collateral_mint
+        }
+    }
+}
+
+pub fn scale_decimals(amount: u64, from_decimals: u8, to_decimals: u8) -> Result<u64> {
+    let scaled = if to_decimals >= from_decimals {
+        let factor = 10u128
+            .checked_pow((to_decimals - from_decimals) as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+        (amount as u128).checked_mul(factor).ok_or(ErrorCode::MathOverflow)?
+    } else {
+        let factor = 10u128
+            .checked_pow((from_decimals - to_decimals) as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+        amount as u128 / factor
+    };
+
+    u64::try_from(scaled).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collateral_mint(decimals: u8, haircut_bps: u16) -> CollateralMint {
+        CollateralMint {
+            mint: Pubkey::new_unique(),
+            token_program: anchor_spl::token::ID,
+            decimals,
+            is_enabled: true,
+            price_feed_id: [0; 32],
+            haircut_bps,
+            pool_token_vault: Pubkey::default(),
+            reward_token_vault: Pubkey::default(),
+            total_liquidity: 0,
+            total_shares: 0,
+            total_pool_amount: 0,
+            total_active_amount: 0,
+            total_fees_collected: 0,
+            protocol_fees: 0,
+            deferred_payouts: 0,
+            total_rewards_funded: 0,
+            total_rewards_distributed: 0,
+            authority_bump: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn usd_value_scales_by_mint_and_price_decimals() {
+        // 1 USDC at $1
+        let usdc = collateral_mint(6, 0);
+        assert_eq!(usdc.usd_value(1_000_000, 100_000_000).unwrap(), 1_000_000);
+
+        // 2 SOL at $150
+        let sol = collateral_mint(9, 0);
+        assert_eq!(sol.usd_value(2_000_000_000, 15_000_000_000).unwrap(), 300_000_000);
+    }
+
+    #[test]
+    fn usd_value_rejects_values_that_do_not_fit() {
+        let mint = collateral_mint(0, 0);
+        assert!(mint.usd_value(u64::MAX, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn margin_value_applies_the_haircut() {
+        assert_eq!(collateral_mint(9, 2_000).margin_value(1_000), 800);
+        assert_eq!(collateral_mint(9, 0).margin_value(1_000), 1_000);
+        // Haircuts above 100% count for nothing rather than underflowing
+        assert_eq!(collateral_mint(9, 12_000).margin_value(1_000), 0);
+    }
+
+    #[test]
+    fn scale_decimals_converts_in_both_directions() {
+        assert_eq!(scale_decimals(1_000_000, 6, 9).unwrap(), 1_000_000_000);
+        assert_eq!(scale_decimals(1_999, 9, 6).unwrap(), 1);
+        assert!(scale_decimals(u64::MAX, 0, 9).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+use crate::state::FeeSplitAmounts;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FeeCategory {
+    Deposit,
+    Withdrawal,
+    Trading,
+    Closing,
+    Liquidation,
+}
+
+// Protocol fee accounting, PDA ["fee_ledger"]. Per-category totals are lifetime gross
+// figures, the split totals show where they went. The `uncollected_*` balances are the
+// treasury share that `collect_protocol_fees` sweeps: deposit and withdrawal fees sit in
+// the protocol treasury PDA, trading, closing and liquidation fees in the trading pool vault.
+#[account]
+#[derive(InitSpace)]
+pub struct FeeLedger {
+    pub admin: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub deposit_fees: u64,
+    pub withdrawal_fees: u64,
+    pub trading_fees: u64,
+    pub closing_fees: u64,
+    pub liquidation_fees: u64,
+    pub lp_fees: u64,
+    pub treasury_fees: u64,
+    pub insurance_fees: u64,
+    pub referrer_fees: u64,
+    pub uncollected_treasury_fees: u64,
+    pub uncollected_pool_fees: u64,
+    pub total_collected: u64,
+    pub last_collected_at: i64,
+    pub bump: u8,
+}
+
+impl FeeLedger {
+    pub fn record(&mut self, category: FeeCategory, split: &FeeSplitAmounts) -> Result<()> {
+        let amount = split.lp + split.treasury + split.insurance + split.referrer;
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let total = match category {
+            FeeCategory::Deposit => &mut self.deposit_fees,
+            FeeCategory::Withdrawal => &mut self.withdrawal_fees,
+            FeeCategory::Trading => &mut self.trading_fees,
+            FeeCategory::Closing => &mut self.closing_fees,
+            FeeCategory::Liquidation => &mut self.liquidation_fees,
+        };
+        *total = total.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+        self.lp_fees = self.lp_fees.checked_add(split.lp).ok_or(ErrorCode::MathOverflow)?;
+        self.treasury_fees = self.treasury_fees.checked_add(split.treasury).ok_or(ErrorCode::MathOverflow)?;
+        self.insurance_fees = self.insurance_fees.checked_add(split.insurance).ok_or(ErrorCode::MathOverflow)?;
+        self.referrer_fees = self.referrer_fees.checked_add(split.referrer).ok_or(ErrorCode::MathOverflow)?;
+
+        let uncollected = match category {
+            FeeCategory::Deposit | FeeCategory::Withdrawal => &mut self.uncollected_treasury_fees,
+            _ => &mut self.uncollected_pool_fees,
+        };
+        *uncollected = uncollected.checked_add(split.treasury).ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+}
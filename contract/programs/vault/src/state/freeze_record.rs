@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+// Per-wallet compliance freeze, PDA ["freeze_record", wallet]. The record is kept
+// after an unfreeze so the history stays on-chain.
+#[account]
+#[derive(InitSpace)]
+pub struct FreezeRecord {
+    pub wallet: Pubkey,
+    pub is_frozen: bool,
+    pub reason_code: u16,
+    pub frozen_by: Pubkey,
+    pub frozen_at: i64,
+    pub unfrozen_at: i64,
+    pub bump: u8,
+}
+
+impl FreezeRecord {
+    // User instructions pass the PDA unchecked because most wallets never have a
+    // record; a missing account means "not frozen".
+    pub fn ensure_not_frozen(record_info: &AccountInfo) -> Result<()> {
+        if record_info.owner != &crate::ID || record_info.data_is_empty() {
+            return Ok(());
+        }
+
+        let data = record_info.try_borrow_data()?;
+        let record = FreezeRecord::try_deserialize(&mut &data[..])?;
+        require!(!record.is_frozen, ErrorCode::AccountFrozen);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+// Backstop for trading pool payouts, PDA ["insurance_fund"]. Lamports are held in the
+// system-owned vault PDA ["insurance_vault"], fed by the insurance share of every fee
+// (see FeeLedger.insurance_fees) and by admin deposits.
+#[account]
+#[derive(InitSpace)]
+pub struct InsuranceFund {
+    pub admin: Pubkey,
+    pub total_admin_deposits: u64,
+    pub total_covered: u64,             // Pool shortfalls paid from the fund
+    pub total_uncovered_bad_debt: u64,  // Shortfalls the fund could not cover
+    pub bad_debt_events: u64,
+    pub last_covered_at: i64,
+    pub vault_bump: u8,
+    pub bump: u8,
+}
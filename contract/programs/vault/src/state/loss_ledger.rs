@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+// Record of bad debt written down against LP liquidity, PDA ["loss_ledger"]. A payout
+// neither the trading pool nor the insurance fund can cover is socialized: it reduces
+// PoolLiquidity.total_liquidity (a pro-rata haircut on every LP share) and the unpaid
+// amount stays owed to the trader as `PositionState.deferred_payout`.
+#[account]
+#[derive(InitSpace)]
+pub struct LossLedger {
+    pub total_socialized: u64,
+    pub socialization_events: u64,
+    pub cumulative_haircut_per_share: u128, // Lamports per share, scaled by HAIRCUT_PRECISION
+    pub outstanding_deferred_payouts: u64,
+    pub total_deferred_paid: u64,
+    pub last_socialized_at: i64,
+    pub bump: u8,
+}
+
+impl LossLedger {
+    pub const HAIRCUT_PRECISION: u128 = 1_000_000_000_000;
+}
@@ -0,0 +1,148 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+use crate::constants::*;
+use crate::state::{ConfigParams, PRICE_HISTORY_CAPACITY};
+use crate::error::ErrorCode;
+
+// Price positions are marked at for health scoring and liquidation eligibility.
+// Entry and exit always use the spot price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum MarkPriceMode {
+    Spot,
+    Ema,  // Pyth EMA price, so a single wick does not trigger liquidation
+    Twap, // TWAP of the market's PriceHistory over `twap_window_secs`
+}
+
+// Per-underlying limits and fee overrides, changed by `admin` through `update_market`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct MarketParams {
+    pub price_feed_id: [u8; 32], // Pyth feed every price lookup for this market must use
+    pub is_active: bool,         // Inactive markets take no new positions; open ones still settle
+    pub max_leverage: u8,
+    pub min_position_size: u64,  // In lamports, like ConfigParams::min_position_size
+    pub max_position_size: u64,  // In lamports; 0 means no cap
+    // Overrides of the protocol-wide fees; None falls back to ProtocolConfig
+    pub trading_fee_bps: Option<u16>,
+    pub closing_fee_bps: Option<u16>,
+    pub mark_price_mode: MarkPriceMode,
+    // Backup price source: ed25519 key signing `oracle::SignedPriceMessage`s.
+    // Pubkey::default() disables it.
+    pub secondary_signer: Pubkey,
+    pub max_deviation_bps: u16, // Largest accepted gap between fresh primary and secondary prices
+    // TWAP over the market's PriceHistory; used by MarkPriceMode::Twap and `settle_at_twap`
+    pub twap_window_secs: u32,
+    // A TWAP needs this many observations inside its window, together covering at least
+    // `twap_min_coverage_bps` of it
+    pub twap_min_observations: u16,
+    pub twap_min_coverage_bps: u16,
+    pub settle_at_twap: bool, // Close leveraged positions at the TWAP instead of the spot price
+    // Circuit breaker: pause new positions when a price moves more than `breaker_threshold_bps`
+    // from any stored price of the last `breaker_window_secs`. 0 disables it.
+    pub breaker_threshold_bps: u16,
+    pub breaker_window_secs: u32,
+}
+
+// Tradeable underlying (BTC, ETH, SOL...), PDA ["market", symbol]. Positions record
+// their market and are always priced against its feed.
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub symbol: [u8; 8], // Ticker, zero padded
+    pub params: MarketParams,
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+impl MarketParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_leverage >= 1 && self.max_leverage <= MAX_LEVERAGE,
+            ErrorCode::InvalidConfig
+        );
+        require!(self.min_position_size > 0, ErrorCode::InvalidConfig);
+        require!(
+            self.max_position_size == 0 || self.max_position_size >= self.min_position_size,
+            ErrorCode::InvalidConfig
+        );
+        if let Some(fee_bps) = self.trading_fee_bps {
+            require!(fee_bps <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+        }
+        if let Some(fee_bps) = self.closing_fee_bps {
+            require!(fee_bps <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+        }
+        if self.mark_price_mode == MarkPriceMode::Twap || self.settle_at_twap {
+            require!(
+                self.twap_window_secs > 0 && self.twap_window_secs <= MAX_CONFIG_TWAP_WINDOW,
+                ErrorCode::InvalidConfig
+            );
+            require!(
+                self.twap_min_observations >= 1
+                    && self.twap_min_observations as usize <= PRICE_HISTORY_CAPACITY,
+                ErrorCode::InvalidConfig
+            );
+            require!(
+                self.twap_min_coverage_bps > 0 && self.twap_min_coverage_bps <= 10000,
+                ErrorCode::InvalidConfig
+            );
+        }
+        if self.breaker_threshold_bps > 0 {
+            require!(self.breaker_threshold_bps <= 10000, ErrorCode::InvalidConfig);
+            require!(
+                self.breaker_window_secs > 0 && self.breaker_window_secs <= MAX_CONFIG_BREAKER_WINDOW,
+                ErrorCode::InvalidConfig
+            );
+        }
+        if self.secondary_signer != Pubkey::default() {
+            require!(
+                self.max_deviation_bps > 0 && self.max_deviation_bps <= 10000,
+                ErrorCode::InvalidConfig
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Market {
+    // Rejects a price update published for another feed
+    pub fn check_price_update(&self, price_update: &PriceUpdateV2) -> Result<()> {
+        require!(
+            price_update.price_message.feed_id == self.params.price_feed_id,
+            ErrorCode::PriceFeedMismatch
+        );
+        Ok(())
+    }
+
+    pub fn has_circuit_breaker(&self) -> bool {
+        self.params.breaker_threshold_bps > 0
+    }
+
+    pub fn has_secondary_source(&self) -> bool {
+        self.params.secondary_signer != Pubkey::default()
+    }
+
+    // Position size in lamports against both the protocol and the market limits
+    pub fn check_position_size(&self, config: &ConfigParams, native_size: u64) -> Result<()> {
+        require!(
+            native_size >= config.min_position_size.max(self.params.min_position_size),
+            ErrorCode::PositionTooSmall
+        );
+        require!(
+            self.params.max_position_size == 0 || native_size <= self.params.max_position_size,
+            ErrorCode::PositionTooLarge
+        );
+        Ok(())
+    }
+
+    pub fn max_leverage(&self, config: &ConfigParams) -> u8 {
+        config.max_leverage.min(self.params.max_leverage)
+    }
+
+    pub fn trading_fee_bps(&self, config: &ConfigParams) -> u16 {
+        self.params.trading_fee_bps.unwrap_or(config.trading_fee_bps)
+    }
+
+    pub fn closing_fee_bps(&self, config: &ConfigParams) -> u16 {
+        self.params.closing_fee_bps.unwrap_or(config.closing_fee_bps)
+    }
+}
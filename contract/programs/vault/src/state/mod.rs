@@ -1,11 +1,55 @@
 pub mod vault_state;
 pub use vault_state::*;
 
+pub mod position_account;
+pub use position_account::*;
+
 pub mod position_state;
 pub use position_state::*;
 
+pub mod position_vault;
+pub use position_vault::*;
+
 pub mod settlement;
 pub use settlement::*;
 
 pub mod trading_pool;
-pub use trading_pool::*;
\ No newline at end of file
+pub use trading_pool::*;
+
+pub mod pool_liquidity;
+pub use pool_liquidity::*;
+
+pub mod payout_curve;
+pub use payout_curve::*;
+
+pub mod protocol_config;
+pub use protocol_config::*;
+
+pub mod freeze_record;
+pub use freeze_record::*;
+
+pub mod sanctions;
+pub use sanctions::*;
+pub mod stake_rewards;
+pub use stake_rewards::*;
+
+pub mod fee_ledger;
+pub use fee_ledger::*;
+
+pub mod referral;
+pub use referral::*;
+
+pub mod insurance_fund;
+pub use insurance_fund::*;
+
+pub mod loss_ledger;
+pub use loss_ledger::*;
+
+pub mod collateral_mint;
+pub use collateral_mint::*;
+
+pub mod market;
+pub use market::*;
+
+pub mod price_history;
+pub use price_history::*;
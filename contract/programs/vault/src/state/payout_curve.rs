@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+use crate::state::PositionType;
+use crate::error::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum CurveKind {
+    Linear,
+    Stepped,
+    ExponentialDecay,
+}
+
+// Per-product payout schedule for range positions.
+//
+// StayIn:   clean expiry pays `max_payout_percentage`; a broken band pays up to
+//           `min_payout_percentage`, growing with the time held before the break.
+// Breakout: a breakout pays between `max_payout_percentage` (immediate) and
+//           `min_payout_percentage` (at expiry); no breakout pays 0.
+#[account]
+#[derive(InitSpace)]
+pub struct PayoutCurve {
+    pub authority: Pubkey,
+    pub position_type: PositionType,
+    pub kind: CurveKind,
+    pub max_payout_percentage: u8,
+    pub min_payout_percentage: u8,
+    pub step_count: u8,       // Stepped only
+    pub half_life_secs: i64,  // ExponentialDecay only
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+impl PayoutCurve {
+    pub const BPS: u64 = 10_000;
+    pub const MAX_STEPS: u8 = 100;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_payout_percentage > 0 && self.min_payout_percentage <= self.max_payout_percentage,
+            ErrorCode::InvalidPayoutCurve
+        );
+
+        match self.kind {
+            CurveKind::Linear => {}
+            CurveKind::Stepped => require!(
+                self.step_count > 0 && self.step_count <= Self::MAX_STEPS,
+                ErrorCode::InvalidPayoutCurve
+            ),
+            CurveKind::ExponentialDecay => require!(
+                self.half_life_secs > 0,
+                ErrorCode::InvalidPayoutCurve
+            ),
+        }
+
+        Ok(())
+    }
+
+    // Decaying weight in basis points: 10_000 at `elapsed == 0`, falling towards 0
+    // as `elapsed` approaches `duration`.
+    pub fn weight_bps(&self, elapsed: i64, duration: i64) -> Result<u64> {
+        require!(duration > 0, ErrorCode::DivisionByZero);
+
+        let elapsed = elapsed.clamp(0, duration) as u64;
+        let duration = duration as u64;
+
+        let weight = match self.kind {
+            CurveKind::Linear => {
+                Self::BPS - elapsed * Self::BPS / duration
+            }
+            CurveKind::Stepped => {
+                let steps = self.step_count as u64;
+                let bucket = (elapsed * steps / duration).min(steps - 1);
+                (steps - bucket) * Self::BPS / steps
+            }
+            CurveKind::ExponentialDecay => {
+                let half_life = self.half_life_secs as u64;
+                let halvings = elapsed / half_life;
+                if halvings >= 14 {
+                    0
+                } else {
+                    // Halve per full half-life, then interpolate linearly inside the last one
+                    let base = Self::BPS >> halvings;
+                    let remainder = elapsed % half_life;
+                    base - (base / 2) * remainder / half_life
+                }
+            }
+        };
+
+        Ok(weight)
+    }
+
+    pub fn payout_percentage(&self, is_win: bool, elapsed: i64, duration: i64) -> Result<u8> {
+        let max = self.max_payout_percentage as u64;
+        let min = self.min_payout_percentage as u64;
+        let weight = self.weight_bps(elapsed, duration)?;
+
+        let payout = match (self.position_type, is_win) {
+            (PositionType::StayIn, true) => max,
+            // Broken band: partial payout grows with time held
+            (PositionType::StayIn, false) => min * (Self::BPS - weight) / Self::BPS,
+            // Breakout: payout shrinks the longer the breakout took
+            (PositionType::Breakout, true) => min + (max - min) * weight / Self::BPS,
+            (PositionType::Breakout, false) => 0,
+            (PositionType::Leveraged, _) => return err!(ErrorCode::InvalidPositionType),
+        };
+
+        Ok(payout as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(position_type: PositionType, kind: CurveKind) -> PayoutCurve {
+        PayoutCurve {
+            authority: Pubkey::default(),
+            position_type,
+            kind,
+            max_payout_percentage: 90,
+            min_payout_percentage: 10,
+            step_count: 4,
+            half_life_secs: 100,
+            last_updated: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn linear_weight_falls_to_zero_at_expiry() {
+        let curve = curve(PositionType::Breakout, CurveKind::Linear);
+
+        assert_eq!(curve.weight_bps(0, 1000).unwrap(), 10_000);
+        assert_eq!(curve.weight_bps(250, 1000).unwrap(), 7_500);
+        assert_eq!(curve.weight_bps(1000, 1000).unwrap(), 0);
+        // Elapsed time is clamped to [0, duration]
+        assert_eq!(curve.weight_bps(-5, 1000).unwrap(), 10_000);
+        assert_eq!(curve.weight_bps(2000, 1000).unwrap(), 0);
+    }
+
+    #[test]
+    fn stepped_weight_drops_once_per_bucket() {
+        let curve = curve(PositionType::Breakout, CurveKind::Stepped);
+
+        assert_eq!(curve.weight_bps(0, 1000).unwrap(), 10_000);
+        assert_eq!(curve.weight_bps(249, 1000).unwrap(), 10_000);
+        assert_eq!(curve.weight_bps(250, 1000).unwrap(), 7_500);
+        assert_eq!(curve.weight_bps(999, 1000).unwrap(), 2_500);
+        // The last bucket holds through expiry
+        assert_eq!(curve.weight_bps(1000, 1000).unwrap(), 2_500);
+    }
+
+    #[test]
+    fn exponential_weight_halves_every_half_life() {
+        let curve = curve(PositionType::Breakout, CurveKind::ExponentialDecay);
+
+        assert_eq!(curve.weight_bps(0, 10_000).unwrap(), 10_000);
+        assert_eq!(curve.weight_bps(100, 10_000).unwrap(), 5_000);
+        assert_eq!(curve.weight_bps(150, 10_000).unwrap(), 3_750);
+        assert_eq!(curve.weight_bps(200, 10_000).unwrap(), 2_500);
+        assert_eq!(curve.weight_bps(1_400, 10_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn weight_rejects_empty_duration() {
+        let curve = curve(PositionType::Breakout, CurveKind::Linear);
+
+        assert!(curve.weight_bps(0, 0).is_err());
+    }
+
+    #[test]
+    fn breakout_payout_shrinks_with_time_to_breakout() {
+        let curve = curve(PositionType::Breakout, CurveKind::Linear);
+
+        assert_eq!(curve.payout_percentage(true, 0, 1000).unwrap(), 90);
+        assert_eq!(curve.payout_percentage(true, 500, 1000).unwrap(), 50);
+        assert_eq!(curve.payout_percentage(true, 1000, 1000).unwrap(), 10);
+        assert_eq!(curve.payout_percentage(false, 500, 1000).unwrap(), 0);
+    }
+
+    #[test]
+    fn stay_in_payout_grows_with_time_held_before_a_break() {
+        let curve = curve(PositionType::StayIn, CurveKind::Linear);
+
+        assert_eq!(curve.payout_percentage(true, 1000, 1000).unwrap(), 90);
+        assert_eq!(curve.payout_percentage(false, 0, 1000).unwrap(), 0);
+        assert_eq!(curve.payout_percentage(false, 500, 1000).unwrap(), 5);
+        assert_eq!(curve.payout_percentage(false, 1000, 1000).unwrap(), 10);
+    }
+
+    #[test]
+    fn leveraged_positions_have_no_curve() {
+        let curve = curve(PositionType::Leveraged, CurveKind::Linear);
+
+        assert!(curve.payout_percentage(true, 0, 1000).is_err());
+    }
+}
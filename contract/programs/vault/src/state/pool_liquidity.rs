@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+// SOL LP liquidity and the pool shares issued against it, PDA ["pool_liquidity"].
+// Token collateral keeps the same share accounting on CollateralMint. Fee income raises
+// `total_liquidity` without minting shares and socialized bad debt lowers it, so every
+// share carries its pro-rata part of both.
+#[account]
+#[derive(InitSpace)]
+pub struct PoolLiquidity {
+    pub total_liquidity: u64,
+    pub available_liquidity: u64,
+    pub locked_liquidity: u64,
+    pub total_shares: u64,
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+impl PoolLiquidity {
+    // Shares a deposit of `value` buys at the current share price, 1:1 for the first deposit
+    pub fn shares_for_deposit(&self, value: u64) -> Result<u64> {
+        if self.total_liquidity == 0 || self.total_shares == 0 {
+            return Ok(value);
+        }
+
+        let shares = (value as u128)
+            .checked_mul(self.total_shares as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / self.total_liquidity as u128;
+
+        u64::try_from(shares).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    // Adds `value` of liquidity and the `shares` issued for it
+    pub fn mint_shares(&mut self, shares: u64, value: u64, current_time: i64) -> Result<()> {
+        self.total_liquidity = self.total_liquidity
+            .checked_add(value)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.available_liquidity = self.available_liquidity
+            .checked_add(value)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_shares = self.total_shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.last_updated = current_time;
+
+        Ok(())
+    }
+
+    // Takes `shares` worth `value` out of the pool
+    pub fn burn_shares(&mut self, shares: u64, value: u64, current_time: i64) -> Result<()> {
+        self.total_liquidity = self.total_liquidity
+            .checked_sub(value)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.available_liquidity = self.available_liquidity.saturating_sub(value);
+        self.total_shares = self.total_shares
+            .checked_sub(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.last_updated = current_time;
+
+        Ok(())
+    }
+
+    // Adds income (the LP share of fees) without minting shares, raising every share's value
+    pub fn add_income(&mut self, amount: u64, current_time: i64) -> Result<()> {
+        self.total_liquidity = self.total_liquidity
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.available_liquidity = self.available_liquidity
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.last_updated = current_time;
+
+        Ok(())
+    }
+
+    // Writes `amount` of loss down against the liquidity behind the shares. Returns what
+    // was actually written down, which is less than `amount` once the pool is empty.
+    pub fn write_down(&mut self, amount: u64, current_time: i64) -> u64 {
+        let written_down = amount.min(self.total_liquidity);
+
+        self.total_liquidity -= written_down;
+        self.available_liquidity = self.available_liquidity.saturating_sub(written_down);
+        self.last_updated = current_time;
+
+        written_down
+    }
+}
+
+// What `shares` are worth today
+pub fn share_value(shares: u64, total_liquidity: u64, total_shares: u64) -> Result<u64> {
+    if total_shares == 0 {
+        return Ok(0);
+    }
+
+    let value = (shares as u128)
+        .checked_mul(total_liquidity as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / total_shares as u128;
+
+    u64::try_from(value).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// Shares to burn for a withdrawal of `value`, rounded up so the remaining LPs never
+// cover the rounding
+pub fn shares_for_value(value: u64, total_liquidity: u64, total_shares: u64) -> Result<u64> {
+    require!(total_liquidity > 0, ErrorCode::InsufficientBalance);
+
+    let shares = (value as u128)
+        .checked_mul(total_shares as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .div_ceil(total_liquidity as u128);
+
+    u64::try_from(shares).map_err(|_| error!(ErrorCode::MathOverflow))
+}
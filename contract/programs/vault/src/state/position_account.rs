@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+// A wallet's LP position in the trading pool, PDA ["position", user]. `shares` are
+// pool shares (see PoolLiquidity / CollateralMint); `amount` is the principal behind them.
+#[account]
+#[derive(InitSpace)]
+pub struct PositionAccount {
+    pub owner: Pubkey,
+    pub pool_id: u64,
+    pub amount: u64,
+    pub shares: u64,
+    pub lock_start_time: i64,
+    pub lock_end_time: i64,
+    pub last_deposit_time: i64,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub collateral_mint: Pubkey, // Pubkey::default() for native SOL
+    pub bump: u8,
+}
@@ -1,10 +1,22 @@
 use anchor_lang::prelude::*;
+use crate::oracle::PRICE_DECIMALS;
+use crate::state::SettlementData;
 
 #[account]
 pub struct PositionState {
     pub user: Pubkey,
     pub order_id: u64,
+    pub market: Pubkey, // Market PDA the position is priced against
     pub status: PositionStatus,
+    pub position_type: PositionType,
+    pub lower_bound: u64,
+    pub upper_bound: u64,
+    pub min_observed_price: u64,
+    pub max_observed_price: u64,
+    pub barrier_touched: bool,
+    pub barrier_touched_at: i64,
+    pub last_observed_price: u64,
+    pub last_observed_at: i64,
     pub is_long: bool,
     pub size: u64,
     pub entry_price: u64,
@@ -20,17 +32,46 @@ pub struct PositionState {
     pub claimable_rewards: u64,
     pub is_claimed: bool,
     pub total_rewards_earned: u64,
+    pub deferred_payout: u64, // Settlement owed but unpaid after pool and insurance ran dry
+    pub collateral_mint: Pubkey, // Pubkey::default() for native SOL
+    // Extra margin posted on top of `collateral_amount`, in other assets
+    pub margin_deposits: [MarginDeposit; MAX_MARGIN_DEPOSITS],
+    pub margin_value: u64,          // USD value of all margin after haircuts, see CollateralMint::USD_DECIMALS
+    pub effective_collateral: u64,  // `margin_value` in units of `collateral_mint`; used for health
+    pub margin_valued_at: i64,      // 0 until margin is first valued against the registry
     pub bump: u8,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct SettlementData {
-    pub settlement_time: i64,
-    pub settlement_price: u64,
-    pub payout_percentage: u8,
+pub const MAX_MARGIN_DEPOSITS: usize = 4;
+
+const PRICE_SCALE: u128 = 10u128.pow(PRICE_DECIMALS as u32);
+
+// Value of `size` at `price` in collateral units. Prices carry PRICE_DECIMALS decimals,
+// so size × price is scaled back down by 10^PRICE_DECIMALS.
+pub fn notional(size: u64, price: u64) -> Result<u128> {
+    let value = (size as u128)
+        .checked_mul(price as u128)
+        .ok_or(crate::error::ErrorCode::MathOverflow)?;
+
+    Ok(value / PRICE_SCALE)
+}
+
+// Margin asset held by a position. `mint` is Pubkey::default() for native SOL;
+// an empty slot has `amount == 0`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MarginDeposit {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum PositionType {
+    Leveraged,
+    StayIn,   // Wins if price stays within [lower_bound, upper_bound] until expiry
+    Breakout, // Wins if price leaves [lower_bound, upper_bound] before expiry
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PositionStatus {
     Active,
     Healthy,
@@ -40,27 +81,6 @@ pub enum PositionStatus {
     Liquidated,
 }
 
-#[account]
-pub struct VaultState {
-    pub owner: Pubkey,
-    pub vault_bump: u8,
-    pub state_bump: u8,
-    pub total_deposited: u64,
-    pub total_withdrawn: u64,
-    pub active_positions: u32,
-    pub total_rewards_claimed: u64,
-}
-
-#[account]
-pub struct TradingPool {
-    pub authority: Pubkey,
-    pub total_pool_amount: u64,
-    pub total_active_amount: u64,
-    pub total_fees_collected: u64,
-    pub vault_bump: u8,
-    pub bump: u8,
-}
-
 #[account]
 pub struct RewardPool {
     pub authority: Pubkey,
@@ -69,6 +89,14 @@ pub struct RewardPool {
     pub base_reward_rate: u16, // basis points per hour
     pub performance_pool_amount: u64,
     pub last_distribution_time: i64,
+    // Staking accumulator (MasterChef style)
+    pub total_staked: u64,
+    pub acc_reward_per_share: u128, // Scaled by REWARD_PRECISION
+    pub emission_rate_per_second: u64,
+    pub emission_start_time: i64,
+    pub emission_end_time: i64,
+    pub last_accrual_time: i64,
+    pub total_accrued: u64,
     pub vault_bump: u8,
     pub bump: u8,
 }
@@ -77,7 +105,17 @@ impl PositionState {
     pub const LEN: usize = 8 + // discriminator
         32 + // user
         8 + // order_id
+        32 + // market
         1 + // status
+        1 + // position_type
+        8 + // lower_bound
+        8 + // upper_bound
+        8 + // min_observed_price
+        8 + // max_observed_price
+        1 + // barrier_touched
+        8 + // barrier_touched_at
+        8 + // last_observed_price
+        8 + // last_observed_at
         1 + // is_long
         8 + // size
         8 + // entry_price
@@ -93,8 +131,15 @@ impl PositionState {
         8 + // claimable_rewards
         1 + // is_claimed
         8 + // total_rewards_earned
+        8 + // deferred_payout
+        32 + // collateral_mint
+        (32 + 8) * MAX_MARGIN_DEPOSITS + // margin_deposits
+        8 + // margin_value
+        8 + // effective_collateral
+        8 + // margin_valued_at
         1; // bump
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user: Pubkey,
         order_id: u64,
@@ -110,7 +155,17 @@ impl PositionState {
         Self {
             user,
             order_id,
+            market: Pubkey::default(),
             status: PositionStatus::Active,
+            position_type: PositionType::Leveraged,
+            lower_bound: 0,
+            upper_bound: 0,
+            min_observed_price: entry_price,
+            max_observed_price: entry_price,
+            barrier_touched: false,
+            barrier_touched_at: 0,
+            last_observed_price: entry_price,
+            last_observed_at: current_time,
             is_long,
             size,
             entry_price,
@@ -126,8 +181,216 @@ impl PositionState {
             claimable_rewards: 0,
             is_claimed: false,
             total_rewards_earned: 0,
+            deferred_payout: 0,
+            collateral_mint: Pubkey::default(),
+            margin_deposits: [MarginDeposit::default(); MAX_MARGIN_DEPOSITS],
+            margin_value: 0,
+            effective_collateral: 0,
+            margin_valued_at: 0,
+            bump,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_range(
+        user: Pubkey,
+        order_id: u64,
+        position_type: PositionType,
+        lower_bound: u64,
+        upper_bound: u64,
+        amount: u64,
+        entry_price: u64,
+        expires_at: i64,
+        bump: u8,
+    ) -> Self {
+        // Range positions are fully collateralised: size == collateral, 1x leverage
+        let mut position = Self::new(
+            user,
+            order_id,
+            false,
+            amount,
+            entry_price,
+            amount,
+            1,
+            expires_at,
             bump,
+        );
+        position.position_type = position_type;
+        position.lower_bound = lower_bound;
+        position.upper_bound = upper_bound;
+        position
+    }
+
+    pub fn is_range(&self) -> bool {
+        self.position_type != PositionType::Leveraged
+    }
+
+    pub fn is_price_in_range(&self, price: u64) -> bool {
+        price >= self.lower_bound && price <= self.upper_bound
+    }
+
+    // Folds a verified oracle price into the running min/max and latches
+    // `barrier_touched` the first time a range position's band is left.
+    // Prices published outside [created_at, expires_at] are ignored.
+    pub fn record_price_observation(&mut self, price: u64, publish_time: i64) -> bool {
+        if publish_time < self.created_at || publish_time > self.expires_at {
+            return false;
+        }
+
+        self.min_observed_price = self.min_observed_price.min(price);
+        self.max_observed_price = self.max_observed_price.max(price);
+
+        if publish_time >= self.last_observed_at {
+            self.last_observed_price = price;
+            self.last_observed_at = publish_time;
+        }
+
+        if self.is_range() && !self.barrier_touched && !self.is_price_in_range(price) {
+            self.barrier_touched = true;
+            self.barrier_touched_at = publish_time;
+            return true;
         }
+
+        false
+    }
+
+    pub fn has_margin_deposits(&self) -> bool {
+        self.margin_deposits.iter().any(|deposit| deposit.amount > 0)
+    }
+
+    // Adds to the slot already holding `mint`, or to the first empty one
+    pub fn add_margin_deposit(&mut self, mint: Pubkey, amount: u64) -> Result<usize> {
+        let slot = self.margin_deposits.iter()
+            .position(|deposit| deposit.amount > 0 && deposit.mint == mint)
+            .or_else(|| self.margin_deposits.iter().position(|deposit| deposit.amount == 0))
+            .ok_or(crate::error::ErrorCode::MarginSlotsFull)?;
+
+        let deposit = &mut self.margin_deposits[slot];
+        deposit.mint = mint;
+        deposit.amount = deposit.amount.checked_add(amount)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+
+        Ok(slot)
+    }
+
+    // Collateral counted for health: the haircut margin once it has been valued for a
+    // position holding extra margin, the raw collateral amount otherwise
+    pub fn margin_collateral(&self) -> u64 {
+        if self.margin_valued_at > 0 && self.has_margin_deposits() {
+            self.effective_collateral
+        } else {
+            self.collateral_amount
+        }
+    }
+
+    // Takes `seized` out of the collateral and the same share out of the haircut margin
+    pub fn seize_collateral(&mut self, seized: u64) {
+        if self.collateral_amount > 0 {
+            let seized_margin = (self.effective_collateral as u128 * seized as u128
+                / self.collateral_amount as u128) as u64;
+            let seized_value = (self.margin_value as u128 * seized_margin as u128
+                / self.effective_collateral.max(1) as u128) as u64;
+            self.effective_collateral = self.effective_collateral.saturating_sub(seized_margin);
+            self.margin_value = self.margin_value.saturating_sub(seized_value);
+        }
+        self.collateral_amount = self.collateral_amount.saturating_sub(seized);
+    }
+
+    // Drops a margin valuation older than `maximum_age`, so it is never used for health
+    pub fn expire_margin_valuation(&mut self, current_time: i64, maximum_age: u64) {
+        if self.margin_valued_at > 0
+            && current_time.saturating_sub(self.margin_valued_at) > maximum_age as i64
+        {
+            self.margin_valued_at = 0;
+        }
+    }
+
+    // Released margin leaves the collateral and the haircut margin the same way seized collateral does
+    pub fn release_collateral(&mut self, released: u64) {
+        self.seize_collateral(released);
+    }
+
+    // Collateral above what keeps the health score at `target_health` at `current_price`
+    pub fn excess_collateral(&self, current_price: u64, target_health: u16) -> Result<u64> {
+        let margin = self.margin_collateral();
+        if margin == 0 {
+            return Ok(0);
+        }
+
+        let required_margin = notional(self.size, current_price)?
+            .checked_mul(target_health as u128)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?
+            .checked_div(100 * self.leverage as u128)
+            .ok_or(crate::error::ErrorCode::DivisionByZero)?;
+
+        // Health counts the haircut margin; convert the surplus back to collateral units
+        let excess_margin = (margin as u128).saturating_sub(required_margin);
+        let excess = excess_margin * self.collateral_amount as u128 / margin as u128;
+
+        Ok(excess.min(self.collateral_amount as u128) as u64)
+    }
+
+    // Margin the position needs at `current_price`: notional / leverage
+    pub fn required_margin(&self, current_price: u64) -> Result<u64> {
+        let required_margin = self.required_margin_u128(current_price)?;
+
+        u64::try_from(required_margin).map_err(|_| error!(crate::error::ErrorCode::MathOverflow))
+    }
+
+    fn required_margin_u128(&self, current_price: u64) -> Result<u128> {
+        notional(self.size, current_price)?
+            .checked_div(self.leverage as u128)
+            .ok_or(crate::error::ErrorCode::DivisionByZero.into())
+    }
+
+    // Health score = (Collateral / Required Margin) * 100, computed in u128 since
+    // size × price alone overflows u64 at realistic magnitudes
+    pub fn calculate_health_score(&self, current_price: u64) -> Result<u16> {
+        let required_margin = self.required_margin_u128(current_price)?;
+
+        let health_score = (self.margin_collateral() as u128 * 100)
+            .checked_div(required_margin)
+            .ok_or(crate::error::ErrorCode::DivisionByZero)?;
+
+        Ok(health_score.min(u16::MAX as u128) as u16)
+    }
+
+    // Price at which the health score drops to `liquidation_threshold`
+    pub fn calculate_liquidation_price(&self, liquidation_threshold: u16) -> Result<u64> {
+        let numerator = (self.margin_collateral() as u128)
+            .checked_mul(100 * self.leverage as u128)
+            .and_then(|value| value.checked_mul(PRICE_SCALE))
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+
+        let denominator = (self.size as u128)
+            .checked_mul(liquidation_threshold as u128)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+
+        let liquidation_price = numerator
+            .checked_div(denominator)
+            .ok_or(crate::error::ErrorCode::DivisionByZero)?;
+
+        Ok(liquidation_price.min(u64::MAX as u128) as u64)
+    }
+
+    // P&L of the price move from entry to `price` on `size`, in collateral units.
+    // Losses round away from zero so the position never gains from the rounding.
+    pub fn price_pnl(&self, size: u64, price: u64) -> Result<i128> {
+        let price_diff = if self.is_long {
+            price as i128 - self.entry_price as i128
+        } else {
+            self.entry_price as i128 - price as i128
+        };
+
+        let pnl = (size as i128)
+            .checked_mul(price_diff)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+
+        Ok(pnl.div_euclid(PRICE_SCALE as i128))
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.status != PositionStatus::Settled && self.status != PositionStatus::Liquidated
     }
 
     pub fn settle(
@@ -177,27 +440,6 @@ impl PositionState {
     }
 }
 
-impl VaultState {
-    pub const LEN: usize = 8 + // discriminator
-        32 + // owner
-        1 + // vault_bump
-        1 + // state_bump
-        8 + // total_deposited
-        8 + // total_withdrawn
-        4 + // active_positions
-        8; // total_rewards_claimed
-}
-
-impl TradingPool {
-    pub const LEN: usize = 8 + // discriminator
-        32 + // authority
-        8 + // total_pool_amount
-        8 + // total_active_amount
-        8 + // total_fees_collected
-        1 + // vault_bump
-        1; // bump
-}
-
 impl RewardPool {
     pub const LEN: usize = 8 + // discriminator
         32 + // authority
@@ -206,6 +448,336 @@ impl RewardPool {
         2 + // base_reward_rate
         8 + // performance_pool_amount
         8 + // last_distribution_time
+        8 + // total_staked
+        16 + // acc_reward_per_share
+        8 + // emission_rate_per_second
+        8 + // emission_start_time
+        8 + // emission_end_time
+        8 + // last_accrual_time
+        8 + // total_accrued
         1 + // vault_bump
         1; // bump
-}
\ No newline at end of file
+
+    pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+    // Brings `acc_reward_per_share` up to `current_time`. Must run before any change to
+    // `total_staked` so every staker is credited at the rate that applied to them.
+    pub fn accrue(&mut self, current_time: i64) -> Result<()> {
+        if current_time <= self.last_accrual_time {
+            return Ok(());
+        }
+
+        let elapsed = self.emission_seconds_between(self.last_accrual_time, current_time);
+        if self.total_staked == 0 || self.emission_rate_per_second == 0 || elapsed == 0 {
+            self.last_accrual_time = current_time;
+            return Ok(());
+        }
+
+        let emitted = (elapsed as u128)
+            .checked_mul(self.emission_rate_per_second as u128)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+
+        // Never emit more than has been funded
+        let unallocated = self.total_reward_amount.saturating_sub(self.total_accrued) as u128;
+        let emitted = emitted.min(unallocated);
+
+        let increment = emitted
+            .checked_mul(Self::REWARD_PRECISION)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?
+            / self.total_staked as u128;
+
+        self.acc_reward_per_share = self.acc_reward_per_share
+            .checked_add(increment)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+        self.total_accrued = self.total_accrued
+            .checked_add(emitted as u64)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+        self.last_accrual_time = current_time;
+
+        Ok(())
+    }
+
+    // Seconds of [from, to) that fall inside the emission schedule
+    pub fn emission_seconds_between(&self, from: i64, to: i64) -> i64 {
+        let start = from.max(self.emission_start_time);
+        let end = to.min(self.emission_end_time);
+        (end - start).max(0)
+    }
+
+    // Vault lamports stakers have accrued but not claimed yet; position rewards never
+    // pay out of this
+    pub fn staker_reserve(&self) -> u64 {
+        self.total_accrued.saturating_sub(self.total_distributed)
+    }
+
+    // Emission budget not yet allocated to stakers; time-based position rewards draw on it
+    pub fn unallocated_emissions(&self) -> u64 {
+        self.total_reward_amount.saturating_sub(self.total_accrued)
+    }
+
+    // Books time-based position rewards as emitted and paid at once, so they use up the
+    // emission budget without changing the staker reserve
+    pub fn record_time_rewards(&mut self, amount: u64) -> Result<()> {
+        self.total_accrued = self.total_accrued
+            .checked_add(amount)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+        self.total_distributed = self.total_distributed
+            .checked_add(amount)
+            .ok_or(crate::error::ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn accumulated_for(&self, staked: u64) -> Result<u128> {
+        (staked as u128)
+            .checked_mul(self.acc_reward_per_share)
+            .map(|value| value / Self::REWARD_PRECISION)
+            .ok_or(crate::error::ErrorCode::MathOverflow.into())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PositionState::new reads the clock, which is not available off-chain
+    fn range_position(lower_bound: u64, upper_bound: u64, entry_price: u64) -> PositionState {
+        PositionState {
+            user: Pubkey::default(),
+            order_id: 1,
+            market: Pubkey::default(),
+            status: PositionStatus::Active,
+            position_type: PositionType::StayIn,
+            lower_bound,
+            upper_bound,
+            min_observed_price: entry_price,
+            max_observed_price: entry_price,
+            barrier_touched: false,
+            barrier_touched_at: 0,
+            last_observed_price: entry_price,
+            last_observed_at: 1_000,
+            is_long: false,
+            size: 100,
+            entry_price,
+            collateral_amount: 100,
+            leverage: 1,
+            created_at: 1_000,
+            expires_at: 2_000,
+            settlement_data: None,
+            unrealized_pnl: 0,
+            liquidation_price: None,
+            last_health_check: 1_000,
+            last_reward_claim: 1_000,
+            claimable_rewards: 0,
+            is_claimed: false,
+            total_rewards_earned: 0,
+            deferred_payout: 0,
+            collateral_mint: Pubkey::default(),
+            margin_deposits: [MarginDeposit::default(); MAX_MARGIN_DEPOSITS],
+            margin_value: 0,
+            effective_collateral: 0,
+            margin_valued_at: 0,
+            bump: 0,
+        }
+    }
+
+    fn leveraged_position(size: u64, collateral_amount: u64, leverage: u8, entry_price: u64) -> PositionState {
+        let mut position = range_position(0, 0, entry_price);
+        position.position_type = PositionType::Leveraged;
+        position.is_long = true;
+        position.size = size;
+        position.collateral_amount = collateral_amount;
+        position.leverage = leverage;
+        position
+    }
+
+    fn reward_pool(total_staked: u64, emission_rate_per_second: u64, total_reward_amount: u64) -> RewardPool {
+        RewardPool {
+            authority: Pubkey::default(),
+            total_reward_amount,
+            total_distributed: 0,
+            base_reward_rate: 0,
+            performance_pool_amount: 0,
+            last_distribution_time: 0,
+            total_staked,
+            acc_reward_per_share: 0,
+            emission_rate_per_second,
+            emission_start_time: 100,
+            emission_end_time: 200,
+            last_accrual_time: 100,
+            total_accrued: 0,
+            vault_bump: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn observations_outside_the_position_lifetime_are_ignored() {
+        let mut position = range_position(90, 110, 100);
+
+        assert!(!position.record_price_observation(50, 999));
+        assert!(!position.record_price_observation(50, 2_001));
+        assert_eq!(position.min_observed_price, 100);
+        assert_eq!(position.last_observed_at, 1_000);
+        assert!(!position.barrier_touched);
+    }
+
+    #[test]
+    fn observations_widen_the_range_and_only_newer_ones_move_the_latest() {
+        let mut position = range_position(90, 110, 100);
+
+        position.record_price_observation(105, 1_200);
+        position.record_price_observation(95, 1_100);
+
+        assert_eq!(position.min_observed_price, 95);
+        assert_eq!(position.max_observed_price, 105);
+        assert_eq!(position.last_observed_price, 105);
+        assert_eq!(position.last_observed_at, 1_200);
+    }
+
+    #[test]
+    fn first_move_out_of_the_band_latches_the_barrier() {
+        let mut position = range_position(90, 110, 100);
+
+        assert!(position.record_price_observation(111, 1_300));
+        assert!(position.barrier_touched);
+        assert_eq!(position.barrier_touched_at, 1_300);
+
+        // Later breaks and a return into the band leave the first touch in place
+        assert!(!position.record_price_observation(80, 1_400));
+        assert!(!position.record_price_observation(100, 1_500));
+        assert!(position.barrier_touched);
+        assert_eq!(position.barrier_touched_at, 1_300);
+    }
+
+    #[test]
+    fn band_edges_count_as_inside() {
+        let mut position = range_position(90, 110, 100);
+
+        assert!(!position.record_price_observation(90, 1_100));
+        assert!(!position.record_price_observation(110, 1_200));
+        assert!(!position.barrier_touched);
+    }
+
+    #[test]
+    fn leveraged_positions_never_touch_a_barrier() {
+        let mut position = range_position(0, 0, 100);
+        position.position_type = PositionType::Leveraged;
+
+        assert!(!position.record_price_observation(150, 1_100));
+        assert!(!position.barrier_touched);
+        assert_eq!(position.max_observed_price, 150);
+    }
+
+    #[test]
+    fn notional_and_pnl_drop_the_price_decimals() {
+        // 10 units at $150.00, with prices carrying 8 decimals
+        let position = leveraged_position(10, 300, 5, 150 * 100_000_000);
+
+        assert_eq!(notional(10, 150 * 100_000_000).unwrap(), 1_500);
+        assert_eq!(position.required_margin(150 * 100_000_000).unwrap(), 300);
+        assert_eq!(position.calculate_health_score(150 * 100_000_000).unwrap(), 100);
+
+        // A $2.00 move on 10 units is worth 20
+        assert_eq!(position.price_pnl(10, 152 * 100_000_000).unwrap(), 20);
+        assert_eq!(position.price_pnl(10, 148 * 100_000_000).unwrap(), -20);
+    }
+
+    #[test]
+    fn health_score_holds_at_realistic_magnitudes() {
+        // 100 SOL in lamports at $65,000 with 8 price decimals: size × price is ~6.5e23
+        let position = leveraged_position(100_000_000_000, 1_000_000_000_000_000, 10, 6_500_000_000_000);
+
+        assert_eq!(position.required_margin(6_500_000_000_000).unwrap(), 650_000_000_000_000);
+        assert_eq!(position.calculate_health_score(6_500_000_000_000).unwrap(), 153);
+    }
+
+    #[test]
+    fn health_score_of_an_oversized_position_is_zero_not_an_overflow() {
+        let position = leveraged_position(u64::MAX, 1_000_000_000, 1, u64::MAX);
+
+        assert_eq!(position.calculate_health_score(u64::MAX).unwrap(), 0);
+    }
+
+    #[test]
+    fn pnl_losses_round_against_the_position() {
+        let position = leveraged_position(1, 100, 1, 100_000_000);
+
+        assert_eq!(position.price_pnl(1, 100_000_001).unwrap(), 0);
+        assert_eq!(position.price_pnl(1, 99_999_999).unwrap(), -1);
+    }
+
+    #[test]
+    fn liquidation_price_is_where_health_hits_the_threshold() {
+        let position = leveraged_position(10, 300, 5, 150 * 100_000_000);
+
+        let liquidation_price = position.calculate_liquidation_price(80).unwrap();
+
+        assert_eq!(liquidation_price, 18_750_000_000);
+        assert_eq!(position.calculate_health_score(liquidation_price).unwrap(), 80);
+    }
+
+    #[test]
+    fn accrue_spreads_emissions_over_the_stake() {
+        let mut pool = reward_pool(1_000, 10, 1_000_000);
+
+        pool.accrue(150).unwrap();
+
+        assert_eq!(pool.total_accrued, 500);
+        assert_eq!(pool.last_accrual_time, 150);
+        assert_eq!(pool.accumulated_for(1_000).unwrap(), 500);
+        assert_eq!(pool.accumulated_for(250).unwrap(), 125);
+    }
+
+    #[test]
+    fn accrue_stops_at_the_end_of_the_schedule() {
+        let mut pool = reward_pool(1_000, 10, 1_000_000);
+
+        pool.accrue(500).unwrap();
+
+        assert_eq!(pool.total_accrued, 1_000);
+        assert_eq!(pool.last_accrual_time, 500);
+    }
+
+    #[test]
+    fn accrue_never_emits_more_than_was_funded() {
+        let mut pool = reward_pool(1_000, 10, 300);
+
+        pool.accrue(200).unwrap();
+
+        assert_eq!(pool.total_accrued, 300);
+        assert_eq!(pool.unallocated_emissions(), 0);
+        assert_eq!(pool.accumulated_for(1_000).unwrap(), 300);
+    }
+
+    #[test]
+    fn accrue_without_stake_only_moves_the_clock() {
+        let mut pool = reward_pool(0, 10, 1_000_000);
+
+        pool.accrue(150).unwrap();
+
+        assert_eq!(pool.total_accrued, 0);
+        assert_eq!(pool.acc_reward_per_share, 0);
+        assert_eq!(pool.last_accrual_time, 150);
+    }
+
+    #[test]
+    fn accrue_ignores_times_already_accrued() {
+        let mut pool = reward_pool(1_000, 10, 1_000_000);
+        pool.accrue(150).unwrap();
+
+        pool.accrue(120).unwrap();
+
+        assert_eq!(pool.total_accrued, 500);
+        assert_eq!(pool.last_accrual_time, 150);
+    }
+
+    #[test]
+    fn time_rewards_use_the_budget_but_not_the_staker_reserve() {
+        let mut pool = reward_pool(1_000, 10, 1_000_000);
+        pool.accrue(150).unwrap();
+
+        pool.record_time_rewards(200).unwrap();
+
+        assert_eq!(pool.staker_reserve(), 500);
+        assert_eq!(pool.unallocated_emissions(), 1_000_000 - 700);
+    }
+}
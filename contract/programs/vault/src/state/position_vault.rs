@@ -0,0 +1,10 @@
+use anchor_lang::prelude::*;
+
+// Collateral held for one position, PDA ["position_vault", position]
+#[account]
+#[derive(InitSpace)]
+pub struct PositionVault {
+    pub position: Pubkey,
+    pub balance: u64,
+    pub bump: u8,
+}
@@ -0,0 +1,364 @@
+use anchor_lang::prelude::*;
+use crate::oracle::OraclePrice;
+use crate::state::{Market, MarketParams};
+use crate::error::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct PriceObservation {
+    pub slot: u64,      // Slot the observation was recorded in
+    pub timestamp: i64, // Oracle publish time
+    pub price: u64,     // oracle::PRICE_DECIMALS fixed point
+    pub conf: u64,
+}
+
+pub const PRICE_HISTORY_CAPACITY: usize = 64;
+
+// Circuit breaker state change caused by a new observation
+pub enum BreakerChange {
+    Tripped { reference_price: u64, deviation_bps: u64 },
+    Cleared,
+}
+
+// Ring buffer of the prices the program has seen for one market, PDA ["price_history", market].
+// Appended to whenever a position of the market is created, checked, closed or liquidated.
+#[account]
+#[derive(InitSpace)]
+pub struct PriceHistory {
+    pub market: Pubkey,
+    pub head: u16,  // Index the next observation is written to
+    pub count: u16, // Number of filled entries, up to PRICE_HISTORY_CAPACITY
+    pub observations: [PriceObservation; PRICE_HISTORY_CAPACITY],
+    pub breaker_tripped_at: i64, // Publish time of the last price that tripped the breaker; 0 when clear
+    pub bump: u8,
+}
+
+impl PriceHistory {
+    pub fn is_breaker_tripped(&self) -> bool {
+        self.breaker_tripped_at != 0
+    }
+
+    // Runs the market's circuit breaker on a new price, then records it. The breaker trips
+    // when the price deviates more than the threshold from any observation within the
+    // window, and clears once a full window has passed without such a move.
+    pub fn observe(&mut self, market: &Market, slot: u64, price: &OraclePrice) -> Option<BreakerChange> {
+        if self.latest().is_some_and(|latest| price.publish_time <= latest.timestamp) {
+            return None;
+        }
+
+        let change = if market.has_circuit_breaker() {
+            self.evaluate_breaker(market, price)
+        } else if self.is_breaker_tripped() {
+            // Breaker was disabled while tripped
+            self.breaker_tripped_at = 0;
+            Some(BreakerChange::Cleared)
+        } else {
+            None
+        };
+
+        self.record(slot, price);
+        change
+    }
+
+    // Whether the breaker would be tripped after observing `price`, without changing any state.
+    // Lets a caller that is about to fail on a trip reject before anything is recorded.
+    pub fn is_tripped_after(&self, market: &Market, price: &OraclePrice) -> bool {
+        if self.latest().is_some_and(|latest| price.publish_time <= latest.timestamp) {
+            return self.is_breaker_tripped();
+        }
+        if !market.has_circuit_breaker() {
+            return false;
+        }
+
+        if self.largest_move(market, price)
+            .is_some_and(|(_, deviation_bps)| deviation_bps > market.params.breaker_threshold_bps as u64)
+        {
+            return true;
+        }
+
+        let window = market.params.breaker_window_secs as i64;
+        self.is_breaker_tripped() && price.publish_time - self.breaker_tripped_at < window
+    }
+
+    // Largest move of `price` against any stored price in the breaker window, with the
+    // reference price it was measured against
+    fn largest_move(&self, market: &Market, price: &OraclePrice) -> Option<(u64, u64)> {
+        let window_start = price.publish_time.saturating_sub(market.params.breaker_window_secs as i64);
+
+        self.iter()
+            .filter(|observation| observation.timestamp >= window_start && observation.price > 0)
+            .map(|observation| {
+                let deviation_bps = (observation.price.abs_diff(price.price) as u128 * 10000
+                    / observation.price as u128) as u64;
+                (observation.price, deviation_bps)
+            })
+            .max_by_key(|(_, deviation_bps)| *deviation_bps)
+    }
+
+    fn evaluate_breaker(&mut self, market: &Market, price: &OraclePrice) -> Option<BreakerChange> {
+        let window = market.params.breaker_window_secs as i64;
+
+        if let Some((reference_price, deviation_bps)) = self.largest_move(market, price) {
+            if deviation_bps > market.params.breaker_threshold_bps as u64 {
+                let was_tripped = self.is_breaker_tripped();
+                self.breaker_tripped_at = price.publish_time;
+                return (!was_tripped).then_some(BreakerChange::Tripped { reference_price, deviation_bps });
+            }
+        }
+
+        if self.is_breaker_tripped() && price.publish_time - self.breaker_tripped_at >= window {
+            self.breaker_tripped_at = 0;
+            return Some(BreakerChange::Cleared);
+        }
+
+        None
+    }
+
+    // Appends a price unless it is not newer than the latest one already stored
+    pub fn record(&mut self, slot: u64, price: &OraclePrice) -> bool {
+        if let Some(latest) = self.latest() {
+            if price.publish_time <= latest.timestamp {
+                return false;
+            }
+        }
+
+        self.observations[self.head as usize] = PriceObservation {
+            slot,
+            timestamp: price.publish_time,
+            price: price.price,
+            conf: price.conf,
+        };
+        self.head = ((self.head as usize + 1) % PRICE_HISTORY_CAPACITY) as u16;
+        self.count = (self.count + 1).min(PRICE_HISTORY_CAPACITY as u16);
+
+        true
+    }
+
+    pub fn latest(&self) -> Option<&PriceObservation> {
+        if self.count == 0 {
+            return None;
+        }
+        let index = (self.head as usize + PRICE_HISTORY_CAPACITY - 1) % PRICE_HISTORY_CAPACITY;
+        Some(&self.observations[index])
+    }
+
+    // Observations from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = &PriceObservation> {
+        let oldest = self.head as usize + PRICE_HISTORY_CAPACITY - self.count as usize;
+        (0..self.count as usize).map(move |offset| &self.observations[(oldest + offset) % PRICE_HISTORY_CAPACITY])
+    }
+
+    // Time-weighted average price over the market's `twap_window_secs` up to `now`. Only
+    // observations published inside the window count, each for the time until the next
+    // one (or `now`). Fails unless at least `twap_min_observations` of them cover
+    // `twap_min_coverage_bps` of the window.
+    pub fn twap(&self, now: i64, params: &MarketParams) -> Result<u64> {
+        let window = params.twap_window_secs as i64;
+        let window_start = now.saturating_sub(window);
+        let mut weighted_sum: u128 = 0;
+        let mut total_time: u128 = 0;
+        let mut samples: u16 = 0;
+
+        let mut observations = self.iter()
+            .filter(|observation| observation.timestamp >= window_start && observation.timestamp <= now)
+            .peekable();
+        while let Some(observation) = observations.next() {
+            samples += 1;
+
+            let segment_end = observations.peek()
+                .map(|next| next.timestamp)
+                .unwrap_or(now);
+            let duration = (segment_end - observation.timestamp) as u128;
+
+            weighted_sum = weighted_sum
+                .checked_add(observation.price as u128 * duration)
+                .ok_or(ErrorCode::MathOverflow)?;
+            total_time += duration;
+        }
+
+        require!(
+            samples >= params.twap_min_observations
+                && total_time > 0
+                && total_time * 10000 >= window as u128 * params.twap_min_coverage_bps as u128,
+            ErrorCode::InsufficientPriceHistory
+        );
+
+        Ok((weighted_sum / total_time) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{MarketParams, MarkPriceMode};
+
+    fn history(prices: &[(i64, u64)]) -> PriceHistory {
+        let mut history = PriceHistory {
+            market: Pubkey::default(),
+            head: 0,
+            count: 0,
+            observations: [PriceObservation::default(); PRICE_HISTORY_CAPACITY],
+            breaker_tripped_at: 0,
+            bump: 0,
+        };
+        for &(timestamp, price) in prices {
+            history.record(0, &quote(timestamp, price));
+        }
+        history
+    }
+
+    fn quote(publish_time: i64, price: u64) -> OraclePrice {
+        OraclePrice { price, conf: 0, ema_price: price, ema_conf: 0, publish_time }
+    }
+
+    #[test]
+    fn twap_weights_each_price_by_how_long_it_stood() {
+        let history = history(&[(100, 100), (150, 200), (180, 300)]);
+
+        // (50 * 100 + 30 * 200 + 20 * 300) / 100
+        assert_eq!(history.twap(200, &market(0, 0).params).unwrap(), 170);
+    }
+
+    #[test]
+    fn twap_ignores_prices_from_before_the_window() {
+        let history = history(&[(0, 1_000), (150, 100), (160, 100), (170, 100)]);
+
+        assert_eq!(history.twap(200, &market(0, 0).params).unwrap(), 100);
+    }
+
+    #[test]
+    fn twap_needs_enough_observations_in_the_window() {
+        let history = history(&[(0, 100), (120, 100), (160, 100)]);
+
+        assert!(history.twap(200, &market(0, 0).params).is_err());
+    }
+
+    #[test]
+    fn twap_needs_the_observations_to_cover_enough_of_the_window() {
+        let history = history(&[(190, 100), (195, 100), (199, 100)]);
+
+        assert!(history.twap(200, &market(0, 0).params).is_err());
+    }
+
+    #[test]
+    fn twap_minimums_come_from_the_market() {
+        let history = history(&[(120, 100), (160, 100)]);
+        let mut market = market(0, 0);
+        assert!(history.twap(200, &market.params).is_err());
+
+        market.params.twap_min_observations = 2;
+        assert_eq!(history.twap(200, &market.params).unwrap(), 100);
+
+        market.params.twap_min_coverage_bps = 9000;
+        assert!(history.twap(200, &market.params).is_err());
+    }
+
+    #[test]
+    fn record_keeps_the_newest_prices_once_full() {
+        let prices: Vec<(i64, u64)> = (1..=PRICE_HISTORY_CAPACITY as i64 + 2)
+            .map(|timestamp| (timestamp, timestamp as u64))
+            .collect();
+        let history = history(&prices);
+
+        assert_eq!(history.count as usize, PRICE_HISTORY_CAPACITY);
+        assert_eq!(history.iter().next().unwrap().timestamp, 3);
+        assert_eq!(history.latest().unwrap().timestamp, PRICE_HISTORY_CAPACITY as i64 + 2);
+    }
+
+    #[test]
+    fn record_skips_prices_that_are_not_newer() {
+        let mut history = history(&[(100, 100)]);
+
+        assert!(!history.record(0, &quote(100, 200)));
+        assert!(!history.record(0, &quote(90, 200)));
+        assert_eq!(history.count, 1);
+    }
+
+    fn market(breaker_threshold_bps: u16, breaker_window_secs: u32) -> Market {
+        Market {
+            symbol: *b"BTC\0\0\0\0\0",
+            params: MarketParams {
+                price_feed_id: [0; 32],
+                is_active: true,
+                max_leverage: 10,
+                min_position_size: 0,
+                max_position_size: 0,
+                trading_fee_bps: None,
+                closing_fee_bps: None,
+                mark_price_mode: MarkPriceMode::Spot,
+                secondary_signer: Pubkey::default(),
+                max_deviation_bps: 0,
+                twap_window_secs: 100,
+                twap_min_observations: 3,
+                twap_min_coverage_bps: 5000,
+                settle_at_twap: false,
+                breaker_threshold_bps,
+                breaker_window_secs,
+            },
+            last_updated: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn a_large_move_within_the_window_trips_the_breaker() {
+        let market = market(1_000, 60);
+        let mut history = history(&[(10, 100)]);
+
+        let change = history.observe(&market, 0, &quote(20, 120));
+
+        assert!(matches!(change, Some(BreakerChange::Tripped { reference_price: 100, deviation_bps: 2_000 })));
+        assert_eq!(history.breaker_tripped_at, 20);
+        assert_eq!(history.count, 2);
+    }
+
+    #[test]
+    fn a_move_within_the_threshold_leaves_the_breaker_alone() {
+        let market = market(1_000, 60);
+        let mut history = history(&[(10, 100)]);
+
+        assert!(history.observe(&market, 0, &quote(20, 110)).is_none());
+        assert!(!history.is_breaker_tripped());
+    }
+
+    #[test]
+    fn the_breaker_clears_a_full_window_after_the_last_trip() {
+        let market = market(1_000, 60);
+        let mut history = history(&[(10, 100)]);
+        history.observe(&market, 0, &quote(20, 120));
+
+        // Still inside the window of the tripping price
+        assert!(history.observe(&market, 0, &quote(50, 121)).is_none());
+        assert!(history.is_breaker_tripped());
+
+        let change = history.observe(&market, 0, &quote(111, 121));
+        assert!(matches!(change, Some(BreakerChange::Cleared)));
+        assert!(!history.is_breaker_tripped());
+    }
+
+    #[test]
+    fn disabling_the_breaker_clears_a_trip() {
+        let mut history = history(&[(10, 100)]);
+        history.observe(&market(1_000, 60), 0, &quote(20, 120));
+
+        let change = history.observe(&market(0, 60), 0, &quote(30, 120));
+
+        assert!(matches!(change, Some(BreakerChange::Cleared)));
+        assert!(!history.is_breaker_tripped());
+    }
+
+    #[test]
+    fn is_tripped_after_previews_observe_without_recording() {
+        let market = market(1_000, 60);
+        let history = history(&[(10, 100)]);
+
+        assert!(history.is_tripped_after(&market, &quote(20, 120)));
+        assert!(!history.is_tripped_after(&market, &quote(20, 110)));
+        assert_eq!(history.count, 1);
+        assert!(!history.is_breaker_tripped());
+
+        let mut observed = history.clone();
+        observed.observe(&market, 0, &quote(20, 120));
+        assert!(observed.is_tripped_after(&market, &quote(50, 121)));
+        assert!(!observed.is_tripped_after(&market, &quote(111, 121)));
+    }
+}
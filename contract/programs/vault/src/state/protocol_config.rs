@@ -0,0 +1,359 @@
+use anchor_lang::prelude::*;
+use crate::constants::*;
+use crate::error::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct DepositFeeTier {
+    pub min_amount: u64, // Lower bound of the tier in lamports (inclusive)
+    pub fee_bps: u16,
+}
+
+// Weights for splitting every protocol fee
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct FeeSplit {
+    pub lp_bps: u16,
+    pub treasury_bps: u16,
+    pub insurance_bps: u16,
+    pub referrer_bps: u16,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FeeSplitAmounts {
+    pub lp: u64,
+    pub treasury: u64,
+    pub insurance: u64,
+    pub referrer: u64,
+}
+
+impl FeeSplit {
+    // Treasury takes the rounding remainder and the referrer share when there is no referrer
+    pub fn split(&self, amount: u64, has_referrer: bool) -> FeeSplitAmounts {
+        let share = |bps: u16| ((amount as u128 * bps as u128) / 10000) as u64;
+
+        let lp = share(self.lp_bps);
+        let insurance = share(self.insurance_bps);
+        let referrer = if has_referrer { share(self.referrer_bps) } else { 0 };
+
+        FeeSplitAmounts {
+            lp,
+            treasury: amount - lp - insurance - referrer,
+            insurance,
+            referrer,
+        }
+    }
+}
+
+// Tunable protocol parameters. Every field has a compiled default in constants.rs
+// and can be changed by `admin` through `update_config` without a redeploy.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct ConfigParams {
+    // Oracle
+    pub maximum_age: u64,
+    pub max_confidence_bps: u16, // Prices whose conf / price is wider are rejected
+
+    // Trading fees
+    pub trading_fee_bps: u16,
+    pub closing_fee_bps: u16,
+
+    // Position limits
+    pub min_position_size: u64,
+    pub min_leverage: u8,
+    pub max_leverage: u8,
+    pub range_position_duration: i64,
+
+    // Health thresholds
+    pub healthy_threshold: u16,
+    pub warning_threshold: u16,
+    pub liquidation_threshold: u16,
+
+    // Liquidation
+    pub liquidation_bounty_bps: u16,
+    pub liquidation_step_bps: u16,
+    pub liquidation_penalty_bps: u16,
+    pub max_liquidation_steps: u8,
+
+    // Deposit fee tiers, sorted by descending `min_amount`; the last tier should start at 0
+    pub deposit_fee_tiers: [DepositFeeTier; 4],
+
+    // Withdrawals
+    pub withdrawal_fee_bps: u16,
+
+    // Fee distribution
+    pub fee_split: FeeSplit,
+
+    // Early withdrawal penalties by remaining lock time
+    pub early_withdrawal_penalty_half_bps: u16,    // > 1/2 of the lock remaining
+    pub early_withdrawal_penalty_quarter_bps: u16, // > 1/4 of the lock remaining
+    pub early_withdrawal_penalty_min_bps: u16,     // otherwise
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey, // Pubkey::default() when no transfer is pending
+    pub is_paused: bool,
+    pub paused_at: i64,
+    pub is_emergency: bool, // Withdraw-only mode
+    pub emergency_started_at: i64,
+    pub params: ConfigParams,
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+impl Default for ConfigParams {
+    fn default() -> Self {
+        Self {
+            maximum_age: MAXIMUM_AGE,
+            max_confidence_bps: MAX_CONFIDENCE_BPS,
+            trading_fee_bps: TRADING_FEE_BPS,
+            closing_fee_bps: CLOSING_FEE_BPS,
+            min_position_size: MIN_POSITION_SIZE,
+            min_leverage: MIN_LEVERAGE,
+            max_leverage: MAX_LEVERAGE,
+            range_position_duration: RANGE_POSITION_DURATION,
+            healthy_threshold: HEALTHY_THRESHOLD,
+            warning_threshold: WARNING_THRESHOLD,
+            liquidation_threshold: LIQUIDATION_THRESHOLD,
+            liquidation_bounty_bps: LIQUIDATION_BOUNTY_BPS,
+            liquidation_step_bps: LIQUIDATION_STEP_BPS,
+            liquidation_penalty_bps: LIQUIDATION_PENALTY_BPS,
+            max_liquidation_steps: MAX_LIQUIDATION_STEPS,
+            deposit_fee_tiers: [
+                DepositFeeTier { min_amount: 100_000_000_000, fee_bps: 10 }, // >= 100 SOL
+                DepositFeeTier { min_amount: 10_000_000_000, fee_bps: 20 },  // >= 10 SOL
+                DepositFeeTier { min_amount: 1_000_000_000, fee_bps: 30 },   // >= 1 SOL
+                DepositFeeTier { min_amount: 0, fee_bps: 50 },
+            ],
+            withdrawal_fee_bps: WITHDRAWAL_FEE_BPS,
+            fee_split: FeeSplit {
+                lp_bps: FEE_SPLIT_LP_BPS,
+                treasury_bps: FEE_SPLIT_TREASURY_BPS,
+                insurance_bps: FEE_SPLIT_INSURANCE_BPS,
+                referrer_bps: FEE_SPLIT_REFERRER_BPS,
+            },
+            early_withdrawal_penalty_half_bps: 500,
+            early_withdrawal_penalty_quarter_bps: 350,
+            early_withdrawal_penalty_min_bps: 200,
+        }
+    }
+}
+
+impl ConfigParams {
+    pub fn validate(&self) -> Result<()> {
+        // Oracle
+        require!(
+            self.maximum_age > 0 && self.maximum_age <= MAX_CONFIG_PRICE_AGE,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.max_confidence_bps > 0 && self.max_confidence_bps <= MAX_CONFIG_CONFIDENCE_BPS,
+            ErrorCode::InvalidConfig
+        );
+
+        // Fees
+        require!(self.trading_fee_bps <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+        require!(self.closing_fee_bps <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+
+        // Position limits
+        require!(self.min_position_size > 0, ErrorCode::InvalidConfig);
+        require!(
+            self.min_leverage >= 1
+                && self.min_leverage <= self.max_leverage
+                && self.max_leverage <= MAX_LEVERAGE,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.range_position_duration >= MIN_CONFIG_RANGE_DURATION
+                && self.range_position_duration <= MAX_CONFIG_RANGE_DURATION,
+            ErrorCode::InvalidConfig
+        );
+
+        // Thresholds must be strictly ordered and above 1.0x
+        require!(
+            self.liquidation_threshold >= 100
+                && self.liquidation_threshold < self.warning_threshold
+                && self.warning_threshold < self.healthy_threshold,
+            ErrorCode::InvalidConfig
+        );
+
+        // Liquidation
+        require!(self.liquidation_bounty_bps <= MAX_CONFIG_LIQUIDATION_BOUNTY_BPS, ErrorCode::InvalidConfig);
+        require!(
+            self.liquidation_step_bps > 0 && self.liquidation_step_bps <= 10000,
+            ErrorCode::InvalidConfig
+        );
+        require!(self.liquidation_penalty_bps <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+        require!(self.max_liquidation_steps > 0, ErrorCode::InvalidConfig);
+
+        // Deposit tiers: descending thresholds, bounded fees, catch-all last tier
+        for pair in self.deposit_fee_tiers.windows(2) {
+            require!(pair[0].min_amount > pair[1].min_amount, ErrorCode::InvalidConfig);
+        }
+        for tier in self.deposit_fee_tiers.iter() {
+            require!(tier.fee_bps <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+        }
+        require!(
+            self.deposit_fee_tiers[self.deposit_fee_tiers.len() - 1].min_amount == 0,
+            ErrorCode::InvalidConfig
+        );
+
+        require!(self.withdrawal_fee_bps <= MAX_CONFIG_FEE_BPS, ErrorCode::InvalidConfig);
+
+        // Fee split weights must cover the whole fee
+        let split = self.fee_split;
+        require!(
+            split.lp_bps as u32 + split.treasury_bps as u32 + split.insurance_bps as u32 + split.referrer_bps as u32 == 10000,
+            ErrorCode::InvalidConfig
+        );
+
+        // Early withdrawal penalties: bounded and non-increasing as the lock runs down
+        require!(
+            self.early_withdrawal_penalty_half_bps <= MAX_CONFIG_WITHDRAWAL_PENALTY_BPS
+                && self.early_withdrawal_penalty_quarter_bps <= self.early_withdrawal_penalty_half_bps
+                && self.early_withdrawal_penalty_min_bps <= self.early_withdrawal_penalty_quarter_bps,
+            ErrorCode::InvalidConfig
+        );
+
+        Ok(())
+    }
+
+    // Fee for deposits below every other tier
+    pub fn base_deposit_fee_bps(&self) -> u16 {
+        self.deposit_fee_tiers[self.deposit_fee_tiers.len() - 1].fee_bps
+    }
+
+    pub fn deposit_fee_bps(&self, amount: u64) -> u64 {
+        self.deposit_fee_tiers
+            .iter()
+            .find(|tier| amount >= tier.min_amount)
+            .map(|tier| tier.fee_bps as u64)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(ConfigParams::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_out_of_range_oracle_settings() {
+        let params = ConfigParams { maximum_age: 0, ..ConfigParams::default() };
+        assert!(params.validate().is_err());
+
+        let params = ConfigParams { maximum_age: MAX_CONFIG_PRICE_AGE + 1, ..ConfigParams::default() };
+        assert!(params.validate().is_err());
+
+        let params = ConfigParams { max_confidence_bps: MAX_CONFIG_CONFIDENCE_BPS + 1, ..ConfigParams::default() };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_inverted_leverage_bounds() {
+        let defaults = ConfigParams::default();
+
+        let params = ConfigParams { min_leverage: defaults.max_leverage + 1, ..defaults };
+        assert!(params.validate().is_err());
+
+        let params = ConfigParams { max_leverage: MAX_LEVERAGE + 1, ..defaults };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_unordered_health_thresholds() {
+        let defaults = ConfigParams::default();
+
+        let params = ConfigParams { warning_threshold: defaults.healthy_threshold, ..defaults };
+        assert!(params.validate().is_err());
+
+        let params = ConfigParams { liquidation_threshold: 99, ..defaults };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_fees_above_the_cap() {
+        let params = ConfigParams { trading_fee_bps: MAX_CONFIG_FEE_BPS + 1, ..ConfigParams::default() };
+        assert!(params.validate().is_err());
+
+        let params = ConfigParams {
+            liquidation_bounty_bps: MAX_CONFIG_LIQUIDATION_BOUNTY_BPS + 1,
+            ..ConfigParams::default()
+        };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn deposit_tiers_must_descend_to_a_catch_all() {
+        let defaults = ConfigParams::default();
+
+        let mut deposit_fee_tiers = defaults.deposit_fee_tiers;
+        deposit_fee_tiers.swap(0, 1);
+        let params = ConfigParams { deposit_fee_tiers, ..defaults };
+        assert!(params.validate().is_err());
+
+        let mut deposit_fee_tiers = defaults.deposit_fee_tiers;
+        deposit_fee_tiers[3].min_amount = 1;
+        let params = ConfigParams { deposit_fee_tiers, ..defaults };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn deposit_fee_uses_the_highest_tier_reached() {
+        let params = ConfigParams::default();
+
+        assert_eq!(params.deposit_fee_bps(100_000_000_000), 10);
+        assert_eq!(params.deposit_fee_bps(99_999_999_999), 20);
+        assert_eq!(params.deposit_fee_bps(1_000_000_000), 30);
+        assert_eq!(params.deposit_fee_bps(1), 50);
+        assert_eq!(params.base_deposit_fee_bps(), 50);
+    }
+
+    #[test]
+    fn early_withdrawal_penalties_must_not_grow_as_the_lock_runs_down() {
+        let defaults = ConfigParams::default();
+
+        let params = ConfigParams {
+            early_withdrawal_penalty_min_bps: defaults.early_withdrawal_penalty_quarter_bps + 1,
+            ..defaults
+        };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn fee_split_weights_must_cover_the_whole_fee() {
+        let defaults = ConfigParams::default();
+
+        let fee_split = FeeSplit { lp_bps: defaults.fee_split.lp_bps + 1, ..defaults.fee_split };
+        let params = ConfigParams { fee_split, ..defaults };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn split_pays_each_share_and_gives_the_remainder_to_the_treasury() {
+        let split = FeeSplit { lp_bps: 5000, treasury_bps: 2000, insurance_bps: 2000, referrer_bps: 1000 };
+
+        let amounts = split.split(1_001, true);
+
+        assert_eq!(amounts.lp, 500);
+        assert_eq!(amounts.insurance, 200);
+        assert_eq!(amounts.referrer, 100);
+        assert_eq!(amounts.treasury, 201);
+    }
+
+    #[test]
+    fn split_without_referrer_sends_the_referrer_share_to_the_treasury() {
+        let split = FeeSplit { lp_bps: 5000, treasury_bps: 2000, insurance_bps: 2000, referrer_bps: 1000 };
+
+        let amounts = split.split(1_000, false);
+
+        assert_eq!(amounts.lp, 500);
+        assert_eq!(amounts.insurance, 200);
+        assert_eq!(amounts.referrer, 0);
+        assert_eq!(amounts.treasury, 300);
+    }
+}
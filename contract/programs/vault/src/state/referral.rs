@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+// Referrer bound to a wallet at its first deposit, PDA ["referral", user]. Only this
+// referrer can receive the referrer share of the wallet's fees; `referrer` is
+// Pubkey::default() when the wallet was not referred.
+#[account]
+#[derive(InitSpace)]
+pub struct ReferralRecord {
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub is_initialized: bool,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl ReferralRecord {
+    pub fn has_referrer(&self) -> bool {
+        self.referrer != Pubkey::default()
+    }
+
+    // Fee paths after the first deposit pass the PDA unchecked, like the freeze record:
+    // a wallet without a record has no referrer. A `referrer` account is only paid if
+    // it is the one on record.
+    pub fn registered_referrer<'info>(
+        record_info: &AccountInfo<'info>,
+        referrer: Option<&SystemAccount<'info>>,
+    ) -> Result<Option<AccountInfo<'info>>> {
+        let Some(referrer) = referrer else {
+            return Ok(None);
+        };
+
+        require!(
+            record_info.owner == &crate::ID && !record_info.data_is_empty(),
+            ErrorCode::ReferrerNotRegistered
+        );
+        let data = record_info.try_borrow_data()?;
+        let record = ReferralRecord::try_deserialize(&mut &data[..])?;
+        require!(
+            record.has_referrer() && record.referrer == referrer.key(),
+            ErrorCode::ReferrerNotRegistered
+        );
+
+        Ok(Some(referrer.to_account_info()))
+    }
+}
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+// Root of the sanctions denylist, PDA ["sanctions_registry"]. `compliance_authority`
+// maintains the list without needing a program upgrade.
+#[account]
+#[derive(InitSpace)]
+pub struct SanctionsRegistry {
+    pub admin: Pubkey,
+    pub compliance_authority: Pubkey,
+    pub total_entries: u32,
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+// One page of the denylist, PDA ["sanctions_page", bucket]. An address always lives
+// in the page for its bucket, so callers cannot route the check to another page.
+#[account]
+#[derive(InitSpace)]
+pub struct SanctionsPage {
+    pub bucket: u8,
+    #[max_len(64)]
+    pub entries: Vec<Pubkey>, // Kept sorted for binary search
+    pub bump: u8,
+}
+
+impl SanctionsPage {
+    pub const MAX_ENTRIES: usize = 64;
+
+    pub fn bucket_for(address: &Pubkey) -> u8 {
+        address.to_bytes()[0]
+    }
+
+    pub fn contains(&self, address: &Pubkey) -> bool {
+        self.entries.binary_search(address).is_ok()
+    }
+
+    pub fn insert(&mut self, address: Pubkey) -> Result<()> {
+        match self.entries.binary_search(&address) {
+            Ok(_) => err!(ErrorCode::AddressAlreadySanctioned),
+            Err(index) => {
+                require!(self.entries.len() < Self::MAX_ENTRIES, ErrorCode::SanctionsPageFull);
+                self.entries.insert(index, address);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn remove(&mut self, address: &Pubkey) -> Result<()> {
+        let index = self.entries
+            .binary_search(address)
+            .map_err(|_| error!(ErrorCode::AddressNotSanctioned))?;
+        self.entries.remove(index);
+        Ok(())
+    }
+
+    // User instructions pass the page unchecked because most buckets are empty;
+    // a missing page means the address is not listed.
+    pub fn ensure_not_sanctioned(page_info: &AccountInfo, address: &Pubkey) -> Result<()> {
+        if page_info.owner != &crate::ID || page_info.data_is_empty() {
+            return Ok(());
+        }
+
+        let data = page_info.try_borrow_data()?;
+        let page = SanctionsPage::try_deserialize(&mut &data[..])?;
+        require!(!page.contains(address), ErrorCode::OFACViolation);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page() -> SanctionsPage {
+        SanctionsPage { bucket: 0, entries: Vec::new(), bump: 0 }
+    }
+
+    fn address(seed: u8) -> Pubkey {
+        Pubkey::new_from_array([seed; 32])
+    }
+
+    #[test]
+    fn insert_keeps_entries_sorted() {
+        let mut page = page();
+
+        page.insert(address(3)).unwrap();
+        page.insert(address(1)).unwrap();
+        page.insert(address(2)).unwrap();
+
+        assert_eq!(page.entries, vec![address(1), address(2), address(3)]);
+        assert!(page.contains(&address(2)));
+        assert!(!page.contains(&address(4)));
+    }
+
+    #[test]
+    fn insert_rejects_duplicates() {
+        let mut page = page();
+        page.insert(address(1)).unwrap();
+
+        assert!(page.insert(address(1)).is_err());
+        assert_eq!(page.entries.len(), 1);
+    }
+
+    #[test]
+    fn insert_rejects_a_full_page() {
+        let mut page = page();
+        for seed in 0..SanctionsPage::MAX_ENTRIES as u8 {
+            page.insert(address(seed)).unwrap();
+        }
+
+        assert!(page.insert(address(SanctionsPage::MAX_ENTRIES as u8)).is_err());
+    }
+
+    #[test]
+    fn remove_delists_an_address() {
+        let mut page = page();
+        page.insert(address(1)).unwrap();
+        page.insert(address(2)).unwrap();
+
+        page.remove(&address(1)).unwrap();
+
+        assert_eq!(page.entries, vec![address(2)]);
+        assert!(page.remove(&address(1)).is_err());
+    }
+
+    #[test]
+    fn an_address_always_maps_to_the_bucket_of_its_first_byte() {
+        assert_eq!(SanctionsPage::bucket_for(&address(7)), 7);
+    }
+}
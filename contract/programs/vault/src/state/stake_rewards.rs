@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+use crate::state::RewardPool;
+
+// Per-wallet staking position, PDA ["stake_rewards", user]. Pool shares minted by a
+// SOL deposit are staked automatically; `reward_debt` is the RewardPool accumulator
+// checkpoint for `total_staked`.
+#[account]
+#[derive(InitSpace)]
+pub struct StakeRewards {
+    pub user: Pubkey,
+    pub total_staked: u64,
+    pub unstaked_shares: u64,
+    pub pending_rewards: u64,
+    pub reward_debt: u128,
+    pub total_claimed: u64,
+    pub last_reward_time: i64,
+    pub last_stake_time: i64,
+    pub is_initialized: bool,
+}
+
+impl StakeRewards {
+    // Moves everything earned since the last checkpoint into `pending_rewards`.
+    // The reward pool must have been accrued to the current time first.
+    pub fn settle_pending(&mut self, reward_pool: &RewardPool, current_time: i64) -> Result<u64> {
+        let accumulated = reward_pool.accumulated_for(self.total_staked)?;
+        let earned = accumulated.saturating_sub(self.reward_debt) as u64;
+
+        self.pending_rewards = self.pending_rewards
+            .checked_add(earned)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.reward_debt = accumulated;
+        self.last_reward_time = current_time;
+
+        Ok(earned)
+    }
+
+    // Re-bases the debt after `total_staked` changed
+    pub fn checkpoint(&mut self, reward_pool: &RewardPool) -> Result<()> {
+        self.reward_debt = reward_pool.accumulated_for(self.total_staked)?;
+        Ok(())
+    }
+
+    // Settles rewards on the current stake, then stops `amount` shares from earning.
+    // Returns the rewards settled.
+    pub fn unstake(&mut self, reward_pool: &mut RewardPool, amount: u64, current_time: i64) -> Result<u64> {
+        require!(
+            self.total_staked >= amount,
+            ErrorCode::InsufficientStake
+        );
+
+        reward_pool.accrue(current_time)?;
+        let earned = self.settle_pending(reward_pool, current_time)?;
+
+        self.total_staked -= amount;
+        self.unstaked_shares = self.unstaked_shares
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        reward_pool.total_staked = reward_pool.total_staked
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.checkpoint(reward_pool)?;
+
+        Ok(earned)
+    }
+
+    // Drops pool shares that left the pool, unstaking them first if needed. Unstaked
+    // shares go first. Rewards earned up to now stay in `pending_rewards`.
+    pub fn release_shares(&mut self, reward_pool: &mut RewardPool, shares: u64, current_time: i64) -> Result<u64> {
+        let shares = shares.min(self.total_staked.saturating_add(self.unstaked_shares));
+        let from_staked = shares.saturating_sub(self.unstaked_shares);
+
+        let earned = self.unstake(reward_pool, from_staked, current_time)?;
+        self.unstaked_shares -= shares;
+
+        Ok(earned)
+    }
+}
@@ -1,12 +1,21 @@
 use anchor_lang::prelude::*;
 
+// Counterparty pool for positions, PDA ["trading_pool"]. Lamports are held in the
+// system-owned PDA ["trading_pool_vault", trading_pool].
 #[account]
 #[derive(InitSpace)]
 pub struct TradingPool {
-    pub authority: Pubkey,         
-    pub total_active_amount: u64,  
-    pub total_pool_amount: u64,    
-    pub bump: u8,                
-    pub vault_bump: u8,            
+    pub authority: Pubkey,
+    pub total_active_amount: u64,
+    pub total_pool_amount: u64,
+    pub total_fees_collected: u64,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub last_updated: i64,
+    pub bump: u8,
+    pub vault_bump: u8,
 }
 
+impl TradingPool {
+    pub const MIN_INITIAL_DEPOSIT: u64 = 1_000_000; // 0.001 SOL minimum
+}
@@ -1,13 +1,24 @@
 use anchor_lang::prelude::*;
 
+// Per-wallet vault, PDA ["vault_state", owner]. Lamports are held in the system-owned
+// PDA ["vault", vault_state].
 #[account]
 #[derive(InitSpace)]
 pub struct VaultState {
     pub authority: Pubkey,
+    pub owner: Pubkey,
     pub vault_bump: u8,
     pub state_bump: u8,
+    pub total_deposits: u64,
+    pub total_withdrawals: u64,
+    pub active_positions: u32,
+    pub is_initialized: bool,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub last_updated: i64,
 }
 
 impl VaultState {
-    pub const MIN_ORDER_AMOUNT: u64 = 100_000_000; 
+    pub const MIN_ORDER_AMOUNT: u64 = 100_000; // 0.0001 SOL
+    pub const MAX_ORDER_AMOUNT: u64 = 1_000_000_000_000; // 1000 SOL
 }
@@ -1,27 +1,43 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { PublicKey, LAMPORTS_PER_SOL, Keypair } from "@solana/web3.js";
+import { PublicKey, LAMPORTS_PER_SOL, SystemProgram } from "@solana/web3.js";
+import { HermesClient } from "@pythnetwork/hermes-client";
+import { PythSolanaReceiver } from "@pythnetwork/pyth-solana-receiver";
 import { assert } from "chai";
 import { Vault } from "../target/types/vault";
 
+// Pyth BTC/USD feed; its prices use 8 decimals, like the program's PRICE_DECIMALS
+const BTC_USD_FEED_ID = "0xe62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43";
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
+
 describe("Vault Position Tests", () => {
   // Configure the client to use the local cluster
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
 
   const program = anchor.workspace.Vault as Program<Vault>;
-  
+
+  // The protocol admin is the provider wallet, which is also the upgrade authority
+  const admin = provider.wallet as anchor.Wallet;
+  const hermes = new HermesClient("https://hermes.pyth.network/", {});
+  const pythSolanaReceiver = new PythSolanaReceiver({
+    connection: provider.connection,
+    wallet: admin,
+  });
+
   // Test parameters
   const amount = LAMPORTS_PER_SOL * 0.1; // 0.1 SOL
-  const lowerBound = 60000; // $60,000
-  const upperBound = 70000; // $70,000
+  const stake = LAMPORTS_PER_SOL * 0.02; // Per position, out of the deposit
   const orderId = 12345;
   const backendOrderId = 67890;
-  
+  const rejectedOrderId = 24680;
+  const poolId = 0;
+  // Market symbols are zero padded to 8 bytes
+  const marketSymbol = Buffer.concat([Buffer.from("BTC"), Buffer.alloc(5)]);
+
   // Test accounts
   let user = anchor.web3.Keypair.generate();
-  let admin = anchor.web3.Keypair.generate(); // Admin/backend wallet
-  
+
   // PDAs
   let vaultState: PublicKey;
   let vaultStateBump: number;
@@ -31,14 +47,90 @@ describe("Vault Position Tests", () => {
   let positionBump: number;
   let backendCreatedPosition: PublicKey;
   let backendPositionBump: number;
-  
-  // Mock price update account - since we can't integrate with actual Pyth for tests
-  let mockPriceAccount: Keypair;
-  
+  let market: PublicKey;
+  let priceHistory: PublicKey;
+  let protocolConfig: PublicKey;
+
+  // Latest BTC/USD update from Hermes, posted on chain alongside each position
+  let priceUpdateData: string[];
+  let btcPrice: anchor.BN;
+
+  const positionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("position"),
+        user.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8)
+      ],
+      program.programId
+    );
+
+  // Band of `bps` basis points around the fetched price, in the price's 8 decimals
+  const bandAround = (bps: number) => {
+    const offset = btcPrice.muln(bps).divn(10000);
+    return [btcPrice.sub(offset), btcPrice.add(offset)];
+  };
+
+  // Posts the price update and creates the position in the same batch, then
+  // closes the update account
+  const createRangePosition = async (
+    positionType: any,
+    lowerBound: anchor.BN,
+    upperBound: anchor.BN,
+    id: number
+  ) => {
+    const txBuilder = pythSolanaReceiver.newTransactionBuilder({ closeUpdateAccounts: true });
+    await txBuilder.addPostPriceUpdates(priceUpdateData);
+    await txBuilder.addPriceConsumerInstructions(async (getPriceUpdateAccount) => [
+      {
+        instruction: await program.methods
+          .createPosition(
+            positionType,
+            lowerBound,
+            upperBound,
+            new anchor.BN(id),
+            new anchor.BN(stake)
+          )
+          .accountsPartial({
+            user: user.publicKey,
+            position: positionPda(id)[0],
+            userVault: vault,
+            userVaultState: vaultState,
+            market: market,
+            priceHistory: priceHistory,
+            priceUpdate: getPriceUpdateAccount(BTC_USD_FEED_ID),
+            // Only read for markets with a secondary price source
+            instructionsSysvar: null,
+            collateralMint: null,
+            mint: null,
+            collateralAuthority: null,
+            vaultTokenAccount: null,
+            poolTokenVault: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .instruction(),
+        signers: [user],
+      },
+    ]);
+    await pythSolanaReceiver.provider.sendAll(
+      await txBuilder.buildVersionedTransactions({ computeUnitPriceMicroLamports: 50000 }),
+      { skipPreflight: false }
+    );
+  };
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+    } catch (e) {
+      const logs = (e.logs ?? e.transactionLogs ?? []).join("\n");
+      assert.include(`${e}\n${logs}`, code);
+      return;
+    }
+    assert.fail(`Expected ${code}`);
+  };
+
   before(async () => {
-    // Create a mock price update account
-    mockPriceAccount = anchor.web3.Keypair.generate();
-    
     // Initialize vault state PDA
     const [vaultStateAddress, vaultStateAddressBump] = PublicKey.findProgramAddressSync(
       [Buffer.from("vault_state"), user.publicKey.toBuffer()],
@@ -46,7 +138,7 @@ describe("Vault Position Tests", () => {
     );
     vaultState = vaultStateAddress;
     vaultStateBump = vaultStateAddressBump;
-    
+
     // Initialize vault PDA
     const [vaultAddress, vaultAddressBump] = PublicKey.findProgramAddressSync(
       [Buffer.from("vault"), vaultState.toBuffer()],
@@ -54,58 +146,158 @@ describe("Vault Position Tests", () => {
     );
     vault = vaultAddress;
     vaultBump = vaultAddressBump;
-    
+
     // Initialize position PDAs
-    const [positionAddress, positionAddressBump] = PublicKey.findProgramAddressSync(
-      [
-        Buffer.from("position"),
-        user.publicKey.toBuffer(),
-        new anchor.BN(orderId).toArrayLike(Buffer, "le", 8)
-      ],
+    [position, positionBump] = positionPda(orderId);
+    [backendCreatedPosition, backendPositionBump] = positionPda(backendOrderId);
+
+    // Positions are priced against a market and record its prices
+    [market] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), marketSymbol],
       program.programId
     );
-    position = positionAddress;
-    positionBump = positionAddressBump;
-    
-    // Initialize backend-created position PDA
-    const [backendPositionAddress, backendPositionAddressBump] = PublicKey.findProgramAddressSync(
-      [
-        Buffer.from("position"),
-        user.publicKey.toBuffer(),
-        new anchor.BN(backendOrderId).toArrayLike(Buffer, "le", 8)
-      ],
+    [priceHistory] = PublicKey.findProgramAddressSync(
+      [Buffer.from("price_history"), market.toBuffer()],
+      program.programId
+    );
+    [protocolConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_config")],
+      program.programId
+    );
+
+    // Protocol accounts are shared by every run against the cluster, so only
+    // create the ones that are missing
+    if (!(await program.account.protocolConfig.fetchNullable(protocolConfig))) {
+      const [programData] = PublicKey.findProgramAddressSync(
+        [program.programId.toBuffer()],
+        BPF_LOADER_UPGRADEABLE_ID
+      );
+      await program.methods
+        .initConfig()
+        .accountsPartial({ admin: admin.publicKey, program: program.programId, programData })
+        .rpc();
+    }
+
+    const [tradingPool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("trading_pool")],
       program.programId
     );
-    backendCreatedPosition = backendPositionAddress;
-    backendPositionBump = backendPositionAddressBump;
+    if (!(await program.account.tradingPool.fetchNullable(tradingPool))) {
+      await program.methods.initTradingPool(null).accounts({ admin: admin.publicKey }).rpc();
+    }
 
-    // Fund the user and admin accounts
+    const [feeLedger] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_ledger")],
+      program.programId
+    );
+    if (!(await program.account.feeLedger.fetchNullable(feeLedger))) {
+      await program.methods.initFeeLedger(admin.publicKey).accounts({ admin: admin.publicKey }).rpc();
+    }
+
+    const [insuranceFund] = PublicKey.findProgramAddressSync(
+      [Buffer.from("insurance_fund")],
+      program.programId
+    );
+    if (!(await program.account.insuranceFund.fetchNullable(insuranceFund))) {
+      await program.methods.initInsuranceFund().accounts({ admin: admin.publicKey }).rpc();
+      // Deposit fee shares are too small to open the insurance vault on their own
+      await program.methods
+        .depositInsuranceFund(new anchor.BN(0.01 * LAMPORTS_PER_SOL))
+        .accounts({ admin: admin.publicKey })
+        .rpc();
+    }
+
+    // Same for the treasury, which only ever receives fee shares
+    const [protocolTreasury] = PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_treasury")],
+      program.programId
+    );
+    if ((await provider.connection.getBalance(protocolTreasury)) === 0) {
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: admin.publicKey,
+            toPubkey: protocolTreasury,
+            lamports: 0.01 * LAMPORTS_PER_SOL,
+          })
+        )
+      );
+    }
+
+    const [rewardPool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward_pool")],
+      program.programId
+    );
+    if (!(await program.account.rewardPool.fetchNullable(rewardPool))) {
+      const now = Math.floor(Date.now() / 1000);
+      await program.methods
+        .initRewardPool(new anchor.BN(0), new anchor.BN(now), new anchor.BN(now + 365 * 86400))
+        .accounts({ admin: admin.publicKey })
+        .rpc();
+    }
+
+    if (!(await program.account.market.fetchNullable(market))) {
+      await program.methods
+        .createMarket([...marketSymbol], {
+          priceFeedId: [...Buffer.from(BTC_USD_FEED_ID.slice(2), "hex")],
+          isActive: true,
+          maxLeverage: 10,
+          minPositionSize: new anchor.BN(1000),
+          maxPositionSize: new anchor.BN(0),
+          tradingFeeBps: null,
+          closingFeeBps: null,
+          markPriceMode: { spot: {} },
+          secondarySigner: PublicKey.default,
+          maxDeviationBps: 0,
+          twapWindowSecs: 0,
+          twapMinObservations: 0,
+          twapMinCoverageBps: 0,
+          settleAtTwap: false,
+          breakerThresholdBps: 0,
+          breakerWindowSecs: 0,
+        })
+        .accountsPartial({ admin: admin.publicKey, market })
+        .rpc();
+    }
+    if (!(await program.account.priceHistory.fetchNullable(priceHistory))) {
+      await program.methods
+        .initPriceHistory()
+        .accountsPartial({ admin: admin.publicKey, market, priceHistory })
+        .rpc();
+    }
+
+    // Fund the user account
     await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
-    await provider.connection.requestAirdrop(admin.publicKey, 10 * LAMPORTS_PER_SOL);
-    
+
     // Wait a moment for airdrop to be confirmed
     await new Promise(resolve => setTimeout(resolve, 2000));
   });
 
+  beforeEach(async () => {
+    const updates = await hermes.getLatestPriceUpdates([BTC_USD_FEED_ID], { encoding: "base64" });
+    priceUpdateData = updates.binary.data;
+    btcPrice = new anchor.BN(updates.parsed[0].price.price);
+  });
+
   it("Initializes the vault", async () => {
     try {
       await program.methods
-        .initialize()
-        .accounts({
+        .initialize(null)
+        .accountsPartial({
           user: user.publicKey,
           vaultState: vaultState,
           vault: vault,
-          systemProgram: anchor.web3.SystemProgram.programId,
+          systemProgram: SystemProgram.programId,
         })
         .signers([user])
         .rpc();
-      
+
       // Verify vault state was created
       const vaultStateAccount = await program.account.vaultState.fetch(vaultState);
       assert.equal(vaultStateAccount.authority.toString(), user.publicKey.toString());
       assert.equal(vaultStateAccount.stateBump, vaultStateBump);
       assert.equal(vaultStateAccount.vaultBump, vaultBump);
-      
+
       console.log("Vault initialized successfully");
     } catch (e) {
       console.error("Error initializing vault:", e);
@@ -118,35 +310,44 @@ describe("Vault Position Tests", () => {
       // Get balances before
       const userBalanceBefore = await provider.connection.getBalance(user.publicKey);
       const vaultBalanceBefore = await provider.connection.getBalance(vault);
-      
+
       await program.methods
-        .deposit(new anchor.BN(amount), new anchor.BN(backendOrderId))
-        .accounts({
+        .deposit(new anchor.BN(amount), new anchor.BN(poolId))
+        .accountsPartial({
           user: user.publicKey,
           vault: vault,
           vaultState: vaultState,
-          systemProgram: anchor.web3.SystemProgram.programId,
+          // SOL deposit without a referrer: the token accounts stay unset
+          referrer: null,
+          collateralMint: null,
+          mint: null,
+          collateralAuthority: null,
+          userTokenAccount: null,
+          vaultTokenAccount: null,
+          poolTokenVault: null,
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
         })
         .signers([user])
         .rpc();
-        
+
       // Verify deposit worked
       const userBalanceAfterDeposit = await provider.connection.getBalance(user.publicKey);
       const vaultBalanceAfterDeposit = await provider.connection.getBalance(vault);
-      
+
       // Account for transaction fees (roughly)
       const userBalanceDiff = userBalanceBefore - userBalanceAfterDeposit - amount;
       assert.isTrue(
         userBalanceDiff >= 0 && userBalanceDiff < 2000000,
         `User balance difference (${userBalanceDiff}) should be positive and less than 2000000 lamports`
       );
-      
+
       assert.equal(
         vaultBalanceAfterDeposit - vaultBalanceBefore,
         amount,
         "Vault balance should increase by deposit amount"
       );
-      
+
       console.log("User deposited funds successfully");
     } catch (e) {
       console.error("Error during deposit:", e);
@@ -154,65 +355,98 @@ describe("Vault Position Tests", () => {
     }
   });
 
-  // Skip the test for now due to pyth integration complexity
-  it.skip("Backend creates position with already deposited funds", async () => {
-    try {
-      // Get balances before
-      const adminBalanceBefore = await provider.connection.getBalance(admin.publicKey);
-      const vaultBalanceBefore = await provider.connection.getBalance(vault);
-      
-      await program.methods
-        .createPosition(
-          { breakout: {} }, // Use different position type for variety
-          new anchor.BN(lowerBound),
-          new anchor.BN(upperBound),
-          new anchor.BN(backendOrderId),
-          new anchor.BN(amount)
-        )
-        .accounts({
-          user: user.publicKey, // User account (not a signer)
-          admin: admin.publicKey, // Admin is the signer
-          position: backendCreatedPosition,
-          vault: vault,
-          vaultState: vaultState,
-          priceUpdate: mockPriceAccount.publicKey,
-          systemProgram: anchor.web3.SystemProgram.programId,
-        })
-        .signers([admin])
-        .rpc();
-        
-      // Verify position was created
-      const positionAccount = await program.account.positionState.fetch(backendCreatedPosition);
-      assert.equal(positionAccount.user.toString(), user.publicKey.toString());
-      assert.deepEqual(positionAccount.positionType, { breakout: {} });
-      assert.equal(positionAccount.lowerBound.toString(), lowerBound.toString());
-      assert.equal(positionAccount.upperBound.toString(), backendOrderId.toString());
-      assert.equal(positionAccount.amount.toString(), amount.toString());
-      assert.equal(positionAccount.status.active !== undefined, true);
-      assert.equal(positionAccount.bump, backendPositionBump);
-      
-      // The admin should only pay for rent, not for the position amount
-      const adminBalanceAfter = await provider.connection.getBalance(admin.publicKey);
-      const adminBalanceDiff = adminBalanceBefore - adminBalanceAfter;
-      
-      // Admin should only pay transaction fees and rent, not transfer funds
-      assert.isTrue(
-        adminBalanceDiff > 0 && adminBalanceDiff < 2000000,
-        `Admin balance difference (${adminBalanceDiff}) should only reflect fees and rent`
-      );
-      
-      // Vault balance should not change from backend-initiated position creation
-      const vaultBalanceAfterPosition = await provider.connection.getBalance(vault);
-      assert.equal(
-        vaultBalanceAfterPosition,
-        vaultBalanceBefore,
-        "Vault balance should not change when backend creates position"
-      );
-      
-      console.log("Backend successfully created position with pre-deposited funds");
-    } catch (e) {
-      console.error("Error creating position from backend:", e);
-      throw e;
-    }
+  it("Creates a STAY_IN position that expires after the configured duration", async () => {
+    const [lowerBound, upperBound] = bandAround(500);
+    await createRangePosition({ stayIn: {} }, lowerBound, upperBound, orderId);
+
+    const positionAccount = await program.account.positionState.fetch(position);
+    assert.equal(positionAccount.user.toString(), user.publicKey.toString());
+    assert.equal(positionAccount.orderId.toNumber(), orderId);
+    assert.deepEqual(positionAccount.positionType, { stayIn: {} });
+    assert.equal(positionAccount.lowerBound.toString(), lowerBound.toString());
+    assert.equal(positionAccount.upperBound.toString(), upperBound.toString());
+    assert.equal(positionAccount.market.toString(), market.toString());
+    assert.equal(positionAccount.collateralAmount.toString(), stake.toString());
+    assert.isTrue(positionAccount.entryPrice.gt(lowerBound) && positionAccount.entryPrice.lt(upperBound));
+    assert.equal(positionAccount.status.active !== undefined, true);
+    assert.equal(positionAccount.bump, positionBump);
+
+    // The lifetime comes from the protocol config, not a compiled constant
+    const config = await program.account.protocolConfig.fetch(protocolConfig);
+    assert.equal(
+      positionAccount.expiresAt.sub(positionAccount.createdAt).toString(),
+      config.params.rangePositionDuration.toString()
+    );
+  });
+
+  it("Backend creates a BREAKOUT position with already deposited funds", async () => {
+    // Get balances before
+    const userBalanceBefore = await provider.connection.getBalance(user.publicKey);
+    const vaultBalanceBefore = await provider.connection.getBalance(vault);
+
+    const [lowerBound, upperBound] = bandAround(300);
+    await createRangePosition({ breakout: {} }, lowerBound, upperBound, backendOrderId);
+
+    // Verify position was created
+    const positionAccount = await program.account.positionState.fetch(backendCreatedPosition);
+    assert.equal(positionAccount.user.toString(), user.publicKey.toString());
+    assert.deepEqual(positionAccount.positionType, { breakout: {} });
+    assert.equal(positionAccount.lowerBound.toString(), lowerBound.toString());
+    assert.equal(positionAccount.upperBound.toString(), upperBound.toString());
+    assert.equal(positionAccount.market.toString(), market.toString());
+    assert.equal(positionAccount.collateralAmount.toString(), stake.toString());
+    assert.equal(positionAccount.status.active !== undefined, true);
+    assert.equal(positionAccount.bump, backendPositionBump);
+
+    // The user only pays rent for the position and its vault from the wallet;
+    // the stake comes from the vault
+    const userBalanceAfter = await provider.connection.getBalance(user.publicKey);
+    const userBalanceDiff = userBalanceBefore - userBalanceAfter;
+    assert.isTrue(
+      userBalanceDiff > 0 && userBalanceDiff < 10000000,
+      `User balance difference (${userBalanceDiff}) should only reflect rent`
+    );
+
+    // The stake (plus any trading fee) moves out of the user's vault
+    const vaultBalanceAfterPosition = await provider.connection.getBalance(vault);
+    assert.isTrue(
+      vaultBalanceBefore - vaultBalanceAfterPosition >= stake,
+      "Vault balance should drop by at least the position amount"
+    );
+  });
+
+  it("Rejects a zero lower bound", async () => {
+    const [, upperBound] = bandAround(500);
+    await expectError(
+      createRangePosition({ stayIn: {} }, new anchor.BN(0), upperBound, rejectedOrderId),
+      "InvalidRange"
+    );
+  });
+
+  it("Rejects a lower bound that is not below the upper bound", async () => {
+    const [lowerBound, upperBound] = bandAround(500);
+    await expectError(
+      createRangePosition({ stayIn: {} }, upperBound, lowerBound, rejectedOrderId),
+      "InvalidRange"
+    );
+    await expectError(
+      createRangePosition({ breakout: {} }, lowerBound, lowerBound, rejectedOrderId),
+      "InvalidRange"
+    );
+  });
+
+  it("Rejects a band that does not contain the entry price", async () => {
+    // Entirely above the price
+    const [, upperBound] = bandAround(500);
+    await expectError(
+      createRangePosition({ stayIn: {} }, upperBound, upperBound.muln(2), rejectedOrderId),
+      "InvalidRange"
+    );
+
+    // Touching the price: the bid has to sit strictly above the lower bound
+    await expectError(
+      createRangePosition({ breakout: {} }, btcPrice, upperBound, rejectedOrderId),
+      "InvalidRange"
+    );
   });
-});
\ No newline at end of file
+});
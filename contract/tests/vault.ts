@@ -26,6 +26,19 @@ describe("vault", () => {
 
   // Constants
   const MIN_AMOUNT = 0.1 * LAMPORTS_PER_SOL; // 0.1 SOL
+  const POOL_ID = new anchor.BN(0);
+
+  // SOL deposits and withdrawals without a referrer leave the token accounts unset
+  const solAccounts = {
+    referrer: null,
+    collateralMint: null,
+    mint: null,
+    collateralAuthority: null,
+    userTokenAccount: null,
+    vaultTokenAccount: null,
+    poolTokenVault: null,
+    tokenProgram: null,
+  };
 
   it("Fund the user account", async () => {
     // Airdrop SOL to the user
@@ -61,8 +74,8 @@ describe("vault", () => {
   it("Initialize the vault", async () => {
     // Initialize the vault
     await program.methods
-      .initialize()
-      .accounts({
+      .initialize(null)
+      .accountsPartial({
         user: user.publicKey,
         vaultState: vaultStatePda,
         vault: vaultPda,
@@ -83,18 +96,18 @@ describe("vault", () => {
     
     // Define deposit amount (0.1 SOL)
     const depositAmount = MIN_AMOUNT;
-    const orderId = new anchor.BN(1);
     
     // Execute deposit
     await program.methods
       .deposit(
         new anchor.BN(depositAmount),
-        orderId
+        POOL_ID
       )
-      .accounts({
+      .accountsPartial({
         user: user.publicKey,
         vault: vaultPda,
         vaultState: vaultStatePda,
+        ...solAccounts,
         systemProgram: SystemProgram.programId,
       })
       .signers([user])
@@ -114,19 +127,19 @@ describe("vault", () => {
   it("Deposit < 0.1 SOL fails", async () => {
     // Define small deposit amount (0.05 SOL)
     const smallDepositAmount = MIN_AMOUNT / 2;
-    const orderId = new anchor.BN(2);
     
     try {
       // Attempt to deposit a small amount
       await program.methods
         .deposit(
           new anchor.BN(smallDepositAmount),
-          orderId
+          POOL_ID
         )
-        .accounts({
+        .accountsPartial({
           user: user.publicKey,
           vault: vaultPda,
           vaultState: vaultStatePda,
+          ...solAccounts,
           systemProgram: SystemProgram.programId,
         })
         .signers([user])
@@ -147,18 +160,18 @@ describe("vault", () => {
     
     // Define withdrawal amount (exactly what we deposited: 0.1 SOL)
     const withdrawAmount = MIN_AMOUNT;
-    const orderId = new anchor.BN(3);
     
     // Execute withdrawal
     await program.methods
       .withdraw(
         new anchor.BN(withdrawAmount),
-        orderId
+        false
       )
-      .accounts({
+      .accountsPartial({
         user: user.publicKey,
         vault: vaultPda,
         vaultState: vaultStatePda,
+        ...solAccounts,
         systemProgram: SystemProgram.programId,
       })
       .signers([user])
@@ -181,12 +194,13 @@ describe("vault", () => {
     await program.methods
       .deposit(
         new anchor.BN(smallDeposit),
-        new anchor.BN(4)
+        POOL_ID
       )
-      .accounts({
+      .accountsPartial({
         user: user.publicKey,
         vault: vaultPda,
         vaultState: vaultStatePda,
+        ...solAccounts,
         systemProgram: SystemProgram.programId,
       })
       .signers([user])
@@ -194,19 +208,19 @@ describe("vault", () => {
       
     // Define excessive withdrawal amount (more than what's in the vault)
     const excessiveWithdrawAmount = 2 * MIN_AMOUNT; // 0.2 SOL
-    const orderId = new anchor.BN(5);
     
     try {
       // Attempt to withdraw an excessive amount
       await program.methods
         .withdraw(
           new anchor.BN(excessiveWithdrawAmount),
-          orderId
+          false
         )
-        .accounts({
+        .accountsPartial({
           user: user.publicKey,
           vault: vaultPda,
           vaultState: vaultStatePda,
+          ...solAccounts,
           systemProgram: SystemProgram.programId,
         })
         .signers([user])
@@ -227,12 +241,13 @@ describe("vault", () => {
       await program.methods
         .withdraw(
           new anchor.BN(balance),
-          new anchor.BN(6)
+          true
         )
-        .accounts({
+        .accountsPartial({
           user: user.publicKey,
           vault: vaultPda,
           vaultState: vaultStatePda,
+          ...solAccounts,
           systemProgram: SystemProgram.programId,
         })
         .signers([user])
@@ -242,7 +257,7 @@ describe("vault", () => {
     // Now close the vault
     await program.methods
       .close()
-      .accounts({
+      .accountsPartial({
         user: user.publicKey,
         vault: vaultPda,
         vaultState: vaultStatePda,